@@ -0,0 +1,509 @@
+use std::error;
+use std::fmt;
+use std::fs::{self, File, Permissions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::IoResultExt;
+use crate::{Builder, NamedTempFile, TempPath};
+
+/// Atomically copy the contents of `src` to `dst`.
+///
+/// The contents are first copied into a temporary file in `dst`'s directory which is then
+/// atomically renamed over `dst`. Readers of `dst` will therefore either see the previous file or
+/// the complete copy, never a partially written file. The permissions of `src` are copied along
+/// with its contents and both the new file and (on Unix) its parent directory are synchronized to
+/// disk before this function returns.
+///
+/// The copy itself is performed by [`std::io::copy`] which uses kernel-side copy mechanisms such as
+/// `copy_file_range` or `sendfile` where available.
+///
+/// On success, the total number of bytes copied is returned.
+///
+/// # Errors
+///
+/// If `src` can't be read, the temporary file can't be created in `dst`'s directory, or the
+/// temporary file can't be renamed to `dst`, `Err` is returned. The temporary file is deleted on
+/// failure.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use tempfile::{atomic_copy, tempdir};
+///
+/// let dir = tempdir()?;
+/// let src = dir.path().join("src.txt");
+/// let dst = dir.path().join("dst.txt");
+/// fs::write(&src, "Brian was here. Briefly.")?;
+///
+/// atomic_copy(&src, &dst)?;
+/// assert_eq!(fs::read_to_string(&dst)?, "Brian was here. Briefly.");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn atomic_copy<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<u64> {
+    let src = src.as_ref();
+    let dst = dst.as_ref();
+
+    let mut reader = File::open(src).with_err_path(|| src)?;
+    let permissions = reader.metadata().with_err_path(|| src)?.permissions();
+
+    let dir = parent_dir(dst)?;
+    let mut tmp = Builder::new().tempfile_in(dir)?;
+    let copied = io::copy(&mut reader, tmp.as_file_mut()).with_err_path(|| tmp.path())?;
+
+    // Windows resets the file attributes when persisting so we apply the permissions afterwards.
+    #[cfg(not(windows))]
+    tmp.as_file()
+        .set_permissions(permissions)
+        .with_err_path(|| tmp.path())?;
+    tmp.as_file().sync_all().with_err_path(|| tmp.path())?;
+
+    tmp.persist(dst)?;
+    #[cfg(windows)]
+    fs::set_permissions(dst, permissions).with_err_path(|| dst)?;
+    sync_dir(dir)?;
+    Ok(copied)
+}
+
+/// Atomically replace the contents of `path` with `contents`, creating it if it doesn't exist.
+///
+/// This is an atomic version of [`std::fs::write`]: `contents` are first written to a temporary
+/// file in `path`'s directory which is then atomically renamed over `path`. Readers of `path` will
+/// therefore either see the previous contents or the new contents, never a partially written file.
+/// Both the new file and (on Unix) its parent directory are synchronized to disk before this
+/// function returns.
+///
+/// If `path` already exists, its permissions are preserved. Otherwise, the new file is created
+/// with the same (restrictive) permissions as any other temporary file.
+///
+/// # Errors
+///
+/// If the temporary file can't be created in `path`'s directory, written, or renamed to `path`,
+/// `Err` is returned. The temporary file is deleted on failure.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use tempfile::{tempdir, write_atomic};
+///
+/// let dir = tempdir()?;
+/// let path = dir.path().join("config.toml");
+///
+/// write_atomic(&path, "answer = 42\n")?;
+/// assert_eq!(fs::read_to_string(&path)?, "answer = 42\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    let mut file = AtomicWriteFile::open(path)?;
+    file.write_all(contents.as_ref())?;
+    file.commit()
+}
+
+/// A file that atomically replaces a target path when committed.
+///
+/// Writes go to a temporary file in the target's directory. Calling [`AtomicWriteFile::commit`]
+/// atomically renames the temporary file over the target, so readers of the target will either
+/// see its previous contents or the complete new contents, never a partially written file.
+/// Dropping an `AtomicWriteFile` without committing it (or calling
+/// [`AtomicWriteFile::discard`]) deletes the temporary file, leaving the target untouched.
+///
+/// If the target already exists, its permissions are preserved. Otherwise, the new file is created
+/// with the same (restrictive) permissions as any other temporary file.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use std::io::Write;
+/// use tempfile::{tempdir, AtomicWriteFile};
+///
+/// let dir = tempdir()?;
+/// let path = dir.path().join("data.csv");
+///
+/// let mut file = AtomicWriteFile::open(&path)?;
+/// writeln!(file, "id,name")?;
+/// writeln!(file, "1,Brian")?;
+/// // Nothing is visible at `path` until the file is committed.
+/// assert!(!path.exists());
+/// file.commit()?;
+///
+/// assert_eq!(fs::read_to_string(&path)?, "id,name\n1,Brian\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct AtomicWriteFile {
+    tmp: NamedTempFile,
+    target: PathBuf,
+    permissions: Option<Permissions>,
+    sync: bool,
+}
+
+impl AtomicWriteFile {
+    /// Start atomically writing a new version of the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// If `path` has no parent directory, its metadata can't be read, or the temporary file can't
+    /// be created in its directory, `Err` is returned.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<AtomicWriteFile> {
+        let target = path.as_ref();
+        let permissions = match fs::metadata(target) {
+            Ok(metadata) => Some(metadata.permissions()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_err_path(|| target),
+        };
+        let tmp = Builder::new().tempfile_in(parent_dir(target)?)?;
+        Ok(AtomicWriteFile {
+            tmp,
+            target: target.to_owned(),
+            permissions,
+            sync: true,
+        })
+    }
+
+    /// Set whether [`AtomicWriteFile::commit`] synchronizes the new file (and, on Unix, its parent
+    /// directory) to disk.
+    ///
+    /// Without synchronization, the rename is still atomic with respect to other readers but a
+    /// system crash shortly after committing may leave the target empty or with its previous
+    /// contents, depending on the filesystem.
+    ///
+    /// Default: `true`.
+    pub fn sync_on_commit(&mut self, sync: bool) -> &mut Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Returns the path this file will be written to when committed.
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Get a reference to the underlying temporary file.
+    pub fn as_file(&self) -> &File {
+        self.tmp.as_file()
+    }
+
+    /// Get a mutable reference to the underlying temporary file.
+    pub fn as_file_mut(&mut self) -> &mut File {
+        self.tmp.as_file_mut()
+    }
+
+    /// Atomically replace the target with the written contents.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be synchronized or renamed to the target, `Err` is returned and the
+    /// temporary file is deleted.
+    pub fn commit(self) -> io::Result<()> {
+        let AtomicWriteFile {
+            tmp,
+            target,
+            permissions,
+            sync,
+        } = self;
+
+        // Windows resets the file attributes when persisting so we apply the permissions
+        // afterwards.
+        #[cfg(not(windows))]
+        if let Some(permissions) = &permissions {
+            tmp.as_file()
+                .set_permissions(permissions.clone())
+                .with_err_path(|| tmp.path())?;
+        }
+        if sync {
+            tmp.as_file().sync_all().with_err_path(|| tmp.path())?;
+        }
+
+        tmp.persist(&target)?;
+        #[cfg(windows)]
+        if let Some(permissions) = permissions {
+            fs::set_permissions(&target, permissions).with_err_path(|| &target)?;
+        }
+        if sync {
+            sync_dir(parent_dir(&target)?)?;
+        }
+        Ok(())
+    }
+
+    /// Discard the written contents, deleting the temporary file and leaving the target untouched.
+    ///
+    /// This is equivalent to dropping the `AtomicWriteFile` except that errors deleting the
+    /// temporary file are reported.
+    pub fn discard(self) -> io::Result<()> {
+        self.tmp.close()
+    }
+}
+
+impl Read for AtomicWriteFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.as_file_mut()
+            .read(buf)
+            .with_err_path(|| self.tmp.path())
+    }
+}
+
+impl Write for AtomicWriteFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.as_file_mut()
+            .write(buf)
+            .with_err_path(|| self.tmp.path())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.as_file_mut().flush().with_err_path(|| self.tmp.path())
+    }
+}
+
+impl Seek for AtomicWriteFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.as_file_mut()
+            .seek(pos)
+            .with_err_path(|| self.tmp.path())
+    }
+}
+
+/// Persist a batch of temporary files, moving each to its target path, as a single transaction.
+///
+/// The files are persisted in order, replacing any existing files at their targets. If any of
+/// them can't be persisted, the files persisted so far are rolled back (in reverse order) on a
+/// best-effort basis: the files they replaced are restored or, if nothing was replaced, the new
+/// files are removed. The temporary file that couldn't be persisted and the ones that weren't
+/// processed are returned in the error. On success, the persisted files are returned in order.
+///
+/// Unlike persisting a single file, the batch as a whole isn't atomic: other processes may observe
+/// some of the new files before the rest are persisted or rolled back. Replaced files are restored
+/// from a hard link created just before they're replaced, so they can't be restored on
+/// filesystems without hard links. Such files are left in place rather than removed, and reported
+/// as rollback failures.
+///
+/// # Errors
+///
+/// Returns a [`PersistAllError`] reporting the error, the target that couldn't be persisted, the
+/// targets that were persisted before the failure, and any failures to roll them back. The
+/// temporary files that weren't persisted can be recovered with [`PersistAllError::into_files`].
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use std::io::Write;
+/// use tempfile::{persist_all, tempdir, NamedTempFile};
+///
+/// let dir = tempdir()?;
+/// let mut index = NamedTempFile::new_in(&dir)?;
+/// let mut data = NamedTempFile::new_in(&dir)?;
+/// write!(index, "0")?;
+/// write!(data, "Brian was here. Briefly.")?;
+///
+/// persist_all([
+///     (index, dir.path().join("index")),
+///     (data, dir.path().join("data")),
+/// ])?;
+/// assert_eq!(fs::read_to_string(dir.path().join("index"))?, "0");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn persist_all<I, P>(files: I) -> Result<Vec<File>, PersistAllError>
+where
+    I: IntoIterator<Item = (NamedTempFile, P)>,
+    P: AsRef<Path>,
+{
+    let mut done = Vec::new();
+    let mut files = files.into_iter();
+    while let Some((file, target)) = files.next() {
+        let target = target.as_ref();
+        match Persisted::new(file, target) {
+            Ok(persisted) => done.push(persisted),
+            Err((error, file)) => {
+                let persisted = done.iter().map(|p| p.target.clone()).collect();
+                let rollback_failures = done
+                    .into_iter()
+                    .rev()
+                    .filter_map(|p| {
+                        let target = p.target.clone();
+                        p.rollback().err().map(|e| (target, e))
+                    })
+                    .collect();
+                let unpersisted = std::iter::once((file, target.to_owned()))
+                    .chain(files.map(|(file, target)| (file, target.as_ref().to_owned())))
+                    .collect();
+                return Err(PersistAllError {
+                    error,
+                    target: target.to_owned(),
+                    persisted,
+                    rollback_failures,
+                    unpersisted,
+                });
+            }
+        }
+    }
+    // Dropping the backups deletes them.
+    Ok(done.into_iter().map(|p| p.file).collect())
+}
+
+/// A file persisted by [`persist_all`], along with what's needed to roll it back.
+struct Persisted {
+    file: File,
+    target: PathBuf,
+    /// Whether the file replaced an existing one.
+    replaced: bool,
+    /// A hard link to the file that was replaced, if it could be created.
+    backup: Option<TempPath>,
+}
+
+impl Persisted {
+    fn new(file: NamedTempFile, target: &Path) -> Result<Persisted, (io::Error, NamedTempFile)> {
+        // Link the file we're about to replace to a temporary name so it can be restored. This is
+        // best-effort: hard links aren't supported everywhere.
+        let replaced = fs::symlink_metadata(target).is_ok();
+        let backup = if replaced {
+            let dir = match parent_dir(target) {
+                Ok(dir) => dir,
+                Err(e) => return Err((e, file)),
+            };
+            Builder::new()
+                .make_in(dir, |path| fs::hard_link(target, path))
+                .ok()
+                .map(NamedTempFile::into_temp_path)
+        } else {
+            None
+        };
+        match file.persist(target) {
+            Ok(file) => Ok(Persisted {
+                file,
+                target: target.to_owned(),
+                replaced,
+                backup,
+            }),
+            Err(e) => Err((e.error, e.file)),
+        }
+    }
+
+    fn rollback(self) -> io::Result<()> {
+        match self.backup {
+            Some(backup) => backup.persist(&self.target).map_err(io::Error::from),
+            // Removing the file would lose both the old and the new contents.
+            None if self.replaced => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "the replaced file couldn't be backed up, so it can't be restored",
+            ))
+            .with_err_path(|| &self.target),
+            None => fs::remove_file(&self.target).with_err_path(|| &self.target),
+        }
+    }
+}
+
+/// Error returned by [`persist_all`] when a file couldn't be persisted.
+#[derive(Debug)]
+pub struct PersistAllError {
+    error: io::Error,
+    target: PathBuf,
+    persisted: Vec<PathBuf>,
+    rollback_failures: Vec<(PathBuf, io::Error)>,
+    /// The file that couldn't be persisted, followed by the ones that weren't processed.
+    unpersisted: Vec<(NamedTempFile, PathBuf)>,
+}
+
+impl PersistAllError {
+    /// Returns the error that caused the batch to fail.
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Returns the target path of the file that couldn't be persisted.
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Returns the target paths of the files that were persisted before the failure, in order.
+    /// These have been rolled back unless listed in [`PersistAllError::rollback_failures`].
+    pub fn persisted(&self) -> &[PathBuf] {
+        &self.persisted
+    }
+
+    /// Returns the target paths that couldn't be rolled back, along with the reason.
+    pub fn rollback_failures(&self) -> &[(PathBuf, io::Error)] {
+        &self.rollback_failures
+    }
+
+    /// Returns the temporary file that couldn't be persisted.
+    pub fn file(&self) -> &NamedTempFile {
+        &self.unpersisted[0].0
+    }
+
+    /// Returns the temporary files that weren't processed because of the failure, along with
+    /// their targets.
+    pub fn remaining(&self) -> &[(NamedTempFile, PathBuf)] {
+        &self.unpersisted[1..]
+    }
+
+    /// Consumes the error, returning the error that caused the batch to fail.
+    pub fn into_error(self) -> io::Error {
+        self.error
+    }
+
+    /// Consumes the error, returning the temporary files that weren't persisted along with their
+    /// targets: the one that couldn't be persisted, followed by the ones that weren't processed.
+    pub fn into_files(self) -> Vec<(NamedTempFile, PathBuf)> {
+        self.unpersisted
+    }
+}
+
+impl From<PersistAllError> for io::Error {
+    fn from(error: PersistAllError) -> io::Error {
+        io::Error::new(error.error.kind(), error)
+    }
+}
+
+impl fmt::Display for PersistAllError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to persist temporary file to {}: {}",
+            self.target.display(),
+            self.error
+        )?;
+        if !self.rollback_failures.is_empty() {
+            write!(
+                f,
+                " ({} file(s) couldn't be rolled back)",
+                self.rollback_failures.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for PersistAllError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Returns the directory containing `path`.
+fn parent_dir(path: &Path) -> io::Result<&Path> {
+    match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Ok(Path::new(".")),
+        Some(parent) => Ok(parent),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "target path has no parent directory",
+        ))
+        .with_err_path(|| path),
+    }
+}
+
+/// Synchronize a directory so that a rename into it reaches the disk.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .with_err_path(|| dir)
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
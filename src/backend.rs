@@ -0,0 +1,166 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions, Permissions};
+use std::io;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::path::Path;
+
+use crate::{dir, file};
+
+/// The storage operations used to create, persist, and delete temporary files and directories.
+///
+/// By default, temporary files and directories are managed with the [`OsBackend`], which uses the
+/// platform's native filesystem APIs. A custom backend can be configured per-[`Builder`] with
+/// [`Builder::backend`]; all [`NamedTempFile`]s, [`TempPath`]s, and [`TempDir`]s created by that
+/// builder then use it for the rest of their lifetime (reopening, persisting, and cleanup).
+///
+/// Every method has a default implementation that delegates to the [`OsBackend`], so
+/// implementations only need to override the operations they want to customize (e.g., an NFS-safe
+/// persist or an auditing wrapper).
+///
+/// # Security
+///
+/// Backends are responsible for upholding the same guarantees as the default implementation. In
+/// particular, [`TempBackend::create_file`] and [`TempBackend::create_dir`] must fail with
+/// [`std::io::ErrorKind::AlreadyExists`] if the path already exists, atomically. Otherwise, a
+/// [time-of-check to time-of-use bug][TOCTOU] could be introduced.
+///
+/// # Examples
+///
+/// ```
+/// use std::io;
+/// use std::path::Path;
+/// use tempfile::{Builder, TempBackend};
+///
+/// #[derive(Debug)]
+/// struct Verbose;
+///
+/// impl TempBackend for Verbose {
+///     fn remove_file(&self, path: &Path) -> io::Result<()> {
+///         println!("removing {}", path.display());
+///         std::fs::remove_file(path)
+///     }
+/// }
+///
+/// static VERBOSE: Verbose = Verbose;
+///
+/// let file = Builder::new().backend(&VERBOSE).tempfile()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// [`Builder`]: crate::Builder
+/// [`Builder::backend`]: crate::Builder::backend
+/// [`NamedTempFile`]: crate::NamedTempFile
+/// [`TempPath`]: crate::TempPath
+/// [`TempDir`]: crate::TempDir
+/// [TOCTOU]: https://en.wikipedia.org/wiki/Time-of-check_to_time-of-use
+pub trait TempBackend: fmt::Debug + Send + Sync {
+    /// Create a new file at `path` with the given options, failing if it already exists.
+    ///
+    /// `permissions` are the permissions configured with [`crate::Builder::permissions`], if any.
+    fn create_file(
+        &self,
+        path: &Path,
+        open_options: &mut OpenOptions,
+        permissions: Option<&Permissions>,
+    ) -> io::Result<File> {
+        file::imp::create_named(path, open_options, permissions)
+    }
+
+    /// Open a new handle to `file`, located at `path`, making sure that it's the same file.
+    fn reopen(&self, file: &File, path: &Path) -> io::Result<File> {
+        file::imp::reopen(file, path)
+    }
+
+    /// Open a new handle to `file`, located at `path`, with the given options, making sure that
+    /// it's the same file.
+    fn reopen_with(&self, file: &File, path: &Path, options: &OpenOptions) -> io::Result<File> {
+        file::imp::reopen_with(file, path, options)
+    }
+
+    /// Move the file at `old_path` to `new_path`, replacing any existing file if `overwrite` is
+    /// true and failing otherwise.
+    fn persist(&self, old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<()> {
+        file::imp::persist(old_path, new_path, overwrite)
+    }
+
+    /// Turn the temporary file at `path` into a regular, non-temporary file.
+    fn keep(&self, path: &Path) -> io::Result<()> {
+        file::imp::keep(path)
+    }
+
+    /// Delete the temporary file at `path`.
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    /// Create a new directory at `path`, failing if it already exists.
+    ///
+    /// `permissions` are the permissions configured with [`crate::Builder::permissions`], if any.
+    fn create_dir(&self, path: &Path, permissions: Option<&Permissions>) -> io::Result<()> {
+        dir::imp::create(path, permissions)
+    }
+
+    /// Move the directory at `old_path` to `new_path`.
+    fn rename_dir(&self, old_path: &Path, new_path: &Path) -> io::Result<()> {
+        fs::rename(old_path, new_path)
+    }
+
+    /// Recursively delete the temporary directory at `path`.
+    ///
+    /// Errors listing multiple entries that couldn't be removed should wrap a
+    /// [`CleanupError`](crate::CleanupError).
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        dir::remove_dir_all(path)
+    }
+}
+
+/// The default [`TempBackend`], using the platform's native filesystem APIs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OsBackend;
+
+impl TempBackend for OsBackend {}
+
+/// A reference to a backend, compared by address.
+///
+/// The flag records whether this is the built-in [`OsBackend`] (and not a custom backend), which
+/// can't reliably be determined by comparing addresses.
+#[derive(Clone, Copy)]
+pub(crate) struct BackendRef(
+    pub(crate) &'static dyn TempBackend,
+    #[cfg_attr(not(any(unix, windows)), allow(dead_code))] bool,
+);
+
+impl BackendRef {
+    pub(crate) const OS: BackendRef = BackendRef(&OsBackend, true);
+
+    pub(crate) fn custom(backend: &'static dyn TempBackend) -> BackendRef {
+        BackendRef(backend, false)
+    }
+
+    /// Returns `true` if this is the built-in [`OsBackend`].
+    #[cfg(any(unix, windows))]
+    pub(crate) fn is_os(self) -> bool {
+        self.1
+    }
+}
+
+// Backends are shared, immutable (`Sync`) references; don't let them make `TempDir` and `TempPath`
+// non-unwind-safe.
+impl UnwindSafe for BackendRef {}
+impl RefUnwindSafe for BackendRef {}
+
+impl fmt::Debug for BackendRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl PartialEq for BackendRef {
+    fn eq(&self, other: &Self) -> bool {
+        // Compare both the data pointers and the vtables: zero-sized backends may share an
+        // address. Vtables aren't guaranteed to be unique, but false negatives are acceptable.
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for BackendRef {}
@@ -0,0 +1,58 @@
+//! Introspection of live temporary files and directories, for leak hunting.
+//!
+//! This module is only available with the `debug-registry` feature. When enabled, every
+//! temporary file and directory created through a [`Builder`](crate::Builder) (including the
+//! `new` constructors) is recorded in a process-global registry until it's deleted, persisted,
+//! or kept.
+
+use std::path::{Path, PathBuf};
+
+use crate::registry;
+
+/// A temporary file or directory that's still alive (see [`live_resources`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveResource {
+    path: PathBuf,
+    is_dir: bool,
+}
+
+impl LiveResource {
+    /// Returns the path of the temporary file or directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `true` if this is a temporary directory.
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+}
+
+/// Returns the temporary files and directories created by this process that haven't been deleted,
+/// persisted, or kept yet, in creation order.
+///
+/// Temporary files and directories leaked with [`std::mem::forget`] are listed until the process
+/// exits.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::debug::live_resources;
+///
+/// let dir = tempfile::tempdir()?;
+/// assert!(live_resources().iter().any(|r| r.path() == dir.path()));
+///
+/// let path = dir.path().to_owned();
+/// drop(dir);
+/// assert!(!live_resources().iter().any(|r| r.path() == path));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[must_use]
+pub fn live_resources() -> Vec<LiveResource> {
+    registry::live()
+        .into_iter()
+        .map(|(path, is_dir)| LiveResource { path, is_dir })
+        .collect()
+}
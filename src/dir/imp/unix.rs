@@ -1,13 +1,15 @@
 use crate::error::IoResultExt;
-use crate::TempDir;
+use std::ffi::{CString, OsStr};
 use std::io;
-use std::path::PathBuf;
+use std::os::unix::ffi::OsStrExt;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsFd, BorrowedFd};
+use std::path::{Path, PathBuf};
 
-pub fn create(
-    path: PathBuf,
-    permissions: Option<&std::fs::Permissions>,
-    keep: bool,
-) -> io::Result<TempDir> {
+use rustix::io::Errno;
+
+pub fn create(path: &Path, permissions: Option<&std::fs::Permissions>) -> io::Result<()> {
     let mut dir_options = std::fs::DirBuilder::new();
     #[cfg(not(target_os = "wasi"))]
     {
@@ -16,11 +18,157 @@ pub fn create(
             dir_options.mode(p.mode());
         }
     }
-    dir_options
-        .create(&path)
-        .with_err_path(|| &path)
-        .map(|_| TempDir {
-            path: path.into_boxed_path(),
-            keep,
+    dir_options.create(path).with_err_path(|| path)
+}
+
+/// Returns `true` if the directory described by `meta` is owned by the current user.
+pub fn is_owned(meta: &std::fs::Metadata) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(meta.uid() == rustix::process::getuid().as_raw())
+}
+
+/// Open a handle to the directory at `path`, without following symlinks.
+pub fn open(path: &Path) -> io::Result<std::os::unix::io::OwnedFd> {
+    use rustix::fs::{Mode, OFlags};
+    rustix::fs::open(
+        path,
+        OFlags::RDONLY | OFlags::DIRECTORY | OFlags::NOFOLLOW | OFlags::CLOEXEC,
+        Mode::empty(),
+    )
+    .map_err(io::Error::from)
+    .with_err_path(|| path)
+}
+
+/// Returns `true` if the directory open at `dir` has no entries.
+pub fn is_empty_at(dir: BorrowedFd<'_>) -> io::Result<bool> {
+    for entry in rustix::fs::Dir::read_from(dir)? {
+        if !matches!(entry?.file_name().to_bytes(), b"." | b"..") {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Remove everything inside the directory open at `dir` (located at `path`), relative to the
+/// handle, collecting failures. Directories are only reported if none of their contents failed to
+/// be removed.
+pub fn remove_contents_at(
+    dir: BorrowedFd<'_>,
+    path: &Path,
+    failures: &mut Vec<(PathBuf, io::Error)>,
+) {
+    use rustix::fs::{openat, statat, unlinkat, AtFlags, Dir, FileType, Mode, OFlags};
+
+    // Collect the names first so we don't modify the directory while iterating over it.
+    let names = Dir::read_from(dir).and_then(|entries| {
+        entries
+            .filter(|entry| {
+                !matches!(entry, Ok(entry) if matches!(entry.file_name().to_bytes(), b"." | b".."))
+            })
+            .map(|entry| entry.map(|entry| entry.file_name().to_owned()))
+            .collect::<Result<Vec<CString>, _>>()
+    });
+    let names = match names {
+        Ok(names) => names,
+        Err(e) => return failures.push((path.to_owned(), e.into())),
+    };
+
+    for name in names {
+        let child = path.join(OsStr::from_bytes(name.to_bytes()));
+        let is_dir = match statat(dir, &name, AtFlags::SYMLINK_NOFOLLOW) {
+            Ok(stat) => FileType::from_raw_mode(stat.st_mode) == FileType::Directory,
+            Err(Errno::NOENT) => continue,
+            Err(e) => {
+                failures.push((child, e.into()));
+                continue;
+            }
+        };
+        let result = if is_dir {
+            let flags = OFlags::RDONLY | OFlags::DIRECTORY | OFlags::NOFOLLOW | OFlags::CLOEXEC;
+            match openat(dir, &name, flags, Mode::empty()) {
+                Ok(subdir) => {
+                    let before = failures.len();
+                    remove_contents_at(subdir.as_fd(), &child, failures);
+                    if failures.len() > before {
+                        continue;
+                    }
+                    unlinkat(dir, &name, AtFlags::REMOVEDIR)
+                }
+                Err(e) => Err(e),
+            }
+        } else {
+            unlinkat(dir, &name, AtFlags::empty())
+        };
+        match result {
+            Ok(()) | Err(Errno::NOENT) => {}
+            Err(e) => failures.push((child, e.into())),
+        }
+    }
+}
+
+/// Returns the total size and number of the files inside the directory open at `dir` (located at
+/// `path`), walking it relative to the handle without following symbolic links. Entries deleted
+/// while walking are skipped.
+pub fn usage_at(dir: BorrowedFd<'_>, path: &Path) -> io::Result<(u64, u64)> {
+    use rustix::fs::{openat, statat, AtFlags, Dir, FileType, Mode, OFlags};
+
+    let (mut bytes, mut files) = (0, 0);
+    let entries = match Dir::read_from(dir) {
+        Ok(entries) => entries,
+        Err(Errno::NOENT) => return Ok((0, 0)),
+        Err(e) => return Err(io::Error::from(e)).with_err_path(|| path),
+    };
+    for entry in entries {
+        let entry = entry.map_err(io::Error::from).with_err_path(|| path)?;
+        let name = entry.file_name();
+        if matches!(name.to_bytes(), b"." | b"..") {
+            continue;
+        }
+        let child = || path.join(OsStr::from_bytes(name.to_bytes()));
+        let stat = match statat(dir, name, AtFlags::SYMLINK_NOFOLLOW) {
+            Ok(stat) => stat,
+            Err(Errno::NOENT) => continue,
+            Err(e) => return Err(io::Error::from(e)).with_err_path(child),
+        };
+        if FileType::from_raw_mode(stat.st_mode) == FileType::Directory {
+            let flags = OFlags::RDONLY | OFlags::DIRECTORY | OFlags::NOFOLLOW | OFlags::CLOEXEC;
+            let subdir = match openat(dir, name, flags, Mode::empty()) {
+                Ok(subdir) => subdir,
+                Err(Errno::NOENT) => continue,
+                Err(e) => return Err(io::Error::from(e)).with_err_path(child),
+            };
+            let (sub_bytes, sub_files) = usage_at(subdir.as_fd(), &child())?;
+            bytes += sub_bytes;
+            files += sub_files;
+        } else {
+            bytes += stat.st_size as u64;
+            files += 1;
+        }
+    }
+    Ok((bytes, files))
+}
+
+/// Returns the current path of the directory open at `dir`, if it can be found. `path` is the
+/// directory's original path.
+///
+/// On Linux and Android, this follows the directory if it has been renamed. Otherwise, this only
+/// returns `path` if it still refers to the same directory.
+pub fn current_path(dir: BorrowedFd<'_>, path: &Path) -> Option<PathBuf> {
+    let stat = rustix::fs::fstat(dir).ok()?;
+    let is_same = |candidate: &Path| {
+        rustix::fs::lstat(candidate).map_or(false, |st| {
+            st.st_dev == stat.st_dev && st.st_ino == stat.st_ino
         })
+    };
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if let Ok(current) = std::fs::read_link(format!("/proc/self/fd/{}", dir.as_raw_fd())) {
+        if is_same(&current) {
+            return Some(current);
+        }
+    }
+    if is_same(path) {
+        Some(path.to_owned())
+    } else {
+        None
+    }
 }
@@ -9,16 +9,25 @@
 // except according to those terms.
 
 use std::ffi::OsStr;
-use std::fs::remove_dir_all;
+use std::fs::{self, File, OpenOptions};
 use std::mem;
+use std::ops::Deref;
+#[cfg(unix)]
+use std::os::unix::io::{AsFd, BorrowedFd, OwnedFd};
 use std::path::{self, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 use std::{fmt, io};
 
-use crate::error::IoResultExt;
-use crate::Builder;
+use once_cell::sync::OnceCell as OnceLock;
 
-#[cfg(doc)]
+use crate::backend::BackendRef;
 use crate::env;
+use crate::error::{copy_result, IoResultExt};
+use crate::registry;
+use crate::{Builder, OnCleanup};
 
 /// Create a new temporary directory.
 ///
@@ -109,6 +118,144 @@ pub fn tempdir_in<P: AsRef<Path>>(dir: P) -> io::Result<TempDir> {
     TempDir::new_in(dir)
 }
 
+/// Create a new temporary directory, call `f` with its path, then delete it, returning the result
+/// of `f`.
+///
+/// The directory is deleted even if `f` panics. Unlike a [`TempDir`] guard, which is deleted as
+/// soon as it's dropped (e.g., when it's accidentally moved into a function taking
+/// `AsRef<Path>`), the directory is guaranteed to live exactly as long as the call to `f`.
+///
+/// See [`try_with_tempdir`] for closures that can fail.
+///
+/// # Errors
+///
+/// If the directory can not be created or deleted, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+///
+/// let contents = tempfile::with_tempdir(|dir| {
+///     let path = dir.join("my-temporary-note.txt");
+///     fs::write(&path, "Brian was here. Briefly.").unwrap();
+///     fs::read_to_string(&path).unwrap()
+/// })?;
+/// assert_eq!(contents, "Brian was here. Briefly.");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn with_tempdir<R>(f: impl FnOnce(&Path) -> R) -> io::Result<R> {
+    try_with_tempdir(|path| Ok::<_, io::Error>(f(path)))
+}
+
+/// Like [`with_tempdir`], but for closures that can fail.
+///
+/// The directory is deleted whether `f` succeeds, fails, or panics.
+///
+/// # Errors
+///
+/// If the directory can not be created or deleted, or `f` fails, `Err` is returned. If both `f`
+/// and the deletion fail, `f`'s error is returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+///
+/// let contents = tempfile::try_with_tempdir(|dir| {
+///     let path = dir.join("my-temporary-note.txt");
+///     fs::write(&path, "Brian was here. Briefly.")?;
+///     fs::read_to_string(&path)
+/// })?;
+/// assert_eq!(contents, "Brian was here. Briefly.");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn try_with_tempdir<R, E>(f: impl FnOnce(&Path) -> Result<R, E>) -> Result<R, E>
+where
+    E: From<io::Error>,
+{
+    // Dropping the guard (e.g., while unwinding) deletes the directory.
+    let dir = tempdir()?;
+    let result = f(dir.path())?;
+    dir.close()?;
+    Ok(result)
+}
+
+/// Create a new temporary directory named after the calling function.
+///
+/// The directory's prefix is the path of the enclosing function (e.g.,
+/// `my_crate-tests-test_parser-`), sanitized to only contain ASCII alphanumerics, `-`, and `_`.
+/// This makes leftover directories in the temporary directory attributable to the test or
+/// component that leaked them. The directory is otherwise created like [`Builder::tempdir`] with
+/// the default [`Builder`] (see
+/// [`env::override_default_builder`](crate::env::override_default_builder)).
+///
+/// `tempdir!()` creates the directory in [`env::temp_dir()`], `tempdir!(dir)` in `dir`. Both
+/// return an `io::Result<TempDir>`.
+///
+/// # Examples
+///
+/// ```
+/// fn test_parser() -> std::io::Result<()> {
+///     let dir = tempfile::tempdir!()?;
+///     let name = dir.path().file_name().unwrap().to_str().unwrap();
+///     assert!(name.contains("test_parser"));
+///     Ok(())
+/// }
+/// # test_parser().unwrap();
+/// ```
+#[macro_export]
+macro_rules! tempdir {
+    () => {
+        $crate::Builder::new()
+            .prefix(&$crate::__caller_prefix({
+                fn f() {}
+                $crate::__type_name_of(f)
+            }))
+            .tempdir()
+    };
+    ($dir:expr) => {
+        $crate::Builder::new()
+            .prefix(&$crate::__caller_prefix({
+                fn f() {}
+                $crate::__type_name_of(f)
+            }))
+            .tempdir_in($dir)
+    };
+}
+
+#[doc(hidden)]
+pub fn __type_name_of<T>(_: T) -> &'static str {
+    std::any::type_name::<T>()
+}
+
+/// Turn the type name of a function `f` defined in the caller into a directory prefix.
+#[doc(hidden)]
+pub fn __caller_prefix(type_name: &str) -> String {
+    // Keep the end of long paths, the function name is the most useful part.
+    const MAX_LEN: usize = 100;
+
+    let path = type_name.strip_suffix("::f").unwrap_or(type_name);
+    let mut prefix = String::with_capacity(path.len() + 1);
+    for segment in path.split("::").filter(|s| *s != "{{closure}}") {
+        if !prefix.is_empty() {
+            prefix.push('-');
+        }
+        prefix.extend(segment.chars().map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        }));
+    }
+    if prefix.len() > MAX_LEN {
+        prefix.drain(..prefix.len() - MAX_LEN);
+    }
+    prefix.push('-');
+    prefix
+}
+
 /// A directory in the filesystem that is automatically deleted when
 /// it goes out of scope.
 ///
@@ -143,6 +290,18 @@ pub fn tempdir_in<P: AsRef<Path>>(dir: P) -> io::Result<TempDir> {
 /// receiving a signal like `SIGINT`, then the temporary directory
 /// will not be deleted.
 ///
+/// # Security
+///
+/// On Unix, the `TempDir` keeps a handle to the directory it created (see
+/// [`TempDir::as_dir_fd`]) and deletes its contents relative to that handle, so cleanup still
+/// works if the directory is renamed and can't be redirected by replacing the directory (or one of
+/// its ancestors) with a symlink.
+///
+/// On Windows and other platforms, and with a custom [`TempBackend`](crate::TempBackend), the
+/// directory is deleted by path: if it's renamed, it's leaked, and if it (or one of its ancestors)
+/// is replaced by someone who can write to the parent directory, cleanup deletes whatever is at
+/// the path instead. Handle-based deletion on Windows isn't implemented yet.
+///
 /// # Examples
 ///
 /// Create a temporary directory with a generated name:
@@ -183,6 +342,14 @@ pub fn tempdir_in<P: AsRef<Path>>(dir: P) -> io::Result<TempDir> {
 pub struct TempDir {
     path: Box<Path>,
     keep: bool,
+    background: bool,
+    recursive: bool,
+    sealed: bool,
+    on_cleanup: Option<OnCleanup>,
+    backend: BackendRef,
+    // A handle to the directory, opened when it was created (if possible).
+    #[cfg(unix)]
+    handle: Option<OwnedFd>,
 }
 
 impl TempDir {
@@ -352,6 +519,107 @@ impl TempDir {
         Builder::new().prefix(&prefix).tempdir_in(dir)
     }
 
+    /// Takes ownership of an existing directory, deleting it (and everything inside it) when the
+    /// returned `TempDir` is dropped.
+    ///
+    /// This is useful when a scratch directory is created by someone else (e.g., a parent process)
+    /// but should still be cleaned up on drop. Unlike [`TempDirPath::from_path`], the path must
+    /// already refer to a directory. Relative paths are resolved against the current directory,
+    /// so changing it later doesn't affect which directory is deleted.
+    ///
+    /// # Errors
+    ///
+    /// If `path` doesn't exist or isn't a directory (symbolic links aren't followed), `Err` is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::TempDir;
+    ///
+    /// # let parent = tempfile::tempdir()?;
+    /// let scratch = parent.path().join("scratch");
+    /// fs::create_dir(&scratch)?;
+    ///
+    /// let tmp_dir = TempDir::from_existing(&scratch)?;
+    /// drop(tmp_dir);
+    /// assert!(!scratch.exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn from_existing<P: AsRef<Path>>(path: P) -> io::Result<TempDir> {
+        TempDir::from_existing_with(path.as_ref(), false, false)
+    }
+
+    /// Takes ownership of an existing directory like [`TempDir::from_existing`], but only if it's
+    /// empty.
+    ///
+    /// # Errors
+    ///
+    /// If `path` isn't a directory or the directory isn't empty, `Err` is returned.
+    pub fn from_existing_empty<P: AsRef<Path>>(path: P) -> io::Result<TempDir> {
+        TempDir::from_existing_with(path.as_ref(), true, false)
+    }
+
+    /// Takes ownership of an existing directory like [`TempDir::from_existing`], but only if it's
+    /// owned by the current user.
+    ///
+    /// This guards against deleting a directory planted by another user. Ownership can only be
+    /// checked on Unix.
+    ///
+    /// # Errors
+    ///
+    /// If `path` isn't a directory or the directory is owned by another user, `Err` is returned.
+    /// On platforms other than Unix, this always returns `Err`.
+    pub fn from_existing_owned<P: AsRef<Path>>(path: P) -> io::Result<TempDir> {
+        TempDir::from_existing_with(path.as_ref(), false, true)
+    }
+
+    fn from_existing_with(path: &Path, empty: bool, owned: bool) -> io::Result<TempDir> {
+        let path = &*crate::util::absolute(path).with_err_path(|| path)?;
+        let meta = fs::symlink_metadata(path).with_err_path(|| path)?;
+        if !meta.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a directory",
+            ))
+            .with_err_path(|| path);
+        }
+        // Open the directory first and check it through the handle, so that the checks apply to
+        // the directory that will be deleted even if it's replaced in the meantime.
+        #[cfg(unix)]
+        let (handle, meta) = {
+            let file = File::from(imp::open(path)?);
+            let meta = file.metadata().with_err_path(|| path)?;
+            (OwnedFd::from(file), meta)
+        };
+        if owned && !imp::is_owned(&meta).with_err_path(|| path)? {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "directory is owned by another user",
+            ))
+            .with_err_path(|| path);
+        }
+        #[cfg(unix)]
+        let is_empty = |_: &Path| imp::is_empty_at(handle.as_fd());
+        #[cfg(not(unix))]
+        let is_empty = |path: &Path| fs::read_dir(path).map(|mut entries| entries.next().is_none());
+        if empty && !is_empty(path).with_err_path(|| path)? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "directory is not empty",
+            ))
+            .with_err_path(|| path);
+        }
+        #[cfg_attr(not(unix), allow(unused_mut))]
+        let mut dir = adopt(path.to_owned(), &Builder::new());
+        #[cfg(unix)]
+        {
+            dir.handle = Some(handle);
+        }
+        Ok(dir)
+    }
+
     /// Accesses the [`Path`] to the temporary directory.
     ///
     /// [`Path`]: http://doc.rust-lang.org/std/path/struct.Path.html
@@ -382,6 +650,243 @@ impl TempDir {
         self.path.as_ref()
     }
 
+    /// Returns the path of `rel` inside the temporary directory.
+    ///
+    /// Unlike `tmp_dir.path().join(rel)`, this never produces a path outside of the temporary
+    /// directory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rel` is absolute or contains `..` components.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// let note = tmp_dir.child("notes/my-temporary-note.txt");
+    /// assert!(note.starts_with(tmp_dir.path()));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn child<P: AsRef<Path>>(&self, rel: P) -> PathBuf {
+        match self.checked_child(rel.as_ref()) {
+            Ok(path) => path,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Create a file at `rel` inside the temporary directory, creating any missing parent
+    /// directories first.
+    ///
+    /// The file is opened in write-only mode and truncated if it already exists, like
+    /// [`std::fs::File::create`].
+    ///
+    /// # Errors
+    ///
+    /// If `rel` is absolute or contains `..` components, an error with kind
+    /// [`std::io::ErrorKind::InvalidInput`] is returned. If the parent directories or the file
+    /// can't be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// let mut file = tmp_dir.create_child_file("notes/my-temporary-note.txt")?;
+    /// writeln!(file, "Brian was here. Briefly.")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn create_child_file<P: AsRef<Path>>(&self, rel: P) -> io::Result<File> {
+        let path = self.checked_child(rel.as_ref())?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_err_path(|| parent)?;
+        }
+        File::create(&path).with_err_path(|| path)
+    }
+
+    /// Create a directory at `rel` inside the temporary directory, creating any missing parent
+    /// directories first, and return its path.
+    ///
+    /// It is not an error if the directory already exists.
+    ///
+    /// # Errors
+    ///
+    /// If `rel` is absolute or contains `..` components, an error with kind
+    /// [`std::io::ErrorKind::InvalidInput`] is returned. If the directories can't be created,
+    /// `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// let nested = tmp_dir.create_child_dir("a/b/c")?;
+    /// assert!(nested.is_dir());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn create_child_dir<P: AsRef<Path>>(&self, rel: P) -> io::Result<PathBuf> {
+        let path = self.checked_child(rel.as_ref())?;
+        fs::create_dir_all(&path).with_err_path(|| &path)?;
+        Ok(path)
+    }
+
+    /// Recursively copy the contents of the `template` directory into the temporary directory.
+    ///
+    /// Files and directories are copied along with their permissions. On Unix, symbolic links are
+    /// copied as links; on other platforms, they're followed. Existing entries in the temporary
+    /// directory are replaced (symbolic links are replaced, not written through), except that
+    /// existing directories are merged with the template's.
+    ///
+    /// # Errors
+    ///
+    /// If any entry can't be read or copied, or a symbolic link loop is found while following
+    /// links, `Err` is returned. Entries that have already been copied are left in place.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// tmp_dir.copy_from("tests/fixtures")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn copy_from<P: AsRef<Path>>(&self, template: P) -> io::Result<()> {
+        copy_contents(template.as_ref(), self.path())
+    }
+
+    /// Open the file at `rel` inside the temporary directory with the given options.
+    ///
+    /// On Unix, `rel` is resolved one component at a time with `openat` relative to a handle to
+    /// the temporary directory opened when it was created, without following symbolic links, so
+    /// the file is guaranteed to be inside _this_ directory even if it has since been renamed or
+    /// replaced (e.g., by a temporary file cleaner or an attacker). Opening a path that goes
+    /// through a symbolic link fails. On other platforms, or if the directory couldn't be opened
+    /// (see [`TempDir::as_dir_fd`]), `rel` is resolved relative to [`TempDir::path`] and symbolic
+    /// links are followed.
+    ///
+    /// # Errors
+    ///
+    /// If `rel` is absolute or contains `..` components, or the options are invalid (see
+    /// [`OpenInOptions`]), an error with kind [`std::io::ErrorKind::InvalidInput`] is returned. If
+    /// the file can't be opened, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::{OpenInOptions, TempDir};
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// let mut file = tmp_dir.open_in(
+    ///     "my-temporary-note.txt",
+    ///     OpenInOptions::new().write(true).create_new(true),
+    /// )?;
+    /// writeln!(file, "Brian was here. Briefly.")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn open_in<P: AsRef<Path>>(&self, rel: P, options: &OpenInOptions) -> io::Result<File> {
+        let path = self.checked_child(rel.as_ref())?;
+        #[cfg(unix)]
+        if let Some(handle) = &self.handle {
+            let (flags, mode) = options.to_oflags()?;
+            return open_beneath(handle.as_fd(), rel.as_ref(), flags, mode)
+                .map(File::from)
+                .map_err(io::Error::from)
+                .with_err_path(|| path);
+        }
+        options.to_std().open(&path).with_err_path(|| path)
+    }
+
+    /// Borrow the handle to the temporary directory opened when it was created, if any.
+    ///
+    /// The handle refers to _this_ directory even if it has since been renamed or replaced, which
+    /// makes it suitable for IO-safe `*at` APIs (e.g., `rustix` or `cap-std`). It's `None` if the
+    /// directory couldn't be opened (e.g., because it was created by a custom
+    /// [`TempBackend`](crate::TempBackend)).
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::fs::File;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// if let Some(fd) = tmp_dir.as_dir_fd() {
+    ///     let dir = File::from(fd.try_clone_to_owned()?);
+    ///     assert!(dir.metadata()?.is_dir());
+    /// }
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn as_dir_fd(&self) -> Option<BorrowedFd<'_>> {
+        self.handle.as_ref().map(|handle| handle.as_fd())
+    }
+
+    /// Walk the temporary directory and return the total size and number of the files inside it.
+    ///
+    /// Symbolic links are counted as files but not followed. Entries that are deleted while the
+    /// directory is being walked are skipped. On Unix, the directory is walked relative to the
+    /// handle opened when it was created (see [`TempDir::as_dir_fd`]), so _this_ directory is
+    /// measured even if it has since been renamed or replaced.
+    ///
+    /// # Errors
+    ///
+    /// If the directory or one of its subdirectories can't be read, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// std::fs::write(tmp_dir.path().join("a.txt"), "hello")?;
+    /// std::fs::create_dir(tmp_dir.path().join("sub"))?;
+    /// std::fs::write(tmp_dir.path().join("sub/b.txt"), "world!")?;
+    ///
+    /// let usage = tmp_dir.total_size()?;
+    /// assert_eq!(usage.bytes(), 11);
+    /// assert_eq!(usage.files(), 2);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn total_size(&self) -> io::Result<DirUsage> {
+        let mut usage = DirUsage::default();
+        #[cfg(unix)]
+        if let Some(handle) = &self.handle {
+            (usage.bytes, usage.files) = imp::usage_at(handle.as_fd(), self.path())?;
+            return Ok(usage);
+        }
+        usage.add_dir(self.path())?;
+        Ok(usage)
+    }
+
+    fn checked_child(&self, rel: &Path) -> io::Result<PathBuf> {
+        if rel
+            .components()
+            .all(|c| matches!(c, path::Component::Normal(_) | path::Component::CurDir))
+        {
+            Ok(self.path().join(rel))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is not a relative path inside the temporary directory",
+                    rel.display()
+                ),
+            ))
+        }
+    }
+
     /// Persist the temporary directory to disk, returning the [`PathBuf`] where it is located.
     ///
     /// This consumes the [`TempDir`] without deleting directory on the filesystem, meaning that
@@ -408,68 +913,995 @@ impl TempDir {
     /// ```
     #[must_use]
     pub fn into_path(self) -> PathBuf {
-        // Prevent the Drop impl from being called.
-        let mut this = mem::ManuallyDrop::new(self);
+        self.keep()
+    }
 
-        // replace this.path with an empty Box, since an empty Box does not
-        // allocate any heap memory.
-        mem::replace(&mut this.path, PathBuf::new().into_boxed_path()).into()
+    /// Convert the temporary directory into a [`TempDirPath`], a guard that only owns the path.
+    ///
+    /// Any handles the `TempDir` holds to the directory (see [`TempDir::as_dir_fd`]) are closed.
+    /// The directory is still deleted when the returned `TempDirPath` is dropped.
+    #[must_use]
+    #[cfg_attr(not(unix), allow(unused_mut))]
+    pub fn into_temp_dir_path(mut self) -> TempDirPath {
+        #[cfg(unix)]
+        drop(self.handle.take());
+        TempDirPath { dir: self }
     }
 
-    /// Closes and removes the temporary directory, returning a `Result`.
+    /// Convert the temporary directory into a [`SharedTempDir`], a cheaply cloneable handle that
+    /// deletes the directory when the last clone is dropped.
     ///
-    /// Although `TempDir` removes the directory on drop, in the destructor
-    /// any errors are ignored. To detect errors cleaning up the temporary
-    /// directory, call `close` instead.
+    /// # Examples
     ///
-    /// # Errors
+    /// ```
+    /// use std::thread;
+    /// use tempfile::TempDir;
     ///
-    /// This function may return a variety of [`std::io::Error`]s that result from deleting
-    /// the files and directories contained with the temporary directory,
-    /// as well as from deleting the temporary directory itself. These errors
-    /// may be platform specific.
+    /// let dir = TempDir::new()?.shared();
+    /// let worker = {
+    ///     let dir = dir.clone();
+    ///     thread::spawn(move || std::fs::write(dir.path().join("output.txt"), "done"))
+    /// };
+    /// worker.join().unwrap()?;
     ///
-    /// [`std::io::Error`]: http://doc.rust-lang.org/std/io/struct.Error.html
+    /// // The directory is deleted once the last clone is closed (or dropped).
+    /// let path = dir.path().to_owned();
+    /// dir.close()?;
+    /// assert!(!path.exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn shared(self) -> SharedTempDir {
+        SharedTempDir {
+            shared: Some(Arc::new(Shared {
+                dir: self,
+                clones: AtomicUsize::new(1),
+            })),
+        }
+    }
+
+    /// Persist the temporary directory at the target path.
+    ///
+    /// This renames the directory (along with its contents) to `new_path` and disables automatic
+    /// cleanup. It's useful for building a directory tree in a temporary directory and then
+    /// publishing it in a single step. If this method fails, it will return `self` in the
+    /// resulting [`DirPersistError`].
+    ///
+    /// Note: Temporary directories cannot be persisted across filesystems. Also, neither the
+    /// contents nor the containing directory are synchronized, so the update may not yet have
+    /// reached the disk when `persist` returns.
+    ///
+    /// # Errors
+    ///
+    /// If the directory cannot be moved to the new location, `Err` is returned. Moving the
+    /// directory fails if `new_path` is a non-empty directory or a file. On Unix-like systems, an
+    /// existing _empty_ directory at `new_path` is atomically replaced.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::fs::File;
-    /// use std::io::Write;
+    /// use std::fs;
     /// use tempfile::TempDir;
     ///
-    /// // Create a directory inside of `env::temp_dir()`.
-    /// let tmp_dir = TempDir::new()?;
-    /// let file_path = tmp_dir.path().join("my-temporary-note.txt");
-    /// let mut tmp_file = File::create(file_path)?;
-    /// writeln!(tmp_file, "Brian was here. Briefly.")?;
+    /// # let parent = TempDir::new()?;
+    /// # let final_path = parent.path().join("published");
+    /// let tmp_dir = TempDir::new_in(parent.path())?;
+    /// fs::write(tmp_dir.path().join("index.html"), "Hello World!")?;
     ///
-    /// // By closing the `TempDir` explicitly we can check that it has
-    /// // been deleted successfully. If we don't close it explicitly,
-    /// // the directory will still be deleted when `tmp_dir` goes out
-    /// // of scope, but we won't know whether deleting the directory
-    /// // succeeded.
-    /// drop(tmp_file);
-    /// tmp_dir.close()?;
+    /// tmp_dir.persist(&final_path)?;
+    /// assert!(final_path.join("index.html").exists());
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    pub fn close(mut self) -> io::Result<()> {
-        let result = remove_dir_all(self.path()).with_err_path(|| self.path());
-
-        // Set self.path to empty Box to release the memory, since an empty
-        // Box does not allocate any heap memory.
-        self.path = PathBuf::new().into_boxed_path();
-
-        // Prevent the Drop impl from being called.
-        mem::forget(self);
-
-        result
+    pub fn persist<P: AsRef<Path>>(self, new_path: P) -> Result<(), DirPersistError> {
+        let new_path = new_path.as_ref();
+        match self.backend.0.rename_dir(self.path(), new_path) {
+            Ok(()) => {
+                log!(
+                    debug,
+                    "persisted {} to {}",
+                    self.path().display(),
+                    new_path.display()
+                );
+                // Don't try to delete the old path.
+                let _ = self.keep();
+                Ok(())
+            }
+            Err(error) => Err(DirPersistError {
+                error,
+                dir: self,
+                target: new_path.to_owned(),
+            }),
+        }
     }
-}
 
-impl AsRef<Path> for TempDir {
-    fn as_ref(&self) -> &Path {
-        self.path()
+    /// Keep the temporary directory, returning the [`PathBuf`] where it is located.
+    ///
+    /// This consumes the [`TempDir`] without deleting the directory on the filesystem, meaning
+    /// that the directory will no longer be automatically deleted. To decide later whether the
+    /// directory should be kept, use [`TempDir::disable_cleanup`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    ///
+    /// // Keep the temporary directory, getting the path where it is.
+    /// let tmp_path = tmp_dir.keep();
+    /// assert!(tmp_path.exists());
+    ///
+    /// // Delete the temporary directory ourselves.
+    /// fs::remove_dir_all(tmp_path)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn keep(self) -> PathBuf {
+        registry::unregister(self.path());
+
+        // Prevent the Drop impl from being called.
+        let mut this = mem::ManuallyDrop::new(self);
+        #[cfg(unix)]
+        drop(this.handle.take());
+
+        // replace this.path with an empty Box, since an empty Box does not
+        // allocate any heap memory.
+        mem::replace(&mut this.path, PathBuf::new().into_boxed_path()).into()
+    }
+
+    /// Leak the temporary directory, returning a `'static` reference to its path.
+    ///
+    /// The `TempDir` is never dropped, so the directory isn't deleted when the program exits
+    /// (unless it was registered for cleanup on exit or on a signal). This is intended for
+    /// process-global fixtures (e.g., stored in a `static` `OnceLock`), where destructors never run
+    /// anyway.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use tempfile::TempDir;
+    ///
+    /// let fixture_dir: &'static Path = TempDir::new()?.leak();
+    /// assert!(fixture_dir.is_dir());
+    /// # std::fs::remove_dir(fixture_dir)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn leak(self) -> &'static Path {
+        Box::leak(Box::new(self)).path()
+    }
+
+    /// Delete the temporary directory if the process is interrupted by `SIGINT` or `SIGTERM`.
+    ///
+    /// Destructors don't run when a process is killed by a signal so, by default, temporary
+    /// directories are leaked in that case. This function registers the directory with a
+    /// process-global registry and installs a signal handler (once) that deletes all files and
+    /// directories registered this way before terminating the process with the default signal
+    /// behavior. The directory is removed from the registry when it's deleted or kept.
+    ///
+    /// This function is only available on Unix when the `signal-cleanup` feature is enabled.
+    ///
+    /// **NOTE:** The installed handler terminates the process after cleaning up. Don't use this if
+    /// your application handles `SIGINT`/`SIGTERM` itself (e.g., for a graceful shutdown). The
+    /// directory is deleted on signal even if cleanup has been disabled with
+    /// [`TempDir::disable_cleanup`].
+    ///
+    /// # Errors
+    ///
+    /// If the signal handler cannot be installed, `Err` is returned.
+    #[cfg(all(feature = "signal-cleanup", unix))]
+    pub fn cleanup_on_signal(&self) -> io::Result<()> {
+        registry::signal::install()?;
+        registry::register(self.path(), true, registry::Trigger::Signal);
+        Ok(())
+    }
+
+    /// Delete the temporary directory when the process exits, even if its destructor doesn't
+    /// run.
+    ///
+    /// Destructors don't run when the process exits through [`std::process::exit`] so, by
+    /// default, temporary directories are leaked in that case. This function registers the
+    /// directory with a process-global registry and installs an `atexit` hook (once) that deletes
+    /// all files and directories registered this way (but not those only registered with
+    /// `cleanup_on_signal`) that are still alive when the process exits. The directory is removed
+    /// from the registry when it's deleted or kept, so registration never causes a double-delete.
+    ///
+    /// This function is only available on Unix and Windows.
+    ///
+    /// **NOTE:** The directory is deleted at exit even if cleanup has been disabled with
+    /// [`TempDir::disable_cleanup`].
+    ///
+    /// # Errors
+    ///
+    /// If the exit hook cannot be installed, `Err` is returned.
+    #[cfg(any(unix, windows))]
+    pub fn cleanup_on_exit(&self) -> io::Result<()> {
+        registry::exit::install()?;
+        registry::register(self.path(), true, registry::Trigger::Exit);
+        Ok(())
+    }
+
+    /// Keep temporary file cleaners (e.g., `systemd-tmpfiles`) from deleting this directory or its
+    /// contents while they're still in use.
+    ///
+    /// This refreshes the access and modification times of the directory and everything inside
+    /// it now, and registers it with a background thread (started once) that refreshes them every
+    /// hour until the directory is deleted, persisted, or kept. See [`TempPath::keep_alive`].
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// # Errors
+    ///
+    /// If the background thread cannot be started, `Err` is returned.
+    ///
+    /// [`TempPath::keep_alive`]: crate::TempPath::keep_alive
+    #[cfg(unix)]
+    pub fn keep_alive(&self) -> io::Result<()> {
+        registry::keepalive::register(self.path(), true)
+    }
+
+    /// Disable cleanup of the temporary directory. If `disable_cleanup` is `true`, the temporary
+    /// directory will not be deleted when this `TempDir` is dropped. This method is equivalent
+    /// to calling [`Builder::disable_cleanup`] when creating the `TempDir`, but can be toggled at
+    /// any time (e.g., to keep artifacts around only if a later step fails).
+    ///
+    /// **NOTE:** this method is primarily useful for testing/debugging. If you want to simply
+    /// turn a temporary directory into a non-temporary directory, prefer [`TempDir::keep`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let mut tmp_dir = TempDir::new()?;
+    /// let path = tmp_dir.path().to_owned();
+    ///
+    /// // Something went wrong, keep the directory for inspection.
+    /// tmp_dir.disable_cleanup(true);
+    /// drop(tmp_dir);
+    /// assert!(path.exists());
+    /// # std::fs::remove_dir(path)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn disable_cleanup(&mut self, disable_cleanup: bool) {
+        self.keep = disable_cleanup;
+    }
+
+    /// Delete this directory on a background thread when the `TempDir` is dropped, instead of
+    /// blocking the dropping thread.
+    ///
+    /// See [`Builder::background_cleanup`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let mut tmp_dir = TempDir::new()?;
+    /// // ... fill it with lots of files ...
+    /// tmp_dir.background_cleanup(true);
+    /// drop(tmp_dir);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn background_cleanup(&mut self, background_cleanup: bool) {
+        self.background = background_cleanup;
+    }
+
+    /// Make the directory and everything inside it read-only, e.g., to keep a test fixture from
+    /// being modified by the code under test.
+    ///
+    /// Write permission is restored before the directory is deleted (or with
+    /// [`TempDir::unseal`]), so sealed directories are cleaned up as usual, even on Windows.
+    ///
+    /// On Unix, this clears the write bits of every file and directory. On Windows, it sets the
+    /// read-only attribute of every file (Windows ignores it on directories). Symbolic links are
+    /// not followed.
+    ///
+    /// # Errors
+    ///
+    /// If the permissions of some entry can't be changed, `Err` is returned. Some entries may have
+    /// been sealed already; they're still unsealed before cleanup.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let mut tmp_dir = TempDir::new()?;
+    /// let fixture = tmp_dir.path().join("fixture.txt");
+    /// std::fs::write(&fixture, "expected")?;
+    ///
+    /// tmp_dir.seal()?;
+    /// assert!(std::fs::metadata(&fixture)?.permissions().readonly());
+    ///
+    /// // Still deleted as usual.
+    /// tmp_dir.close()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn seal(&mut self) -> io::Result<()> {
+        self.sealed = true;
+        set_tree_readonly(self.path(), true)
+    }
+
+    /// Restore write permission to a directory sealed with [`TempDir::seal`].
+    ///
+    /// # Errors
+    ///
+    /// If the permissions of some entry can't be changed, `Err` is returned.
+    pub fn unseal(&mut self) -> io::Result<()> {
+        set_tree_readonly(self.path(), false)?;
+        self.sealed = false;
+        Ok(())
+    }
+
+    /// Closes and removes the temporary directory, returning a `Result`.
+    ///
+    /// Although `TempDir` removes the directory on drop, in the destructor
+    /// any errors are ignored. To detect errors cleaning up the temporary
+    /// directory, call `close` instead.
+    ///
+    /// # Errors
+    ///
+    /// This function may return a variety of [`std::io::Error`]s that result from deleting
+    /// the files and directories contained with the temporary directory,
+    /// as well as from deleting the temporary directory itself. These errors
+    /// may be platform specific.
+    ///
+    /// If only some entries could be removed, the returned error wraps a [`CleanupError`] listing
+    /// every entry that couldn't be removed. Use [`TempDir::try_cleanup`] to get at it directly.
+    ///
+    /// [`std::io::Error`]: http://doc.rust-lang.org/std/io/struct.Error.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use std::io::Write;
+    /// use tempfile::TempDir;
+    ///
+    /// // Create a directory inside of `env::temp_dir()`.
+    /// let tmp_dir = TempDir::new()?;
+    /// let file_path = tmp_dir.path().join("my-temporary-note.txt");
+    /// let mut tmp_file = File::create(file_path)?;
+    /// writeln!(tmp_file, "Brian was here. Briefly.")?;
+    ///
+    /// // By closing the `TempDir` explicitly we can check that it has
+    /// // been deleted successfully. If we don't close it explicitly,
+    /// // the directory will still be deleted when `tmp_dir` goes out
+    /// // of scope, but we won't know whether deleting the directory
+    /// // succeeded.
+    /// drop(tmp_file);
+    /// tmp_dir.close()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn close(self) -> io::Result<()> {
+        let result = self.remove();
+        self.finish_close(result)
+    }
+
+    /// Closes and removes the temporary directory, but only if it's empty.
+    ///
+    /// Unlike [`TempDir::close`], this never deletes anything inside the directory. To make the
+    /// destructor behave the same way, use [`Builder::recursive_cleanup`].
+    ///
+    /// # Errors
+    ///
+    /// If the directory isn't empty, it's left untouched and the returned error wraps a
+    /// [`CleanupError`] listing the leftover entries. `Err` is also returned if the directory can't
+    /// be read or deleted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// let file_path = tmp_dir.path().join("output.txt");
+    /// std::fs::write(&file_path, "results")?;
+    ///
+    /// // Move our output out of the directory before closing it.
+    /// # let final_path = tmp_dir.path().with_extension("out");
+    /// std::fs::rename(&file_path, &final_path)?;
+    /// tmp_dir.close_empty()?;
+    /// # std::fs::remove_file(final_path)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn close_empty(self) -> io::Result<()> {
+        let result = remove_empty(self.path());
+        self.finish_close(result)
+    }
+
+    /// Closes and removes the temporary directory like [`TempDir::close`], retrying if deletion
+    /// fails because some entry is temporarily in use.
+    ///
+    /// On Windows, antivirus and indexing services frequently open newly created files, causing
+    /// deletion to fail with `ERROR_SHARING_VIOLATION` or `ERROR_ACCESS_DENIED` for a short
+    /// while. This method retries deletion up to `retries` times when every failure is one of these
+    /// errors, sleeping for `backoff` before the first retry and doubling the delay after each
+    /// subsequent attempt.
+    ///
+    /// On other platforms, these failures aren't transient and this method behaves exactly like
+    /// [`TempDir::close`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from the last attempt if the directory still couldn't be removed. See
+    /// [`TempDir::close`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// // Retry up to 5 times, waiting 10ms, 20ms, 40ms, etc.
+    /// tmp_dir.close_with_retries(5, Duration::from_millis(10))?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn close_with_retries(self, retries: u32, backoff: Duration) -> io::Result<()> {
+        let mut delay = backoff;
+        let mut result = self.remove();
+        for _ in 0..retries {
+            match result {
+                Err(ref e) if is_transient(e) => {}
+                _ => break,
+            }
+            thread::sleep(delay);
+            delay = delay.saturating_mul(2);
+            result = self.remove();
+        }
+        self.finish_close(result)
+    }
+
+    /// Remove the directory and its contents, relative to the directory's handle if possible.
+    fn remove(&self) -> io::Result<()> {
+        if self.sealed {
+            // Best-effort: removal reports anything that's still read-only.
+            let _ = set_tree_readonly(self.path(), false);
+        }
+        if !self.recursive {
+            return remove_empty(self.path());
+        }
+        #[cfg(unix)]
+        return remove_at(self.backend, self.path(), self.handle.as_ref());
+        #[cfg(not(unix))]
+        return self.backend.0.remove_dir_all(self.path());
+    }
+
+    fn finish_close(mut self, result: io::Result<()>) -> io::Result<()> {
+        let result = match result {
+            // Cleanup errors already name the offending paths.
+            Err(e) if is_cleanup_error(&e) => Err(e),
+            result => result.with_err_path(|| self.path()),
+        };
+        registry::unregister(self.path());
+        if let Some(on_cleanup) = self.on_cleanup {
+            on_cleanup(self.path(), copy_result(&result));
+        }
+
+        // Set self.path to empty Box to release the memory, since an empty
+        // Box does not allocate any heap memory.
+        self.path = PathBuf::new().into_boxed_path();
+        #[cfg(unix)]
+        drop(self.handle.take());
+
+        // Prevent the Drop impl from being called.
+        mem::forget(self);
+
+        result
+    }
+
+    /// Attempts to remove the temporary directory, reporting every entry that couldn't be removed.
+    ///
+    /// Unlike [`TempDir::close`], this doesn't consume the `TempDir`: if cleanup fails, the
+    /// remaining entries can be dealt with (e.g., by closing whatever process holds them open) and
+    /// cleanup retried. On success, the `TempDir` no longer refers to anything: its path is empty
+    /// and dropping it is a no-op.
+    ///
+    /// If cleanup has been disabled (see [`TempDir::disable_cleanup`]), nothing is removed and
+    /// `Ok(())` is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CleanupError`] listing each path that couldn't be removed along with the
+    /// reason. Directories are only listed if none of their contents failed to be removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let mut tmp_dir = TempDir::new()?;
+    /// std::fs::write(tmp_dir.path().join("file.txt"), b"contents")?;
+    ///
+    /// if let Err(e) = tmp_dir.try_cleanup() {
+    ///     for (path, error) in e.failures() {
+    ///         eprintln!("failed to remove {}: {}", path.display(), error);
+    ///     }
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn try_cleanup(&mut self) -> Result<(), CleanupError> {
+        if self.keep {
+            return Ok(());
+        }
+        let result = self.remove();
+        if let Some(on_cleanup) = self.on_cleanup {
+            on_cleanup(self.path(), copy_result(&result));
+        }
+        match result {
+            Ok(()) => {
+                registry::unregister(self.path());
+                #[cfg(unix)]
+                drop(self.handle.take());
+                self.path = PathBuf::new().into_boxed_path();
+                self.keep = true;
+                Ok(())
+            }
+            Err(e) if is_cleanup_error(&e) => {
+                Err(*e.into_inner().unwrap().downcast::<CleanupError>().unwrap())
+            }
+            Err(e) => Err(CleanupError {
+                failures: vec![(self.path().to_owned(), e)],
+            }),
+        }
+    }
+}
+
+/// The disk usage of a temporary directory, returned by [`TempDir::total_size`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DirUsage {
+    bytes: u64,
+    files: u64,
+}
+
+impl DirUsage {
+    /// Returns the total size of the files, in bytes.
+    #[must_use]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Returns the number of files (including symbolic links), not counting directories.
+    #[must_use]
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+
+    fn add_dir(&mut self, dir: &Path) -> io::Result<()> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_err_path(|| dir),
+        };
+        for entry in entries {
+            let entry = entry.with_err_path(|| dir)?;
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e).with_err_path(|| entry.path()),
+            };
+            if metadata.is_dir() {
+                self.add_dir(&entry.path())?;
+            } else {
+                self.bytes += metadata.len();
+                self.files += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Options for opening a file with [`TempDir::open_in`].
+///
+/// These mirror [`std::fs::OpenOptions`] (which can't be inspected, and therefore can't be passed
+/// on to `openat`), and are validated the same way: a file must be opened for reading, writing,
+/// or appending; creating or truncating a file requires write access; and a file can't be both
+/// truncated and appended to.
+#[derive(Debug, Clone)]
+pub struct OpenInOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+    #[cfg(unix)]
+    mode: u32,
+}
+
+impl Default for OpenInOptions {
+    fn default() -> Self {
+        OpenInOptions::new()
+    }
+}
+
+impl OpenInOptions {
+    /// Create a blank set of options, with every option set to `false`.
+    #[must_use]
+    pub fn new() -> OpenInOptions {
+        OpenInOptions {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+            #[cfg(unix)]
+            mode: 0o666,
+        }
+    }
+
+    /// Open the file for reading. See [`OpenOptions::read`].
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// Open the file for writing. See [`OpenOptions::write`].
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// Open the file in append mode. See [`OpenOptions::append`].
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Truncate the file when opening it. See [`OpenOptions::truncate`].
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Create the file if it doesn't exist. See [`OpenOptions::create`].
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Create the file, failing if it already exists. See [`OpenOptions::create_new`].
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Set the permissions of newly created files, before the umask is applied.
+    ///
+    /// Default: `0o666`.
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    fn to_std(&self) -> OpenOptions {
+        let mut options = OpenOptions::new();
+        options
+            .read(self.read)
+            .write(self.write)
+            .append(self.append)
+            .truncate(self.truncate)
+            .create(self.create)
+            .create_new(self.create_new);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, self.mode);
+        options
+    }
+
+    /// Returns the `open` flags and mode for these options, validating them like `OpenOptions`.
+    #[cfg(unix)]
+    fn to_oflags(&self) -> io::Result<(rustix::fs::OFlags, rustix::fs::Mode)> {
+        use rustix::fs::{Mode, OFlags, RawMode};
+
+        let writable = self.write || self.append;
+        let invalid = |msg| Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        let mut flags = match (self.read, writable) {
+            (true, false) => OFlags::RDONLY,
+            (false, true) => OFlags::WRONLY,
+            (true, true) => OFlags::RDWR,
+            (false, false) => return invalid("the file must be opened for reading or writing"),
+        };
+        if (self.truncate || self.create || self.create_new) && !writable {
+            return invalid("creating or truncating a file requires write access");
+        }
+        if self.truncate && self.append {
+            return invalid("a file can't be both truncated and appended to");
+        }
+        if self.append {
+            flags |= OFlags::APPEND;
+        }
+        if self.create_new {
+            flags |= OFlags::CREATE | OFlags::EXCL;
+        } else {
+            if self.create {
+                flags |= OFlags::CREATE;
+            }
+            if self.truncate {
+                flags |= OFlags::TRUNC;
+            }
+        }
+        flags |= OFlags::CLOEXEC;
+        Ok((flags, Mode::from_raw_mode(self.mode as RawMode)))
+    }
+}
+
+/// Error returned when a temporary directory couldn't be completely removed.
+///
+/// Lists every entry that couldn't be removed along with the reason.
+pub struct CleanupError {
+    failures: Vec<(PathBuf, io::Error)>,
+}
+
+impl CleanupError {
+    /// Returns the entries that couldn't be removed, along with the reason.
+    pub fn failures(&self) -> &[(PathBuf, io::Error)] {
+        &self.failures
+    }
+
+    /// Consumes the error, returning the entries that couldn't be removed.
+    pub fn into_failures(self) -> Vec<(PathBuf, io::Error)> {
+        self.failures
+    }
+
+    /// Returns the kind of the first failure.
+    pub(crate) fn kind(&self) -> io::ErrorKind {
+        self.failures
+            .first()
+            .map_or(io::ErrorKind::Other, |(_, e)| e.kind())
+    }
+}
+
+impl From<CleanupError> for io::Error {
+    fn from(error: CleanupError) -> io::Error {
+        io::Error::new(error.kind(), error)
+    }
+}
+
+impl fmt::Debug for CleanupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CleanupError")
+            .field("failures", &self.failures)
+            .finish()
+    }
+}
+
+impl fmt::Display for CleanupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.failures.split_first() {
+            None => write!(f, "failed to remove temporary directory"),
+            Some(((path, error), rest)) => {
+                write!(f, "failed to remove {}: {}", path.display(), error)?;
+                if !rest.is_empty() {
+                    write!(f, " (and {} more)", rest.len())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CleanupError {}
+
+fn is_cleanup_error(error: &io::Error) -> bool {
+    error.get_ref().map_or(false, |e| e.is::<CleanupError>())
+}
+
+/// Returns `true` if `error` is (or only consists of) failures that are likely to go away on their
+/// own, e.g., because some other process briefly opened a file.
+#[cfg(windows)]
+fn is_transient(error: &io::Error) -> bool {
+    use windows_sys::Win32::Foundation::{ERROR_ACCESS_DENIED, ERROR_SHARING_VIOLATION};
+
+    if let Some(e) = error
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<CleanupError>())
+    {
+        return e.failures.iter().all(|(_, e)| is_transient(e));
+    }
+    matches!(
+        error.raw_os_error(),
+        Some(code) if code == ERROR_SHARING_VIOLATION as i32 || code == ERROR_ACCESS_DENIED as i32
+    )
+}
+
+#[cfg(not(windows))]
+fn is_transient(_error: &io::Error) -> bool {
+    false
+}
+
+/// Remove the directory at `path` and its contents.
+///
+/// When using the OS backend, the contents are removed relative to `handle` (a handle to the
+/// directory opened when it was created) so that cleanup can't be redirected by replacing the
+/// directory (or one of its ancestors) with a symlink or another directory. The directory itself is
+/// then removed at its current path, if it can be found.
+#[cfg(unix)]
+fn remove_at(backend: BackendRef, path: &Path, handle: Option<&OwnedFd>) -> io::Result<()> {
+    let handle = match handle {
+        Some(handle) if backend.is_os() => handle.as_fd(),
+        _ => return backend.0.remove_dir_all(path),
+    };
+    let mut failures = Vec::new();
+    imp::remove_contents_at(handle, path, &mut failures);
+    if !failures.is_empty() {
+        return Err(CleanupError { failures }.into());
+    }
+    match imp::current_path(handle, path) {
+        Some(current) => fs::remove_dir(current),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "temporary directory was moved or replaced",
+        )),
+    }
+}
+
+/// Recursively make `path` read-only (or writable again), without following symbolic links.
+///
+/// Directories are sealed after their contents and unsealed before them. Windows ignores the
+/// read-only attribute on directories, so they're only sealed on Unix.
+fn set_tree_readonly(path: &Path, readonly: bool) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path).with_err_path(|| path)?;
+    if metadata.is_dir() {
+        if !readonly && cfg!(unix) {
+            set_readonly(path, metadata.permissions(), false)?;
+        }
+        for entry in fs::read_dir(path).with_err_path(|| path)? {
+            set_tree_readonly(&entry.with_err_path(|| path)?.path(), readonly)?;
+        }
+        if readonly && cfg!(unix) {
+            set_readonly(path, metadata.permissions(), true)?;
+        }
+        Ok(())
+    } else if metadata.is_file() {
+        set_readonly(path, metadata.permissions(), readonly)
+    } else {
+        Ok(())
+    }
+}
+
+fn set_readonly(path: &Path, mut permissions: fs::Permissions, readonly: bool) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // Only give write permission back to the owner.
+        let mode = permissions.mode();
+        permissions.set_mode(if readonly {
+            mode & !0o222
+        } else {
+            mode | 0o200
+        });
+    }
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(readonly);
+    fs::set_permissions(path, permissions).with_err_path(|| path)
+}
+
+/// Delete the directory at `path` if it's empty, reporting every leftover entry as a
+/// [`CleanupError`] otherwise.
+fn remove_empty(path: &Path) -> io::Result<()> {
+    let failures = fs::read_dir(path)?
+        .map(|entry| {
+            entry.map(|entry| {
+                let error = io::Error::new(
+                    io::ErrorKind::Other,
+                    "left behind by a non-recursive cleanup",
+                );
+                (entry.path(), error)
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    if !failures.is_empty() {
+        return Err(CleanupError { failures }.into());
+    }
+    fs::remove_dir(path)
+}
+
+/// Recursively delete `path`, reporting every entry that couldn't be removed as a
+/// [`CleanupError`].
+pub(crate) fn remove_dir_all(path: &Path) -> io::Result<()> {
+    let error = match fs::remove_dir_all(path) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(e),
+        Err(e) => e,
+    };
+    // Go back over whatever is left, removing as much as we can and collecting the failures.
+    let mut failures = Vec::new();
+    remove_tree(path, &mut failures);
+    if failures.is_empty() {
+        // Something else removed the remaining entries, or the failure was transient.
+        return match fs::symlink_metadata(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            _ => Err(error),
+        };
+    }
+    Err(CleanupError { failures }.into())
+}
+
+fn remove_tree(path: &Path, failures: &mut Vec<(PathBuf, io::Error)>) {
+    let is_dir = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata.is_dir(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+        Err(e) => return failures.push((path.to_owned(), e)),
+    };
+    let result = if is_dir {
+        let before = failures.len();
+        match fs::read_dir(path) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => remove_tree(&entry.path(), failures),
+                        Err(e) => failures.push((path.to_owned(), e)),
+                    }
+                }
+            }
+            Err(e) => failures.push((path.to_owned(), e)),
+        }
+        if failures.len() != before {
+            // The directory can't be empty, so there's no point reporting it too.
+            return;
+        }
+        fs::remove_dir(path)
+    } else {
+        let result = fs::remove_file(path);
+        // Directory symlinks must be removed as directories on Windows.
+        #[cfg(windows)]
+        let result = result.or_else(|_| fs::remove_dir(path));
+        result
+    };
+    match result {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => failures.push((path.to_owned(), e)),
+        _ => {}
+    }
+}
+
+/// Error returned when persisting a temporary directory fails.
+#[derive(Debug)]
+pub struct DirPersistError {
+    /// The underlying IO error.
+    pub error: io::Error,
+    /// The temporary directory that couldn't be persisted.
+    pub dir: TempDir,
+    /// The path the temporary directory was to be persisted to.
+    pub target: PathBuf,
+}
+
+impl From<DirPersistError> for io::Error {
+    #[inline]
+    fn from(error: DirPersistError) -> io::Error {
+        error.error
+    }
+}
+
+impl From<DirPersistError> for TempDir {
+    #[inline]
+    fn from(error: DirPersistError) -> TempDir {
+        error.dir
+    }
+}
+
+impl fmt::Display for DirPersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        crate::file::fmt_persist_error(f, self.dir.path(), Some(&self.target), &self.error)
+    }
+}
+
+impl std::error::Error for DirPersistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        self.path()
     }
 }
 
@@ -483,18 +1915,530 @@ impl fmt::Debug for TempDir {
 
 impl Drop for TempDir {
     fn drop(&mut self) {
-        if !self.keep {
-            let _ = remove_dir_all(self.path());
+        // An empty path means the directory has already been cleaned up.
+        if !self.keep && !self.path.as_os_str().is_empty() && !env::retain_on_drop(self.path()) {
+            #[cfg(unix)]
+            let handle = self.handle.take();
+            let path = mem::replace(&mut self.path, PathBuf::new().into_boxed_path());
+            let (backend, recursive, sealed, on_cleanup) =
+                (self.backend, self.recursive, self.sealed, self.on_cleanup);
+            let cleanup = move || {
+                if sealed {
+                    let _ = set_tree_readonly(&path, false);
+                }
+                #[cfg(unix)]
+                let result = if recursive {
+                    remove_at(backend, &path, handle.as_ref())
+                } else {
+                    remove_empty(&path)
+                };
+                #[cfg(not(unix))]
+                let result = if recursive {
+                    backend.0.remove_dir_all(&path)
+                } else {
+                    remove_empty(&path)
+                };
+                crate::error::log_cleanup(&path, &result);
+                if let Some(on_cleanup) = on_cleanup {
+                    on_cleanup(&path, result);
+                }
+                // Stay registered until we're done so that interrupted deletions are finished on
+                // exit.
+                registry::unregister(&path);
+            };
+            if self.background {
+                run_in_background(Box::new(cleanup));
+            } else {
+                cleanup();
+            }
+        } else {
+            registry::unregister(self.path());
         }
     }
 }
 
-pub(crate) fn create(
-    path: PathBuf,
-    permissions: Option<&std::fs::Permissions>,
-    keep: bool,
-) -> io::Result<TempDir> {
-    imp::create(path, permissions, keep)
+/// A path to a temporary directory that is recursively deleted when dropped.
+///
+/// This is the directory counterpart of [`TempPath`](crate::TempPath): unlike [`TempDir`], it
+/// never holds any handles to the directory and can adopt an existing directory with
+/// [`TempDirPath::from_path`], e.g., one created by another library or process.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use tempfile::TempDirPath;
+///
+/// # let parent = tempfile::tempdir()?;
+/// let created = parent.path().join("created-elsewhere");
+/// fs::create_dir(&created)?;
+/// fs::write(created.join("file.txt"), "contents")?;
+///
+/// let dir = TempDirPath::from_path(&created);
+/// drop(dir);
+/// assert!(!created.exists());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct TempDirPath {
+    dir: TempDir,
+}
+
+impl TempDirPath {
+    /// Create a new `TempDirPath` from an existing path. This can be done even if no directory
+    /// exists at the given path.
+    pub fn from_path(path: impl Into<PathBuf>) -> TempDirPath {
+        TempDirPath {
+            dir: TempDir {
+                path: path.into().into_boxed_path(),
+                keep: false,
+                background: false,
+                recursive: true,
+                sealed: false,
+                on_cleanup: None,
+                backend: BackendRef::OS,
+                #[cfg(unix)]
+                handle: None,
+            },
+        }
+    }
+
+    /// Accesses the [`Path`] to the temporary directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Keep the temporary directory, returning the [`PathBuf`] where it is located.
+    ///
+    /// See [`TempDir::keep`].
+    #[must_use]
+    pub fn keep(self) -> PathBuf {
+        self.dir.keep()
+    }
+
+    /// Disable cleanup of the temporary directory. If `disable_cleanup` is `true`, the directory
+    /// will not be deleted when this `TempDirPath` is dropped.
+    ///
+    /// See [`TempDir::disable_cleanup`].
+    pub fn disable_cleanup(&mut self, disable_cleanup: bool) {
+        self.dir.disable_cleanup(disable_cleanup)
+    }
+
+    /// Closes and removes the temporary directory, returning a `Result`.
+    ///
+    /// See [`TempDir::close`].
+    ///
+    /// # Errors
+    ///
+    /// If the directory cannot be deleted, `Err` is returned.
+    pub fn close(self) -> io::Result<()> {
+        self.dir.close()
+    }
+}
+
+impl From<TempDir> for TempDirPath {
+    fn from(dir: TempDir) -> TempDirPath {
+        dir.into_temp_dir_path()
+    }
+}
+
+impl Deref for TempDirPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl AsRef<Path> for TempDirPath {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl fmt::Debug for TempDirPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.path().fmt(f)
+    }
+}
+
+/// A reference-counted temporary directory that is deleted when the last clone is dropped.
+///
+/// Created with [`TempDir::shared`]. Unlike an `Arc<TempDir>`, the last clone can still be closed
+/// with [`SharedTempDir::close`] to handle cleanup errors, or unwrapped back into a [`TempDir`]
+/// with [`SharedTempDir::try_unwrap`].
+pub struct SharedTempDir {
+    // Only `None` while being closed or unwrapped.
+    shared: Option<Arc<Shared>>,
+}
+
+struct Shared {
+    dir: TempDir,
+    // The number of `SharedTempDir`s that haven't been closed or dropped yet. Unlike the `Arc`'s
+    // strong count, this is decremented _before_ a clone lets go of the `Arc`, so exactly one
+    // closer sees it drop to zero.
+    clones: AtomicUsize,
+}
+
+impl SharedTempDir {
+    fn shared(&self) -> &Shared {
+        self.shared
+            .as_ref()
+            .expect("shared temporary directory already consumed")
+    }
+
+    /// Accesses the [`Path`] to the temporary directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.shared().dir.path()
+    }
+
+    /// Returns the number of `SharedTempDir`s referring to this temporary directory.
+    #[must_use]
+    pub fn clone_count(&self) -> usize {
+        self.shared().clones.load(Ordering::Acquire)
+    }
+
+    /// Returns the underlying [`TempDir`] if this is the last clone, or `self` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// If other clones still exist, `self` is returned unchanged.
+    pub fn try_unwrap(mut self) -> Result<TempDir, SharedTempDir> {
+        let shared = self
+            .shared
+            .take()
+            .expect("shared temporary directory already consumed");
+        match Arc::try_unwrap(shared) {
+            Ok(shared) => Ok(shared.dir),
+            Err(shared) => {
+                self.shared = Some(shared);
+                Err(self)
+            }
+        }
+    }
+
+    /// Closes this clone. If it was the last one, the temporary directory is removed and any
+    /// error is returned; otherwise, the directory is left for the remaining clones and `Ok` is
+    /// returned.
+    ///
+    /// Even if several clones are closed concurrently, exactly one of them removes the directory
+    /// and reports the result.
+    ///
+    /// See [`TempDir::close`].
+    ///
+    /// # Errors
+    ///
+    /// If this was the last clone and the directory cannot be deleted, `Err` is returned.
+    pub fn close(mut self) -> io::Result<()> {
+        let mut shared = self
+            .shared
+            .take()
+            .expect("shared temporary directory already consumed");
+        if shared.clones.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return Ok(());
+        }
+        // This was the last clone, but the others may not have released the `Arc` yet. They're
+        // about to, without blocking.
+        loop {
+            match Arc::try_unwrap(shared) {
+                Ok(shared) => return shared.dir.close(),
+                Err(again) => {
+                    shared = again;
+                    thread::yield_now();
+                }
+            }
+        }
+    }
+}
+
+impl Clone for SharedTempDir {
+    fn clone(&self) -> SharedTempDir {
+        self.shared().clones.fetch_add(1, Ordering::Relaxed);
+        SharedTempDir {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for SharedTempDir {
+    fn drop(&mut self) {
+        // The directory is removed when the last `Arc` is dropped.
+        if let Some(shared) = self.shared.take() {
+            shared.clones.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+impl From<TempDir> for SharedTempDir {
+    fn from(dir: TempDir) -> SharedTempDir {
+        dir.shared()
+    }
+}
+
+impl Deref for SharedTempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl AsRef<Path> for SharedTempDir {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl fmt::Debug for SharedTempDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedTempDir")
+            .field("path", &self.path())
+            .field("clone_count", &self.clone_count())
+            .finish()
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Run `job` on the shared background cleanup thread, falling back on running it on the current
+/// thread if the background thread couldn't be started (or has died).
+fn run_in_background(job: Job) {
+    static QUEUE: OnceLock<Mutex<mpsc::Sender<Job>>> = OnceLock::new();
+
+    let queue = QUEUE.get_or_try_init(|| {
+        let (tx, rx) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("tempfile-cleanup".into())
+            .spawn(move || rx.into_iter().for_each(|job| job()))?;
+        Ok::<_, io::Error>(Mutex::new(tx))
+    });
+    let job = match queue {
+        Ok(queue) => match queue.lock().unwrap_or_else(|e| e.into_inner()).send(job) {
+            Ok(()) => return,
+            Err(mpsc::SendError(job)) => job,
+        },
+        Err(_) => job,
+    };
+    job()
+}
+
+fn copy_contents(src: &Path, dst: &Path) -> io::Result<()> {
+    let root = fs::canonicalize(src).with_err_path(|| src)?;
+    copy_tree(src, dst, &mut vec![root])
+}
+
+/// Copy the contents of `src` into `dst`. `ancestors` holds the canonical paths of `src` and the
+/// directories containing it, to detect symbolic link loops on platforms where links are followed.
+fn copy_tree(src: &Path, dst: &Path, ancestors: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(src).with_err_path(|| src)? {
+        let entry = entry.with_err_path(|| src)?;
+        let (from, to) = (entry.path(), dst.join(entry.file_name()));
+        #[cfg(unix)]
+        if entry.file_type().with_err_path(|| &from)?.is_symlink() {
+            let target = fs::read_link(&from).with_err_path(|| &from)?;
+            clear_destination(&to, false)?;
+            std::os::unix::fs::symlink(target, &to).with_err_path(|| &to)?;
+            continue;
+        }
+        let metadata = fs::metadata(&from).with_err_path(|| &from)?;
+        if metadata.is_dir() {
+            let canonical = fs::canonicalize(&from).with_err_path(|| &from)?;
+            if ancestors.contains(&canonical) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "symbolic link loop in template directory",
+                ))
+                .with_err_path(|| &from);
+            }
+            clear_destination(&to, true)?;
+            fs::create_dir_all(&to).with_err_path(|| &to)?;
+            ancestors.push(canonical);
+            let result = copy_tree(&from, &to, ancestors);
+            ancestors.pop();
+            result?;
+            // Set permissions last in case the directory is read-only.
+            fs::set_permissions(&to, metadata.permissions()).with_err_path(|| &to)?;
+        } else {
+            // Don't write through a symbolic link at the destination. This also copies the
+            // permissions.
+            clear_destination(&to, false)?;
+            fs::copy(&from, &to).with_err_path(|| &from)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove whatever is at `path` so that it can be replaced, without following symbolic links. An
+/// existing directory is kept if `keep_dir` is set, so that its contents are merged.
+fn clear_destination(path: &Path, keep_dir: bool) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_err_path(|| path),
+    };
+    // On Windows, symbolic links to directories are removed as directories.
+    #[cfg(windows)]
+    let is_dir_link = {
+        use std::os::windows::fs::MetadataExt;
+        metadata.file_type().is_symlink()
+            && metadata.file_attributes()
+                & windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_DIRECTORY
+                != 0
+    };
+    #[cfg(not(windows))]
+    let is_dir_link = false;
+    let result = if metadata.is_dir() {
+        if keep_dir {
+            return Ok(());
+        }
+        fs::remove_dir_all(path)
+    } else if is_dir_link {
+        fs::remove_dir(path)
+    } else {
+        fs::remove_file(path)
+    };
+    result.with_err_path(|| path)
+}
+
+pub(crate) fn create(path: PathBuf, builder: &Builder<'_, '_>) -> io::Result<TempDir> {
+    if let Some(subdir) = builder
+        .subdirs
+        .iter()
+        .find(|subdir| !is_relative_subdir(subdir))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "subdirectories must be relative paths inside the temporary directory",
+        ))
+        .with_err_path(|| subdir);
+    }
+    #[cfg(windows)]
+    let result = match crate::security::SecurityDescriptor::new(&builder.security)? {
+        Some(descriptor) => crate::security::create_dir(&path, &descriptor).with_err_path(|| &path),
+        None => builder
+            .backend
+            .0
+            .create_dir(&path, builder.permissions_for_dir().as_ref()),
+    };
+    #[cfg(not(windows))]
+    let result = builder
+        .backend
+        .0
+        .create_dir(&path, builder.permissions_for_dir().as_ref());
+    result?;
+    if let Err(e) = init_dir(&path, builder).and_then(|()| create_subdirs(&path, builder)) {
+        let _ = builder.backend.0.remove_dir_all(&path);
+        return Err(e);
+    }
+    Ok(adopt(path, builder))
+}
+
+/// Take ownership of the newly created directory at `path`, with the options from `builder`.
+pub(crate) fn adopt(path: PathBuf, builder: &Builder<'_, '_>) -> TempDir {
+    TempDir {
+        // Custom backends may not create real directories, so this is best-effort.
+        #[cfg(unix)]
+        handle: imp::open(&path).ok(),
+        path: path.into_boxed_path(),
+        keep: builder.keep,
+        background: builder.background_cleanup,
+        recursive: builder.recursive_cleanup,
+        sealed: false,
+        on_cleanup: builder.on_cleanup,
+        backend: builder.backend,
+    }
+}
+
+/// Remove whatever a failed [`Builder::make_dir`] closure left behind at `path`, ignoring errors.
+///
+/// `path` must not have existed before the closure was called. To avoid deleting a directory
+/// created concurrently by someone else (e.g., if the closure failed because the name was taken),
+/// nothing is removed unless `path` is a directory owned by the current user, where that can be
+/// checked.
+pub(crate) fn discard(path: &Path, builder: &Builder<'_, '_>) {
+    let is_ours = fs::symlink_metadata(path).map_or(false, |meta| {
+        meta.is_dir() && imp::is_owned(&meta).unwrap_or(true)
+    });
+    if is_ours {
+        match builder.backend.0.remove_dir_all(path) {
+            Ok(()) => log!(debug, "deleted {}", path.display()),
+            Err(e) => log!(warn, "failed to delete {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Open `rel` (a path checked with [`is_relative_subdir`]) relative to `dir` one component at a
+/// time, without following symbolic links.
+#[cfg(unix)]
+fn open_beneath(
+    dir: BorrowedFd<'_>,
+    rel: &Path,
+    flags: rustix::fs::OFlags,
+    mode: rustix::fs::Mode,
+) -> rustix::io::Result<OwnedFd> {
+    use rustix::fs::{openat, Mode, OFlags};
+
+    let mut names: Vec<_> = rel
+        .components()
+        .filter_map(|c| match c {
+            path::Component::Normal(name) => Some(name),
+            _ => None,
+        })
+        .collect();
+    let last = names.pop().unwrap_or_else(|| OsStr::new("."));
+    let mut parent = None;
+    for name in names {
+        let at = parent.as_ref().map_or(dir, |fd: &OwnedFd| fd.as_fd());
+        let flags = OFlags::RDONLY | OFlags::DIRECTORY | OFlags::NOFOLLOW | OFlags::CLOEXEC;
+        parent = Some(openat(at, name, flags, Mode::empty())?);
+    }
+    let at = parent.as_ref().map_or(dir, |fd| fd.as_fd());
+    openat(at, last, flags | OFlags::NOFOLLOW, mode)
+}
+
+/// Returns `true` if `subdir` is a non-empty relative path that doesn't escape its parent.
+fn is_relative_subdir(subdir: &Path) -> bool {
+    subdir.components().next().is_some()
+        && subdir
+            .components()
+            .all(|c| matches!(c, path::Component::Normal(_) | path::Component::CurDir))
+}
+
+/// Apply the ownership and exact permissions configured in `builder` to the newly created
+/// directory at `path`.
+fn init_dir(path: &Path, builder: &Builder<'_, '_>) -> io::Result<()> {
+    // Change the owner first as it may clear the setuid and setgid bits.
+    #[cfg(unix)]
+    if builder.owner.is_some() || builder.group.is_some() {
+        builder.apply_owner(imp::open(path)?.as_fd(), path)?;
+    }
+    builder.apply_exact_permissions(
+        path,
+        builder.permissions_for_dir().as_ref(),
+        |permissions| fs::set_permissions(path, permissions),
+    )
+}
+
+/// Create the subdirectories configured with [`Builder::subdirs`] inside `path`.
+fn create_subdirs(path: &Path, builder: &Builder<'_, '_>) -> io::Result<()> {
+    for subdir in &builder.subdirs {
+        let mut current = path.to_owned();
+        for component in subdir.components() {
+            current.push(component);
+            match builder
+                .backend
+                .0
+                .create_dir(&current, builder.permissions_for_dir().as_ref())
+            {
+                Ok(()) => init_dir(&current, builder)?,
+                // Shared parents and duplicates.
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists && current.is_dir() => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Ok(())
 }
 
-mod imp;
+pub(crate) mod imp;
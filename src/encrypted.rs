@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use chacha20::cipher::zeroize::{Zeroize, Zeroizing};
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::ChaCha20;
+
+use crate::{env, tempfile_in, SpooledTempFile};
+
+/// The number of bytes encrypted per write, and the size of the chunks that are re-encrypted
+/// under a fresh nonce when they're overwritten.
+const CHUNK_SIZE: usize = 8192;
+
+/// A temporary file whose contents are encrypted with an ephemeral key before they're written.
+///
+/// Every `EncryptedTempFile` generates a random key that only lives in memory, and encrypts the
+/// data with the ChaCha20 stream cipher before writing it to the underlying temporary file. Data
+/// is decrypted when it's read back, so the file behaves like any other file through [`Read`],
+/// [`Write`], and [`Seek`], but only ciphertext ever reaches the disk. When the
+/// `EncryptedTempFile` is dropped, the key is zeroed and the contents can't be recovered even if
+/// the file itself is left behind (e.g., on a crash or in a filesystem snapshot).
+///
+/// The underlying file is either an anonymous temporary file ([`EncryptedTempFile::new`]) or a
+/// [`SpooledTempFile`] ([`EncryptedTempFile::spooled`]), which keeps small files in memory.
+///
+/// This type is only available when the `encryption` feature is enabled.
+///
+/// # Security
+///
+/// This protects the confidentiality of the data at rest, not its integrity: modifications of
+/// the underlying file aren't detected and decrypt to garbage. The key is generated with the
+/// operating system's secure random number generator. Files are limited to 256GiB; reading or
+/// writing past that fails.
+///
+/// The file is encrypted in 8KiB chunks. Overwriting data decrypts the chunk it's in and encrypts
+/// it again under a fresh nonce, so a keystream is never reused for different data. The nonces
+/// are only kept in memory, at the cost of a few bytes per chunk that has been overwritten. If
+/// overwriting fails part-way, the rest of the chunk may read back as garbage too.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::EncryptedTempFile;
+/// use std::io::{Read, Seek, SeekFrom, Write};
+///
+/// let mut file = EncryptedTempFile::new()?;
+/// file.write_all(b"secret")?;
+///
+/// file.seek(SeekFrom::Start(0))?;
+/// let mut contents = String::new();
+/// file.read_to_string(&mut contents)?;
+/// assert_eq!(contents, "secret");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct EncryptedTempFile<F = File> {
+    inner: F,
+    key: Zeroizing<[u8; 32]>,
+    nonce: [u8; 12],
+    // How many times each chunk has been overwritten, if at all.
+    generations: HashMap<u64, u32>,
+    pos: u64,
+    len: u64,
+}
+
+impl EncryptedTempFile {
+    /// Create an encrypted anonymous temporary file in [`env::temp_dir()`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or the key can't be generated, `Err` is returned.
+    pub fn new() -> io::Result<EncryptedTempFile> {
+        EncryptedTempFile::new_in(env::temp_dir())
+    }
+
+    /// Create an encrypted anonymous temporary file in the specified directory.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or the key can't be generated, `Err` is returned.
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> io::Result<EncryptedTempFile> {
+        EncryptedTempFile::wrap(tempfile_in(dir)?)
+    }
+}
+
+impl EncryptedTempFile<SpooledTempFile> {
+    /// Create an encrypted spooled temporary file, keeping up to `max_size` bytes in memory before
+    /// rolling over to disk.
+    ///
+    /// See [`SpooledTempFile::new`] for details. The data is encrypted in memory too.
+    ///
+    /// # Errors
+    ///
+    /// If the key can't be generated, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::EncryptedTempFile;
+    /// use std::io::Write;
+    ///
+    /// let mut file = EncryptedTempFile::spooled(15)?;
+    /// file.write_all(b"short")?;
+    /// assert!(!file.as_inner().is_rolled());
+    /// assert_ne!(file.as_inner().as_slice(), Some(&b"short"[..]));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn spooled(max_size: usize) -> io::Result<EncryptedTempFile<SpooledTempFile>> {
+        EncryptedTempFile::wrap(SpooledTempFile::new(max_size))
+    }
+}
+
+impl<F> EncryptedTempFile<F> {
+    /// Wrap an empty file, generating a new key.
+    fn wrap(inner: F) -> io::Result<EncryptedTempFile<F>> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        let mut nonce = [0u8; 12];
+        if let Err(e) = getrandom::fill(&mut *key).and_then(|()| getrandom::fill(&mut nonce)) {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("failed to generate an encryption key: {}", e),
+            ));
+        }
+        Ok(EncryptedTempFile {
+            inner,
+            key,
+            nonce,
+            generations: HashMap::new(),
+            pos: 0,
+            len: 0,
+        })
+    }
+
+    /// Get a reference to the underlying file, which holds the encrypted contents.
+    ///
+    /// Reading, writing, or seeking the underlying file directly (e.g., through `&File`)
+    /// desynchronizes it from the `EncryptedTempFile` and corrupts the contents.
+    pub fn as_inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// Encrypt or decrypt `buf` in place, as the data at `pos`.
+    fn apply_keystream(&self, pos: u64, buf: &mut [u8]) -> io::Result<()> {
+        let mut done = 0;
+        while done < buf.len() {
+            let at = pos + done as u64;
+            let index = at / CHUNK_SIZE as u64;
+            let n = (buf.len() - done).min(CHUNK_SIZE - (at % CHUNK_SIZE as u64) as usize);
+            // Each generation of a chunk gets its own nonce.
+            let mut nonce = self.nonce;
+            for (b, g) in nonce.iter_mut().zip(self.generation(index).to_le_bytes()) {
+                *b ^= g;
+            }
+            let mut cipher = ChaCha20::new(&(*self.key).into(), &nonce.into());
+            cipher
+                .try_seek(at)
+                .and_then(|()| cipher.try_apply_keystream(&mut buf[done..done + n]))
+                .map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::Other,
+                        "encrypted temporary files are limited to 256GiB",
+                    )
+                })?;
+            done += n;
+        }
+        Ok(())
+    }
+
+    /// The number of times the chunk at `index` has been overwritten.
+    fn generation(&self, index: u64) -> u32 {
+        self.generations.get(&index).copied().unwrap_or(0)
+    }
+}
+
+impl<F: fmt::Debug> fmt::Debug for EncryptedTempFile<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedTempFile")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: Read> Read for EncryptedTempFile<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        let result = self.apply_keystream(self.pos, &mut buf[..n]);
+        // Keep our position in sync with the underlying file's even if decrypting fails, but don't
+        // hand out the ciphertext.
+        self.pos += n as u64;
+        if let Err(e) = result {
+            buf[..n].zeroize();
+            return Err(e);
+        }
+        Ok(n)
+    }
+}
+
+impl<F: Read + Write + Seek> EncryptedTempFile<F> {
+    /// Fill the gap between the end of the file and the current position with encrypted zeros,
+    /// so it reads back as zeros like in any other file.
+    fn fill_gap(&mut self) -> io::Result<()> {
+        self.inner.seek(SeekFrom::Start(self.len))?;
+        let mut chunk = [0u8; CHUNK_SIZE];
+        while self.len < self.pos {
+            let n = (self.pos - self.len).min(CHUNK_SIZE as u64) as usize;
+            let chunk = &mut chunk[..n];
+            chunk.fill(0);
+            self.apply_keystream(self.len, chunk)?;
+            self.inner.write_all(chunk)?;
+            self.len += n as u64;
+        }
+        Ok(())
+    }
+
+    /// Overwrite existing data at the current position with `buf` (up to the end of its chunk),
+    /// re-encrypting the whole chunk under a new nonce.
+    fn overwrite(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut chunk = [0u8; CHUNK_SIZE];
+        let result = self.overwrite_with(&mut chunk, buf);
+        // Don't leave plaintext behind on the stack.
+        chunk.zeroize();
+        let written = result?;
+        self.pos += written as u64;
+        self.len = self.len.max(self.pos);
+        Ok(written)
+    }
+
+    fn overwrite_with(&mut self, chunk: &mut [u8; CHUNK_SIZE], buf: &[u8]) -> io::Result<usize> {
+        let index = self.pos / CHUNK_SIZE as u64;
+        let start = index * CHUNK_SIZE as u64;
+        let offset = (self.pos - start) as usize;
+        let n = buf.len().min(CHUNK_SIZE - offset);
+        let old_len = (self.len - start).min(CHUNK_SIZE as u64) as usize;
+        let new_len = old_len.max(offset + n);
+        let generation = self.generation(index).checked_add(1).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "encrypted temporary file chunk overwritten too many times",
+            )
+        })?;
+
+        self.inner.seek(SeekFrom::Start(start))?;
+        self.inner.read_exact(&mut chunk[..old_len])?;
+        self.apply_keystream(start, &mut chunk[..old_len])?;
+        chunk[offset..offset + n].copy_from_slice(&buf[..n]);
+        self.generations.insert(index, generation);
+        self.apply_keystream(start, &mut chunk[..new_len])?;
+        self.inner.seek(SeekFrom::Start(start))?;
+        self.inner.write_all(&chunk[..new_len])?;
+        self.inner.seek(SeekFrom::Start(self.pos + n as u64))?;
+        Ok(n)
+    }
+}
+
+impl<F: Read + Write + Seek> Write for EncryptedTempFile<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.pos > self.len {
+            if let Err(e) = self.fill_gap() {
+                // Keep the underlying file's cursor in sync with ours.
+                let _ = self.inner.seek(SeekFrom::Start(self.pos));
+                return Err(e);
+            }
+        }
+        if self.pos < self.len {
+            let result = self.overwrite(buf);
+            if result.is_err() {
+                // Keep the underlying file's cursor in sync with ours.
+                let _ = self.inner.seek(SeekFrom::Start(self.pos));
+            }
+            return result;
+        }
+        let mut chunk = [0u8; CHUNK_SIZE];
+        let n = buf.len().min(CHUNK_SIZE);
+        chunk[..n].copy_from_slice(&buf[..n]);
+        let result = self
+            .apply_keystream(self.pos, &mut chunk[..n])
+            .and_then(|()| self.inner.write(&chunk[..n]));
+        // Don't leave plaintext behind on the stack (e.g., if encrypting fails).
+        chunk.zeroize();
+        let written = result?;
+        self.pos += written as u64;
+        self.len = self.len.max(self.pos);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<F: Seek> Seek for EncryptedTempFile<F> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = self.inner.seek(pos)?;
+        Ok(self.pos)
+    }
+}
@@ -1,10 +1,28 @@
+use std::cell::RefCell;
 use std::env;
+use std::fs::{self, OpenOptions};
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
 
 // Once rust 1.70 is wide-spread (Debian stable), we can use OnceLock from stdlib.
 use once_cell::sync::OnceCell as OnceLock;
 
+use crate::error::{Error, IoResultExt};
+use crate::{util, Builder};
+
 static DEFAULT_TEMPDIR: OnceLock<PathBuf> = OnceLock::new();
+static TEMPDIR_CANDIDATES: OnceLock<Vec<PathBuf>> = OnceLock::new();
+static CHOSEN_CANDIDATE: OnceLock<PathBuf> = OnceLock::new();
+static GUARDED_TEMPDIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+static DEFAULT_BUILDER: OnceLock<Builder<'static, 'static>> = OnceLock::new();
+
+thread_local! {
+    static SCOPED_TEMPDIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+static KEEP_TEMPFILES: OnceLock<AtomicBool> = OnceLock::new();
+static KEEP_TEMPFILES_VERBOSE: OnceLock<AtomicBool> = OnceLock::new();
 
 /// Override the default temporary directory (defaults to [`std::env::temp_dir`]). This function
 /// changes the _global_ default temporary directory for the entire program and should not be called
@@ -28,17 +46,466 @@ pub fn override_temp_dir(path: &Path) -> Result<(), PathBuf> {
     }
 }
 
+/// Configure an ordered list of candidate default temporary directories (e.g.,
+/// `$XDG_RUNTIME_DIR`, `/tmp`, and the current directory) for environments where the platform's
+/// temporary directory may be read-only or missing.
+///
+/// The first time the default temporary directory is needed, each candidate is checked in order
+/// (see [`validate_temp_dir`]) and the first usable one is chosen and cached for the rest of the
+/// program. If none are usable, the platform's temporary directory ([`std::env::temp_dir`]) is
+/// used and the candidates are checked again next time. Explicit overrides
+/// ([`override_temp_dir`], [`override_temp_dir_guarded`], and [`with_temp_dir`]) take
+/// precedence over the candidates.
+///
+/// Only the first call to this function will succeed. All further calls will fail with
+/// `Err(candidates)` where `candidates` are the previously set candidates.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::PathBuf;
+/// use tempfile::env;
+///
+/// let mut candidates = Vec::new();
+/// if let Some(runtime_dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+///     candidates.push(PathBuf::from(runtime_dir));
+/// }
+/// candidates.push(PathBuf::from("/tmp"));
+/// candidates.push(std::env::current_dir()?);
+/// env::set_temp_dir_candidates(candidates).expect("candidates already set");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn set_temp_dir_candidates<I>(candidates: I) -> Result<(), Vec<PathBuf>>
+where
+    I: IntoIterator,
+    I::Item: Into<PathBuf>,
+{
+    let mut we_set = false;
+    let val = TEMPDIR_CANDIDATES.get_or_init(|| {
+        we_set = true;
+        candidates.into_iter().map(Into::into).collect()
+    });
+    if we_set {
+        Ok(())
+    } else {
+        Err(val.to_owned())
+    }
+}
+
+/// Returns the first usable candidate set with [`set_temp_dir_candidates`], if any.
+fn chosen_candidate() -> Option<&'static PathBuf> {
+    let candidates = TEMPDIR_CANDIDATES.get()?;
+    CHOSEN_CANDIDATE
+        .get_or_try_init(|| {
+            candidates
+                .iter()
+                .find(|dir| validate_dir(dir).is_ok())
+                .cloned()
+                .ok_or(())
+        })
+        .ok()
+}
+
+/// Override the default temporary directory until the returned guard is dropped.
+///
+/// Unlike [`override_temp_dir`], this function can be called any number of times: each call
+/// takes precedence over [`override_temp_dir`] and any previous guarded override, and dropping the
+/// returned guard restores the previous default. Like [`override_temp_dir`], this changes the
+/// _global_ default temporary directory for the entire program. To only affect the current
+/// thread, use [`with_temp_dir`] instead.
+///
+/// Guards should be dropped in the reverse order of their creation (e.g., by keeping them in
+/// nested scopes); otherwise, dropping a guard restores a default that another live guard meant to
+/// replace.
+///
+/// **NOTE:** This function does not check if the specified directory exists and/or is writable.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::{env, tempdir};
+///
+/// let dir = tempdir()?;
+/// {
+///     let _guard = env::override_temp_dir_guarded(dir.path());
+///     assert_eq!(env::temp_dir(), dir.path());
+/// }
+/// assert_ne!(env::temp_dir(), dir.path());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn override_temp_dir_guarded(path: impl AsRef<Path>) -> TempDirOverrideGuard {
+    let mut guarded = GUARDED_TEMPDIR.write().unwrap_or_else(|e| e.into_inner());
+    let previous = guarded.replace(path.as_ref().to_owned());
+    TempDirOverrideGuard { previous }
+}
+
+/// Restores the previous default temporary directory when dropped.
+///
+/// See [`override_temp_dir_guarded`].
+#[derive(Debug)]
+#[must_use = "the override is reverted as soon as the guard is dropped"]
+pub struct TempDirOverrideGuard {
+    previous: Option<PathBuf>,
+}
+
+impl Drop for TempDirOverrideGuard {
+    fn drop(&mut self) {
+        let mut guarded = GUARDED_TEMPDIR.write().unwrap_or_else(|e| e.into_inner());
+        *guarded = self.previous.take();
+    }
+}
+
+/// Run `f` with the default temporary directory overridden on the current thread.
+///
+/// Unlike [`override_temp_dir`], this only affects the current thread for the duration of `f` and
+/// can be called any number of times (and nested). This is primarily useful for test frameworks
+/// that want to isolate each test's temporary files. Temporary files and directories created by
+/// other threads (including threads spawned by `f`) are unaffected.
+///
+/// The previous override (if any) is restored when `f` returns or panics.
+///
+/// **NOTE:** This function does not check if the specified directory exists and/or is writable.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::{env, tempdir, NamedTempFile};
+///
+/// let dir = tempdir()?;
+/// let file = env::with_temp_dir(dir.path(), NamedTempFile::new)?;
+/// assert!(file.path().starts_with(dir.path()));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn with_temp_dir<R>(path: impl AsRef<Path>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<PathBuf>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            SCOPED_TEMPDIR.with(|dir| *dir.borrow_mut() = previous);
+        }
+    }
+
+    let previous = SCOPED_TEMPDIR.with(|dir| dir.replace(Some(path.as_ref().to_owned())));
+    let _restore = Restore(previous);
+    f()
+}
+
 /// Returns the default temporary directory, used for both temporary directories and files if no
 /// directory is explicitly specified.
 ///
 /// This function simply delegates to [`std::env::temp_dir`] unless the default temporary directory
-/// has been override by a call to [`override_temp_dir`].
+/// has been overridden by a call to [`override_temp_dir`], [`override_temp_dir_guarded`] or, on the
+/// current thread, [`with_temp_dir`], or a usable candidate has been configured with
+/// [`set_temp_dir_candidates`].
 ///
-/// **NOTE:** This function does check if the returned directory exists and/or is writable.
+/// The platform's temporary directory isn't cached: changes to the environment (e.g., `TMPDIR` on
+/// Unix) at runtime take effect immediately, so there's nothing to refresh. Note, however, that
+/// modifying the environment is inherently racy in multi-threaded programs; prefer
+/// [`override_temp_dir_guarded`] or [`with_temp_dir`] where possible.
+///
+/// **NOTE:** This function does not check if the returned directory exists and/or is writable.
 pub fn temp_dir() -> PathBuf {
+    if let Some(dir) = SCOPED_TEMPDIR.with(|dir| dir.borrow().clone()) {
+        return dir;
+    }
+    if let Some(dir) = &*GUARDED_TEMPDIR.read().unwrap_or_else(|e| e.into_inner()) {
+        return dir.clone();
+    }
     DEFAULT_TEMPDIR
         .get()
+        .or_else(chosen_candidate)
         .map(|p| p.to_owned())
         // Don't cache this in case the user uses std::env::set to change the temporary directory.
         .unwrap_or_else(env::temp_dir)
 }
+
+/// Override the default [`Builder`] settings (i.e., the settings of [`Builder::new`]). Like
+/// [`override_temp_dir`], this changes the _global_ defaults for the entire program, including
+/// temporary files and directories created by dependencies through [`NamedTempFile::new`],
+/// [`tempdir`], etc. This is intended for program-wide policies (e.g., "all temporary files must
+/// be `0o600` and carry our application's suffix") and should not be called by libraries.
+///
+/// Settings explicitly configured on a `Builder` still take precedence over the defaults.
+/// Anonymous temporary files created by [`tempfile()`](crate::tempfile()) have no name and aren't
+/// affected.
+///
+/// Only the first call to this function will succeed. All further calls will fail with
+/// `Err(builder)` where `builder` is the previously set default `Builder`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tempfile::{env, Builder};
+///
+/// let mut defaults = Builder::new();
+/// defaults.prefix("myapp-").suffix(".tmp").rand_bytes(10);
+/// env::override_default_builder(defaults).expect("defaults already set");
+/// ```
+///
+/// [`NamedTempFile::new`]: crate::NamedTempFile::new
+/// [`tempdir`]: crate::tempdir()
+pub fn override_default_builder(
+    builder: Builder<'static, 'static>,
+) -> Result<(), Box<Builder<'static, 'static>>> {
+    let mut we_set = false;
+    let val = DEFAULT_BUILDER.get_or_init(|| {
+        we_set = true;
+        builder
+    });
+    if we_set {
+        Ok(())
+    } else {
+        Err(Box::new(val.clone()))
+    }
+}
+
+/// Returns the default `Builder` set by [`override_default_builder`], if any.
+pub(crate) fn default_builder() -> Option<&'static Builder<'static, 'static>> {
+    DEFAULT_BUILDER.get()
+}
+
+/// Returns a private, per-application (and, on Unix, per-user) directory inside the default
+/// temporary directory ([`temp_dir`]), creating it if necessary.
+///
+/// The directory is named `{app}-{uid}` on Unix and `{app}` elsewhere (where the default
+/// temporary directory is usually already per-user). On Unix, it's created with `0o700`
+/// permissions. If it already exists, it's checked to be a directory (not a symbolic link) owned
+/// by the current user and inaccessible to other users; otherwise, another user could have
+/// created it in advance to tamper with the application's temporary files.
+///
+/// To place all of the application's temporary files and directories in this directory by
+/// default, pass it to [`override_temp_dir`].
+///
+/// The directory isn't deleted automatically; it's meant to outlive the process.
+///
+/// # Errors
+///
+/// If `app` isn't a valid file name, the directory can't be created, or it already exists but
+/// isn't private, `Err` is returned.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tempfile::{app_temp_dir, env, NamedTempFile};
+///
+/// let dir = app_temp_dir("myapp")?;
+/// env::override_temp_dir(&dir).expect("temporary directory already overridden");
+///
+/// let file = NamedTempFile::new()?;
+/// assert!(file.path().starts_with(&dir));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn app_temp_dir(app: &str) -> io::Result<PathBuf> {
+    if app.is_empty() || app == "." || app == ".." || app.contains(std::path::is_separator) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "application name must be a valid file name",
+        ));
+    }
+    #[cfg(unix)]
+    let name = format!("{}-{}", app, rustix::process::getuid().as_raw());
+    #[cfg(not(unix))]
+    let name = app;
+    let dir = temp_dir().join(name);
+
+    #[cfg_attr(not(unix), allow(unused_mut))]
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    match builder.create(&dir) {
+        Ok(()) => Ok(dir),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            check_private_dir(&dir).with_err_path(|| &dir)?;
+            Ok(dir)
+        }
+        Err(e) => Err(e).with_err_path(|| dir),
+    }
+}
+
+/// Check that the existing `dir` is a directory only accessible to the current user.
+fn check_private_dir(dir: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(dir)?;
+    if !meta.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "application temporary directory exists but is not a directory",
+        ));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        if meta.uid() != rustix::process::getuid().as_raw() || meta.mode() & 0o077 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "application temporary directory is owned by another user or accessible to \
+                 other users",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check that the default temporary directory ([`temp_dir`]) is usable, returning it.
+///
+/// The directory is usable if it exists, is a directory, and a file can be created in it. The
+/// latter is checked by actually creating (and removing) a probe file. Call this function at
+/// startup to report a misconfigured temporary directory (e.g., `TMPDIR` pointing at a missing
+/// directory) up-front instead of failing later with a confusing error. See also
+/// [`Builder::validate_dir`].
+///
+/// # Errors
+///
+/// If the directory is unusable, `Err` is returned describing why.
+///
+/// # Examples
+///
+/// ```
+/// let dir = tempfile::env::validate_temp_dir()?;
+/// println!("using {}", dir.display());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn validate_temp_dir() -> io::Result<PathBuf> {
+    let dir = temp_dir();
+    validate_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Check that temporary files can be created in `dir` (see [`validate_temp_dir`]).
+pub(crate) fn validate_dir(dir: &Path) -> io::Result<()> {
+    match fs::metadata(dir) {
+        Ok(meta) if meta.is_dir() => {}
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "temporary directory is not a directory",
+            ))
+            .with_err_path(|| dir)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(Error::TempDirNotFound {
+                dir: dir.to_owned(),
+            }
+            .into())
+        }
+        Err(e) => {
+            let kind = e.kind();
+            return Err(io::Error::new(
+                kind,
+                format!("temporary directory is inaccessible: {}", e),
+            ))
+            .with_err_path(|| dir);
+        }
+    }
+    let probe = util::create_helper(
+        dir,
+        ".tmp-probe".as_ref(),
+        "".as_ref(),
+        crate::NUM_RAND_CHARS,
+        |path| {
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .map(|_| path)
+        },
+    )
+    .map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("temporary directory is not writable: {}", e),
+        )
+    })?;
+    let _ = fs::remove_file(probe);
+    Ok(())
+}
+
+/// Returns a memory-backed (`tmpfs`) temporary directory, if one is available.
+///
+/// Prefers the default temporary directory, falling back on `/dev/shm`.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+#[allow(clippy::unnecessary_cast)] // The type of `f_type` varies by architecture.
+pub(crate) fn memory_temp_dir() -> Option<PathBuf> {
+    const TMPFS_MAGIC: i64 = 0x0102_1994;
+
+    let is_tmpfs = |dir: &Path| {
+        rustix::fs::statfs(dir).map_or(false, |stat| stat.f_type as i64 == TMPFS_MAGIC)
+    };
+    let default = temp_dir();
+    if is_tmpfs(&default) {
+        return Some(default);
+    }
+    let shm = Path::new("/dev/shm");
+    if is_tmpfs(shm) {
+        return Some(shm.to_owned());
+    }
+    None
+}
+
+#[cfg(not(any(target_os = "android", target_os = "linux")))]
+pub(crate) fn memory_temp_dir() -> Option<PathBuf> {
+    None
+}
+
+fn keep_tempfiles_flag() -> &'static AtomicBool {
+    KEEP_TEMPFILES.get_or_init(|| {
+        let keep = env::var_os("TEMPFILE_KEEP").map_or(false, |v| !v.is_empty() && v != "0");
+        AtomicBool::new(keep)
+    })
+}
+
+fn keep_tempfiles_verbose_flag() -> &'static AtomicBool {
+    KEEP_TEMPFILES_VERBOSE.get_or_init(|| {
+        let verbose = env::var_os("TEMPFILE_KEEP").map_or(false, |v| v == "verbose");
+        AtomicBool::new(verbose)
+    })
+}
+
+/// Enable or disable the global "keep temporary files" debug mode.
+///
+/// While enabled, [`NamedTempFile`], [`TempPath`], and [`TempDir`] skip deletion when dropped.
+/// Explicitly closing a temporary file or directory still deletes it. This is primarily useful for
+/// debugging (e.g., CI failures) and should not be enabled in production.
+///
+/// The retained paths are logged (with the `log` feature) at the `debug` level, and can also be
+/// printed to standard error with [`set_keep_tempfiles_verbose`].
+///
+/// The debug mode can also be enabled without code changes by setting the `TEMPFILE_KEEP`
+/// environment variable to any value other than an empty string or `0`, or to `verbose` to also
+/// print the retained paths. The environment variable is read once, the first time the mode is
+/// queried; calls to this function take precedence.
+///
+/// [`NamedTempFile`]: crate::NamedTempFile
+/// [`TempPath`]: crate::TempPath
+/// [`TempDir`]: crate::TempDir
+pub fn set_keep_tempfiles(keep: bool) {
+    keep_tempfiles_flag().store(keep, Ordering::Relaxed);
+}
+
+/// Returns `true` if the global "keep temporary files" debug mode is enabled.
+///
+/// See [`set_keep_tempfiles`] for details.
+pub fn keep_tempfiles() -> bool {
+    keep_tempfiles_flag().load(Ordering::Relaxed)
+}
+
+/// Print the paths retained by the "keep temporary files" debug mode to standard error.
+///
+/// This has no effect unless the debug mode is enabled. See [`set_keep_tempfiles`] for details.
+pub fn set_keep_tempfiles_verbose(verbose: bool) {
+    keep_tempfiles_verbose_flag().store(verbose, Ordering::Relaxed);
+}
+
+/// Returns `true` (after reporting the path) if `path` should be retained on drop because the
+/// "keep temporary files" debug mode is enabled.
+pub(crate) fn retain_on_drop(path: &Path) -> bool {
+    let keep = keep_tempfiles();
+    if keep {
+        log!(debug, "keeping {}", path.display());
+        if keep_tempfiles_verbose_flag().load(Ordering::Relaxed) {
+            eprintln!("tempfile: keeping {}", path.display());
+        }
+    }
+    keep
+}
@@ -1,6 +1,223 @@
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::{error, fmt, io};
 
+use crate::CleanupError;
+
+/// A structured error describing why a temporary file or directory operation failed.
+///
+/// Functions in this crate return [`io::Error`]s, which wrap an `Error` where applicable. Use
+/// [`Error::from_io`] to recover it instead of matching on error messages. An `Error` can also be
+/// converted back into an [`io::Error`] with the same [`kind`](Error::kind).
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::{Builder, Error};
+///
+/// let dir = tempfile::tempdir()?;
+/// let missing = dir.path().join("missing");
+/// let err = Builder::new().tempfile_in(&missing).unwrap_err();
+/// match Error::from_io(err) {
+///     Ok(Error::TempDirNotFound { dir }) => assert_eq!(dir, missing),
+///     other => panic!("unexpected error: {:?}", other),
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Every candidate name in `dir` was already taken.
+    TooManyCollisions {
+        /// The directory the temporary file or directory was to be created in.
+        dir: PathBuf,
+        /// The number of names that were tried.
+        attempts: u32,
+        /// The last path that was tried.
+        last_attempt: PathBuf,
+    },
+    /// Access to `path` was denied.
+    PermissionDenied {
+        /// The path that couldn't be accessed.
+        path: PathBuf,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// The name of the temporary file or directory is too long.
+    ///
+    /// The random part of names is automatically shortened to fit in the usual limit of 255
+    /// bytes, so this is only returned if the prefix and suffix don't leave room for at least 6
+    /// random characters (or the filesystem has a lower limit).
+    NameTooLong {
+        /// The directory the temporary file or directory was to be created in.
+        dir: PathBuf,
+        /// The name that was too long, or the prefix followed by the suffix if they alone leave
+        /// too little room for the random part.
+        name: OsString,
+    },
+    /// The directory the temporary file or directory was to be created in doesn't exist.
+    TempDirNotFound {
+        /// The missing directory.
+        dir: PathBuf,
+    },
+    /// A temporary file or directory couldn't be persisted because the target is on a different
+    /// filesystem.
+    ///
+    /// Persist errors keep the original OS error, so this is only produced by [`Error::from_io`],
+    /// from the error code. The paths involved are part of the persist error itself.
+    CrossDevice {
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// A temporary directory couldn't be completely removed.
+    Cleanup(CleanupError),
+}
+
+impl Error {
+    /// Extract the structured error from an error returned by this crate, returning the original
+    /// error if it doesn't wrap one.
+    pub fn from_io(err: io::Error) -> Result<Error, io::Error> {
+        // Check by reference first so unrelated errors are returned untouched.
+        if !wraps_error(&err) {
+            if is_cross_device(&err) {
+                return Ok(Error::CrossDevice { source: err });
+            }
+            return Err(err);
+        }
+        let kind = err.kind();
+        let inner = match err.into_inner() {
+            Some(inner) => inner,
+            None => return Err(io::Error::from(kind)),
+        };
+        match inner.downcast::<Error>() {
+            Ok(e) => Ok(*e),
+            Err(inner) => match inner.downcast::<CleanupError>() {
+                Ok(e) => Ok(Error::Cleanup(*e)),
+                Err(inner) => match inner.downcast::<PathError>() {
+                    Ok(e) => {
+                        let PathError { path, err } = *e;
+                        Error::from_io(err)
+                            .map_err(|err| io::Error::new(kind, PathError { path, err }))
+                    }
+                    Err(inner) => Err(io::Error::new(kind, inner)),
+                },
+            },
+        }
+    }
+
+    /// Returns the corresponding [`io::ErrorKind`].
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Error::TooManyCollisions { .. } => io::ErrorKind::AlreadyExists,
+            Error::PermissionDenied { .. } => io::ErrorKind::PermissionDenied,
+            Error::NameTooLong { .. } => io::ErrorKind::InvalidInput,
+            Error::TempDirNotFound { .. } => io::ErrorKind::NotFound,
+            Error::CrossDevice { source, .. } => source.kind(),
+            Error::Cleanup(e) => e.kind(),
+        }
+    }
+}
+
+/// Returns true if `err` wraps a structured [`Error`] (possibly with a path attached).
+fn wraps_error(err: &io::Error) -> bool {
+    match err.get_ref() {
+        Some(inner) => {
+            inner.is::<Error>()
+                || inner.is::<CleanupError>()
+                || inner
+                    .downcast_ref::<PathError>()
+                    .map_or(false, |e| wraps_error(&e.err) || is_cross_device(&e.err))
+        }
+        None => false,
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TooManyCollisions {
+                dir,
+                attempts,
+                last_attempt,
+            } => write!(
+                f,
+                "too many temporary files exist (gave up after {} attempts, the last one being {:?}) \
+                 at path {:?}",
+                attempts,
+                last_attempt.file_name().unwrap_or_default(),
+                dir
+            ),
+            Error::PermissionDenied { path, source } => write!(f, "{} at path {:?}", source, path),
+            Error::NameTooLong { dir, name } => write!(
+                f,
+                "temporary file name {:?} ({} bytes) is too long at path {:?}",
+                name,
+                name.len(),
+                dir
+            ),
+            Error::TempDirNotFound { dir } => {
+                write!(
+                    f,
+                    "temporary directory {:?} is inaccessible: it does not exist",
+                    dir
+                )
+            }
+            Error::CrossDevice { source } => {
+                write!(f, "failed to persist across filesystems: {}", source)
+            }
+            Error::Cleanup(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::PermissionDenied { source, .. } | Error::CrossDevice { source, .. } => {
+                Some(source)
+            }
+            Error::Cleanup(e) => Some(e),
+            Error::TooManyCollisions { .. }
+            | Error::NameTooLong { .. }
+            | Error::TempDirNotFound { .. } => None,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> io::Error {
+        match error {
+            Error::Cleanup(e) => e.into(),
+            error => io::Error::new(error.kind(), error),
+        }
+    }
+}
+
+fn is_cross_device(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    let code = Some(rustix::io::Errno::XDEV.raw_os_error());
+    #[cfg(windows)]
+    let code = Some(windows_sys::Win32::Foundation::ERROR_NOT_SAME_DEVICE as i32);
+    #[cfg(not(any(unix, windows)))]
+    let code = None;
+    code.is_some() && error.raw_os_error() == code
+}
+
+/// Returns `true` if `error` (possibly annotated with a path) reports a file name that's too long.
+pub(crate) fn is_name_too_long(error: &io::Error) -> bool {
+    #[cfg(any(unix, target_os = "wasi"))]
+    let code = Some(rustix::io::Errno::NAMETOOLONG.raw_os_error());
+    #[cfg(windows)]
+    let code = Some(windows_sys::Win32::Foundation::ERROR_FILENAME_EXCED_RANGE as i32);
+    #[cfg(not(any(unix, windows, target_os = "wasi")))]
+    let code = None;
+    let inner = error
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<PathError>())
+        .map_or(error, |e| &e.err);
+    code.is_some() && inner.raw_os_error() == code
+}
+
 #[derive(Debug)]
 struct PathError {
     path: PathBuf,
@@ -33,6 +250,13 @@ impl<T> IoResultExt<T> for Result<T, io::Error> {
         P: Into<PathBuf>,
     {
         self.map_err(|e| {
+            if e.kind() == io::ErrorKind::PermissionDenied {
+                return Error::PermissionDenied {
+                    path: path().into(),
+                    source: e,
+                }
+                .into();
+            }
             io::Error::new(
                 e.kind(),
                 PathError {
@@ -43,3 +267,23 @@ impl<T> IoResultExt<T> for Result<T, io::Error> {
         })
     }
 }
+
+/// Make a copy of an IO result, preserving the OS error code (if any) or the error kind and
+/// message otherwise.
+pub(crate) fn copy_result(result: &io::Result<()>) -> io::Result<()> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => Err(match e.raw_os_error() {
+            Some(code) => io::Error::from_raw_os_error(code),
+            None => io::Error::new(e.kind(), e.to_string()),
+        }),
+    }
+}
+
+/// Log the result of automatically deleting `path`: errors are otherwise silently ignored.
+pub(crate) fn log_cleanup(path: &Path, result: &io::Result<()>) {
+    match result {
+        Ok(()) => log!(debug, "deleted {}", path.display()),
+        Err(e) => log!(warn, "failed to delete {}: {}", path.display(), e),
+    }
+}
@@ -25,6 +25,22 @@ pub fn reopen(_file: &File, _path: &Path) -> io::Result<File> {
     not_supported()
 }
 
+pub fn reopen_with(_file: &File, _path: &Path, _options: &OpenOptions) -> io::Result<File> {
+    not_supported()
+}
+
+pub fn preallocate(_file: &File, _len: u64) -> io::Result<()> {
+    not_supported()
+}
+
+pub fn open_nofollow(_path: &Path) -> io::Result<File> {
+    not_supported()
+}
+
+pub fn same_file(_file: &File, _path: &Path) -> io::Result<bool> {
+    not_supported()
+}
+
 pub fn persist(_old_path: &Path, _new_path: &Path, _overwrite: bool) -> io::Result<()> {
     not_supported()
 }
@@ -32,3 +48,7 @@ pub fn persist(_old_path: &Path, _new_path: &Path, _overwrite: bool) -> io::Resu
 pub fn keep(_path: &Path) -> io::Result<()> {
     not_supported()
 }
+
+pub fn close(_file: File) -> io::Result<()> {
+    not_supported()
+}
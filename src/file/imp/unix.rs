@@ -86,9 +86,20 @@ fn create_unix(dir: &Path) -> io::Result<File> {
     )
 }
 
-#[cfg(any(not(target_os = "wasi"), feature = "nightly"))]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn create_memfile() -> io::Result<File> {
+    use rustix::fs::{memfd_create, MemfdFlags};
+    // The name is only used for debugging (e.g., in `/proc/self/fd`).
+    Ok(memfd_create("tempfile", MemfdFlags::CLOEXEC)?.into())
+}
+
 pub fn reopen(file: &File, path: &Path) -> io::Result<File> {
-    let new_file = OpenOptions::new().read(true).write(true).open(path)?;
+    reopen_with(file, path, OpenOptions::new().read(true).write(true))
+}
+
+#[cfg(any(not(target_os = "wasi"), feature = "nightly"))]
+pub fn reopen_with(file: &File, path: &Path, options: &OpenOptions) -> io::Result<File> {
+    let new_file = options.open(path)?;
     let old_meta = file.metadata()?;
     let new_meta = new_file.metadata()?;
     if old_meta.dev() != new_meta.dev() || old_meta.ino() != new_meta.ino() {
@@ -100,8 +111,70 @@ pub fn reopen(file: &File, path: &Path) -> io::Result<File> {
     Ok(new_file)
 }
 
+/// Reserve `len` bytes of disk space for `file`, extending it if necessary.
+#[cfg(any(
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "linux",
+    target_vendor = "apple"
+))]
+pub fn preallocate(file: &File, len: u64) -> io::Result<()> {
+    use rustix::fs::{fallocate, FallocateFlags};
+    match fallocate(file, FallocateFlags::empty(), 0, len) {
+        Ok(()) => Ok(()),
+        // Not all filesystems support preallocation: some report `EINVAL` (e.g., ZFS on FreeBSD)
+        // or `ENOSYS` instead of `EOPNOTSUPP`.
+        Err(rustix::io::Errno::OPNOTSUPP | rustix::io::Errno::INVAL | rustix::io::Errno::NOSYS) => {
+            extend(file, len)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(not(any(
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "linux",
+    target_vendor = "apple"
+)))]
+pub fn preallocate(file: &File, len: u64) -> io::Result<()> {
+    extend(file, len)
+}
+
+/// Extend `file` to `len` bytes without reserving disk space.
+fn extend(file: &File, len: u64) -> io::Result<()> {
+    if file.metadata()?.len() < len {
+        file.set_len(len)?;
+    }
+    Ok(())
+}
+
+/// Open the existing file at `path` for writing, failing if it's a symbolic link.
+pub fn open_nofollow(path: &Path) -> io::Result<File> {
+    use rustix::fs::{Mode, OFlags};
+    // Non-blocking so a FIFO swapped in for the file can't block us.
+    let flags = OFlags::WRONLY | OFlags::NOFOLLOW | OFlags::NONBLOCK | OFlags::CLOEXEC;
+    Ok(rustix::fs::open(path, flags, Mode::empty())?.into())
+}
+
+/// Returns `true` if `file` is the file at `path` (without following symbolic links).
+#[cfg(any(not(target_os = "wasi"), feature = "nightly"))]
+pub fn same_file(file: &File, path: &Path) -> io::Result<bool> {
+    let file_meta = file.metadata()?;
+    let path_meta = fs::symlink_metadata(path)?;
+    Ok(file_meta.dev() == path_meta.dev() && file_meta.ino() == path_meta.ino())
+}
+
+#[cfg(all(target_os = "wasi", not(feature = "nightly")))]
+pub fn same_file(_file: &File, _path: &Path) -> io::Result<bool> {
+    return Err(io::Error::new(
+        io::ErrorKind::Other,
+        "this operation is supported on WASI only on nightly Rust (with `nightly` feature enabled)",
+    ));
+}
+
 #[cfg(all(target_os = "wasi", not(feature = "nightly")))]
-pub fn reopen(_file: &File, _path: &Path) -> io::Result<File> {
+pub fn reopen_with(_file: &File, _path: &Path, _options: &OpenOptions) -> io::Result<File> {
     return Err(io::Error::new(
         io::ErrorKind::Other,
         "this operation is supported on WASI only on nightly Rust (with `nightly` feature enabled)",
@@ -153,3 +226,13 @@ pub fn persist(_old_path: &Path, _new_path: &Path, _overwrite: bool) -> io::Resu
 pub fn keep(_: &Path) -> io::Result<()> {
     Ok(())
 }
+
+pub fn close(file: File) -> io::Result<()> {
+    #[cfg(not(target_os = "wasi"))]
+    use std::os::unix::io::IntoRawFd;
+    #[cfg(target_os = "wasi")]
+    use std::os::wasi::io::IntoRawFd;
+
+    // SAFETY: We own the file descriptor and give up ownership by converting it into a raw fd.
+    unsafe { rustix::io::try_close(file.into_raw_fd()) }.map_err(io::Error::from)
+}
@@ -1,16 +1,20 @@
-use std::ffi::OsStr;
+use std::ffi::{c_void, OsStr};
 use std::fs::{File, OpenOptions};
 use std::os::windows::ffi::OsStrExt;
 use std::os::windows::fs::OpenOptionsExt;
-use std::os::windows::io::{AsRawHandle, FromRawHandle, RawHandle};
+use std::os::windows::io::{AsRawHandle, FromRawHandle, IntoRawHandle, RawHandle};
 use std::path::Path;
-use std::{io, iter};
+use std::{io, iter, mem, ptr};
 
-use windows_sys::Win32::Foundation::{HANDLE, INVALID_HANDLE_VALUE};
+use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
 use windows_sys::Win32::Storage::FileSystem::{
-    MoveFileExW, ReOpenFile, SetFileAttributesW, FILE_ATTRIBUTE_NORMAL, FILE_ATTRIBUTE_TEMPORARY,
-    FILE_FLAG_DELETE_ON_CLOSE, FILE_GENERIC_READ, FILE_GENERIC_WRITE, FILE_SHARE_DELETE,
-    FILE_SHARE_READ, FILE_SHARE_WRITE, MOVEFILE_REPLACE_EXISTING,
+    FileBasicInfo, FileRenameInfo, GetFileAttributesW, GetFileInformationByHandle,
+    GetFullPathNameW, MoveFileExW, ReOpenFile, SetFileAttributesW, SetFileInformationByHandle,
+    BY_HANDLE_FILE_INFORMATION, DELETE, FILE_ATTRIBUTE_NORMAL, FILE_ATTRIBUTE_TEMPORARY,
+    FILE_BASIC_INFO, FILE_FLAG_DELETE_ON_CLOSE, FILE_FLAG_OPEN_REPARSE_POINT, FILE_GENERIC_READ,
+    FILE_GENERIC_WRITE, FILE_READ_ATTRIBUTES, FILE_RENAME_INFO, FILE_SHARE_DELETE, FILE_SHARE_READ,
+    FILE_SHARE_WRITE, FILE_WRITE_ATTRIBUTES, INVALID_FILE_ATTRIBUTES, MOVEFILE_REPLACE_EXISTING,
+    SYNCHRONIZE,
 };
 
 use crate::util;
@@ -19,6 +23,24 @@ fn to_utf16(s: &Path) -> Vec<u16> {
     s.as_os_str().encode_wide().chain(iter::once(0)).collect()
 }
 
+/// Paths this long (in UTF-16 code units) must be passed to the Win32 APIs in their verbatim
+/// (`\\?\`) form. The limit is `MAX_PATH` (260, including the NUL terminator), minus room for an
+/// 8.3 file name when creating directories.
+const MAX_SHORT_PATH: usize = 248;
+
+/// Returns `path` as a NUL-terminated UTF-16 string for the Win32 APIs, switching to the verbatim
+/// (`\\?\`) form if it's too long for the regular form. The standard library does this
+/// automatically but direct calls to the Win32 APIs have to do it themselves.
+pub fn to_wide_path(path: &Path) -> io::Result<Vec<u16>> {
+    let path_w = to_utf16(path);
+    if path_w.len() <= MAX_SHORT_PATH {
+        return Ok(path_w);
+    }
+    let mut path_w = verbatim(path)?;
+    path_w.push(0);
+    Ok(path_w)
+}
+
 fn not_supported<T>(msg: &str) -> io::Result<T> {
     Err(io::Error::new(io::ErrorKind::Other, msg))
 }
@@ -35,7 +57,6 @@ pub fn create_named(
         .create_new(true)
         .read(true)
         .write(true)
-        .custom_flags(FILE_ATTRIBUTE_TEMPORARY)
         .open(path)
 }
 
@@ -74,9 +95,64 @@ pub fn reopen(file: &File, _path: &Path) -> io::Result<File> {
     }
 }
 
+pub fn reopen_with(file: &File, path: &Path, options: &OpenOptions) -> io::Result<File> {
+    let new_file = options.open(path)?;
+    if file_id(file)? != file_id(&new_file)? {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "original tempfile has been replaced",
+        ));
+    }
+    Ok(new_file)
+}
+
+/// Reserve `len` bytes of disk space for `file`, extending it if necessary.
+pub fn preallocate(file: &File, len: u64) -> io::Result<()> {
+    // Setting the end of file (`SetEndOfFile`) allocates the space on NTFS.
+    if file.metadata()?.len() < len {
+        file.set_len(len)?;
+    }
+    Ok(())
+}
+
+/// Open the existing file at `path` for writing. If it's a symbolic link (or another reparse
+/// point), the link itself is opened.
+pub fn open_nofollow(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(path)
+}
+
+/// Returns `true` if `file` is the file at `path` (without following symbolic links).
+pub fn same_file(file: &File, path: &Path) -> io::Result<bool> {
+    // No access rights are needed to query the file's identity.
+    let other = OpenOptions::new()
+        .access_mode(0)
+        .share_mode(FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE)
+        .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(path)?;
+    Ok(file_id(file)? == file_id(&other)?)
+}
+
+/// Returns the volume serial number and file index, which together identify a file.
+fn file_id(file: &File) -> io::Result<(u32, u32, u32)> {
+    unsafe {
+        let mut info: BY_HANDLE_FILE_INFORMATION = mem::zeroed();
+        if GetFileInformationByHandle(file.as_raw_handle() as HANDLE, &mut info) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((
+            info.dwVolumeSerialNumber,
+            info.nFileIndexHigh,
+            info.nFileIndexLow,
+        ))
+    }
+}
+
 pub fn keep(path: &Path) -> io::Result<()> {
+    let path_w = to_wide_path(path)?;
     unsafe {
-        let path_w = to_utf16(path);
         if SetFileAttributesW(path_w.as_ptr(), FILE_ATTRIBUTE_NORMAL) == 0 {
             Err(io::Error::last_os_error())
         } else {
@@ -86,9 +162,196 @@ pub fn keep(path: &Path) -> io::Result<()> {
 }
 
 pub fn persist(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<()> {
+    // Prefer renaming through a handle so that the attributes and the name are changed on the same
+    // file and the attributes can be restored if the rename fails. Only fall back on the (racy)
+    // path-based APIs if the filesystem doesn't support renaming by handle; any other failure is
+    // returned as-is.
+    match persist_by_handle(old_path, new_path, overwrite) {
+        Err(e) if is_unsupported(&e) => persist_by_path(old_path, new_path, overwrite),
+        result => result,
+    }
+}
+
+/// Returns `true` if `error` indicates that an operation isn't supported by the filesystem.
+fn is_unsupported(error: &io::Error) -> bool {
+    use windows_sys::Win32::Foundation::{
+        ERROR_INVALID_FUNCTION, ERROR_INVALID_PARAMETER, ERROR_NOT_SUPPORTED,
+    };
+
+    matches!(
+        error.raw_os_error(),
+        Some(code) if code == ERROR_INVALID_PARAMETER as i32
+            || code == ERROR_NOT_SUPPORTED as i32
+            || code == ERROR_INVALID_FUNCTION as i32
+    )
+}
+
+/// Like [`persist`], but renaming `file` (located at `old_path`) through a new handle to it instead
+/// of reopening it by path, so that a file swapped in at `old_path` can't be persisted instead.
+pub fn persist_file(
+    file: &File,
+    old_path: &Path,
+    new_path: &Path,
+    overwrite: bool,
+) -> io::Result<()> {
+    match reopen_for_rename(file).and_then(|file| persist_handle(&file, new_path, overwrite)) {
+        Err(e) if is_unsupported(&e) => persist_by_path(old_path, new_path, overwrite),
+        result => result,
+    }
+}
+
+/// Open a new handle to `file` with the access needed to rename it and change its attributes.
+fn reopen_for_rename(file: &File) -> io::Result<File> {
     unsafe {
-        let old_path_w = to_utf16(old_path);
-        let new_path_w = to_utf16(new_path);
+        let handle = ReOpenFile(
+            file.as_raw_handle() as HANDLE,
+            DELETE | FILE_READ_ATTRIBUTES | FILE_WRITE_ATTRIBUTES | SYNCHRONIZE,
+            FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE,
+            0,
+        );
+        if handle == INVALID_HANDLE_VALUE {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(FromRawHandle::from_raw_handle(handle as RawHandle))
+        }
+    }
+}
+
+fn persist_by_handle(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<()> {
+    let file = OpenOptions::new()
+        .access_mode(DELETE | FILE_READ_ATTRIBUTES | FILE_WRITE_ATTRIBUTES | SYNCHRONIZE)
+        .share_mode(FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE)
+        .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(old_path)?;
+    persist_handle(&file, new_path, overwrite)
+}
+
+/// Rename `file`, which must have been opened with `DELETE`, `FILE_READ_ATTRIBUTES`, and
+/// `FILE_WRITE_ATTRIBUTES` access, clearing its temporary attributes.
+fn persist_handle(file: &File, new_path: &Path, overwrite: bool) -> io::Result<()> {
+    let handle = file.as_raw_handle() as HANDLE;
+    let attributes = unsafe {
+        let mut info: BY_HANDLE_FILE_INFORMATION = mem::zeroed();
+        if GetFileInformationByHandle(handle, &mut info) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        info.dwFileAttributes
+    };
+
+    // Don't succeed if this fails. We don't want to claim to have successfully persisted a file
+    // still marked as temporary because this file won't have the same consistency guarantees.
+    set_attributes(handle, FILE_ATTRIBUTE_NORMAL)?;
+    if let Err(e) = rename_by_handle(handle, new_path, overwrite) {
+        // Restore the attributes the file was created with (see `Builder::attributes`).
+        let _ = set_attributes(handle, attributes);
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn set_attributes(handle: HANDLE, attributes: u32) -> io::Result<()> {
+    // Zeroed timestamps are left unchanged.
+    let info = FILE_BASIC_INFO {
+        CreationTime: 0,
+        LastAccessTime: 0,
+        LastWriteTime: 0,
+        ChangeTime: 0,
+        FileAttributes: attributes,
+    };
+    unsafe {
+        if SetFileInformationByHandle(
+            handle,
+            FileBasicInfo,
+            &info as *const FILE_BASIC_INFO as *const c_void,
+            mem::size_of::<FILE_BASIC_INFO>() as u32,
+        ) == 0
+        {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn rename_by_handle(handle: HANDLE, new_path: &Path, overwrite: bool) -> io::Result<()> {
+    let new_path = verbatim(new_path)?;
+    let name_len = new_path.len() * mem::size_of::<u16>();
+    // `FILE_RENAME_INFO` ends in a variable-length name. Its trailing `[u16; 1]` leaves room for
+    // the NUL terminator.
+    let size = mem::size_of::<FILE_RENAME_INFO>() + name_len;
+    let mut buf = vec![0u64; (size + 7) / 8];
+    let info = buf.as_mut_ptr() as *mut FILE_RENAME_INFO;
+    unsafe {
+        (*info).Anonymous.ReplaceIfExists = u8::from(overwrite);
+        (*info).FileNameLength = name_len as u32;
+        ptr::copy_nonoverlapping(
+            new_path.as_ptr(),
+            ptr::addr_of_mut!((*info).FileName) as *mut u16,
+            new_path.len(),
+        );
+        if SetFileInformationByHandle(handle, FileRenameInfo, info as *const c_void, size as u32)
+            == 0
+        {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Returns the absolute, verbatim (`\\?\`) form of `path` without a NUL terminator. Handle-based
+/// renames don't resolve relative paths.
+fn verbatim(path: &Path) -> io::Result<Vec<u16>> {
+    let path_w = to_utf16(path);
+    let mut buf = vec![0u16; 260];
+    let len = loop {
+        let len = unsafe {
+            GetFullPathNameW(
+                path_w.as_ptr(),
+                buf.len() as u32,
+                buf.as_mut_ptr(),
+                ptr::null_mut(),
+            )
+        } as usize;
+        if len == 0 {
+            return Err(io::Error::last_os_error());
+        } else if len > buf.len() {
+            // Too small; `len` includes the NUL terminator.
+            buf.resize(len, 0);
+        } else {
+            break len;
+        }
+    };
+    buf.truncate(len);
+
+    const VERBATIM: &[u16] = &[b'\\' as u16, b'\\' as u16, b'?' as u16, b'\\' as u16];
+    const DEVICE: &[u16] = &[b'\\' as u16, b'\\' as u16, b'.' as u16, b'\\' as u16];
+    const UNC: &[u16] = &[b'U' as u16, b'N' as u16, b'C' as u16, b'\\' as u16];
+    if buf.starts_with(VERBATIM) {
+        Ok(buf)
+    } else if buf.starts_with(DEVICE) {
+        not_supported("can't rename to a device path")
+    } else if buf.starts_with(&VERBATIM[..2]) {
+        // \\server\share -> \\?\UNC\server\share
+        Ok(VERBATIM
+            .iter()
+            .chain(UNC)
+            .chain(&buf[2..])
+            .copied()
+            .collect())
+    } else {
+        Ok(VERBATIM.iter().chain(&buf).copied().collect())
+    }
+}
+
+fn persist_by_path(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<()> {
+    let old_path_w = to_wide_path(old_path)?;
+    let new_path_w = to_wide_path(new_path)?;
+    unsafe {
+        let attributes = GetFileAttributesW(old_path_w.as_ptr());
+        if attributes == INVALID_FILE_ATTRIBUTES {
+            return Err(io::Error::last_os_error());
+        }
 
         // Don't succeed if this fails. We don't want to claim to have successfully persisted a file
         // still marked as temporary because this file won't have the same consistency guarantees.
@@ -104,12 +367,23 @@ pub fn persist(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<
 
         if MoveFileExW(old_path_w.as_ptr(), new_path_w.as_ptr(), flags) == 0 {
             let e = io::Error::last_os_error();
-            // If this fails, the temporary file is now un-hidden and no longer marked temporary
+            // Restore the attributes the file was created with (see `Builder::attributes`). If
+            // this fails, the temporary file is now un-hidden and no longer marked temporary
             // (slightly less efficient) but it will still work.
-            let _ = SetFileAttributesW(old_path_w.as_ptr(), FILE_ATTRIBUTE_TEMPORARY);
+            let _ = SetFileAttributesW(old_path_w.as_ptr(), attributes);
             Err(e)
         } else {
             Ok(())
         }
     }
 }
+
+pub fn close(file: File) -> io::Result<()> {
+    unsafe {
+        if CloseHandle(file.into_raw_handle() as HANDLE) == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -2,7 +2,7 @@ use std::error;
 use std::ffi::OsStr;
 use std::fmt;
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
 use std::mem;
 use std::ops::Deref;
 #[cfg(unix)]
@@ -13,11 +13,13 @@ use std::os::wasi::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
 use std::os::windows::io::{AsHandle, AsRawHandle, BorrowedHandle, RawHandle};
 use std::path::{Path, PathBuf};
 
+use crate::backend::{BackendRef, TempBackend};
 use crate::env;
-use crate::error::IoResultExt;
-use crate::Builder;
+use crate::error::{copy_result, IoResultExt};
+use crate::registry;
+use crate::{util, Builder, OnCleanup};
 
-mod imp;
+pub(crate) mod imp;
 
 /// Create a new temporary file.
 ///
@@ -84,6 +86,183 @@ pub fn tempfile_in<P: AsRef<Path>>(dir: P) -> io::Result<File> {
     imp::create(dir.as_ref())
 }
 
+/// Create a new temporary file in [`env::temp_dir()`], reserving `len` bytes of disk space for it.
+///
+/// The file is extended to `len` (zero-filled) bytes. See [`Builder::preallocate`] for details.
+///
+/// # Errors
+///
+/// If the file can not be created or the space can't be reserved (e.g., because the disk is
+/// full), `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::tempfile_sized;
+///
+/// let file = tempfile_sized(1024 * 1024)?;
+/// assert_eq!(file.metadata()?.len(), 1024 * 1024);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn tempfile_sized(len: u64) -> io::Result<File> {
+    let file = tempfile()?;
+    imp::preallocate(&file, len)?;
+    Ok(file)
+}
+
+/// Create a new temporary file and return `count` handles to it.
+///
+/// See [`tempfile_shared_in`].
+///
+/// # Errors
+///
+/// If the file can not be created or reopened, `Err` is returned.
+pub fn tempfile_shared(count: usize) -> io::Result<Vec<File>> {
+    tempfile_shared_in(env::temp_dir(), count)
+}
+
+/// Create a new temporary file in the specified directory and return `count` handles to it.
+///
+/// Each handle has its own cursor so, e.g., a producer thread can append to the file while a
+/// consumer thread reads it from the start, without coordinating a path. Like [`tempfile()`], the
+/// file is deleted once the last handle is closed.
+///
+/// # Security
+///
+/// The file is created as a [`NamedTempFile`] and only unlinked after it has been reopened, so it
+/// briefly has a name and is exposed to temporary file cleaners during that window. It's never
+/// reopened after that.
+///
+/// # Errors
+///
+/// If the file can not be created, reopened, or deleted, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Read, Write};
+///
+/// let mut handles = tempfile::tempfile_shared(2)?;
+/// let mut reader = handles.pop().unwrap();
+/// let mut writer = handles.pop().unwrap();
+///
+/// writer.write_all(b"shared scratch space")?;
+/// let mut buf = String::new();
+/// reader.read_to_string(&mut buf)?;
+/// assert_eq!(buf, "shared scratch space");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn tempfile_shared_in<P: AsRef<Path>>(dir: P, count: usize) -> io::Result<Vec<File>> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let file = NamedTempFile::new_in(dir)?;
+    let mut files = Vec::with_capacity(count);
+    for _ in 1..count {
+        files.push(file.reopen()?);
+    }
+    let (file, path) = file.into_parts();
+    path.close()?;
+    files.insert(0, file);
+    Ok(files)
+}
+
+/// Create a new named temporary file, call `f` with the open file and its path, then delete it,
+/// returning the result of `f`.
+///
+/// The file is deleted even if `f` panics. Unlike a [`NamedTempFile`] guard, which is deleted as
+/// soon as it's dropped (e.g., when it's accidentally moved into a function taking
+/// `AsRef<Path>`), the file is guaranteed to live exactly as long as the call to `f`.
+///
+/// See [`try_with_tempfile`] for closures that can fail.
+///
+/// # Errors
+///
+/// If the file can not be created or deleted, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use std::io::Write;
+///
+/// let contents = tempfile::with_tempfile(|file, path| {
+///     writeln!(file, "Brian was here. Briefly.").unwrap();
+///     fs::read_to_string(path).unwrap()
+/// })?;
+/// assert_eq!(contents, "Brian was here. Briefly.\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn with_tempfile<R>(f: impl FnOnce(&mut File, &Path) -> R) -> io::Result<R> {
+    try_with_tempfile(|file, path| Ok::<_, io::Error>(f(file, path)))
+}
+
+/// Like [`with_tempfile`], but for closures that can fail.
+///
+/// The file is deleted whether `f` succeeds, fails, or panics.
+///
+/// # Errors
+///
+/// If the file can not be created or deleted, or `f` fails, `Err` is returned. If both `f` and the
+/// deletion fail, `f`'s error is returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use std::io::Write;
+///
+/// let contents = tempfile::try_with_tempfile(|file, path| {
+///     writeln!(file, "Brian was here. Briefly.")?;
+///     fs::read_to_string(path)
+/// })?;
+/// assert_eq!(contents, "Brian was here. Briefly.\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn try_with_tempfile<R, E>(f: impl FnOnce(&mut File, &Path) -> Result<R, E>) -> Result<R, E>
+where
+    E: From<io::Error>,
+{
+    // Dropping the guard (e.g., while unwinding) deletes the file.
+    let (mut file, path) = NamedTempFile::new()?.into_parts();
+    let result = f(&mut file, &path)?;
+    drop(file);
+    path.close()?;
+    Ok(result)
+}
+
+/// Create a new anonymous, memory-backed temporary file.
+///
+/// The file is created with `memfd_create` and never touches any filesystem, so this works even
+/// without a writable temporary directory. The returned file behaves like any other file (e.g., it
+/// can be passed to child processes or used with `sendfile`) but its contents live in memory (and
+/// swap).
+///
+/// # Resource Leaking
+///
+/// The file will be automatically freed by the OS when the last handle to it is closed.
+///
+/// # Errors
+///
+/// If the file can not be created (e.g., because the kernel doesn't support `memfd_create`),
+/// `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::memfile;
+/// use std::io::Write;
+///
+/// let mut file = memfile()?;
+///
+/// writeln!(file, "Brian was here. Briefly.")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn memfile() -> io::Result<File> {
+    imp::create_memfile()
+}
+
 /// Error returned when persisting a temporary file path fails.
 #[derive(Debug)]
 pub struct PathPersistError {
@@ -93,6 +272,13 @@ pub struct PathPersistError {
     pub path: TempPath,
 }
 
+impl PathPersistError {
+    /// Returns the path the temporary file was to be persisted to, if any.
+    pub fn target(&self) -> Option<&Path> {
+        self.path.persist_target.as_deref()
+    }
+}
+
 impl From<PathPersistError> for io::Error {
     #[inline]
     fn from(error: PathPersistError) -> io::Error {
@@ -109,7 +295,7 @@ impl From<PathPersistError> for TempPath {
 
 impl fmt::Display for PathPersistError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "failed to persist temporary file path: {}", self.error)
+        fmt_persist_error(f, &self.path, self.target(), &self.error)
     }
 }
 
@@ -129,6 +315,14 @@ impl error::Error for PathPersistError {
 pub struct TempPath {
     path: Box<Path>,
     keep: bool,
+    wipe: bool,
+    /// A handle to the file created by the [`NamedTempFile`], used to wipe it and (on Windows) to
+    /// persist it without reopening it by path.
+    handle: Option<File>,
+    /// The path the file was last unsuccessfully persisted to, reported by the persist errors.
+    persist_target: Option<Box<Path>>,
+    on_cleanup: Option<OnCleanup>,
+    backend: BackendRef,
 }
 
 impl TempPath {
@@ -158,8 +352,23 @@ impl TempPath {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     pub fn close(mut self) -> io::Result<()> {
-        let result = fs::remove_file(&self.path).with_err_path(|| &*self.path);
+        let wiped = self.wipe();
+        let removed = self
+            .backend
+            .0
+            .remove_file(&self.path)
+            .with_err_path(|| &*self.path);
+        let truncated = match &wiped {
+            Ok(Some(file)) => file.set_len(0).with_err_path(|| &*self.path),
+            _ => Ok(()),
+        };
+        let result = wiped.map(drop).and(removed).and(truncated);
+        registry::unregister(&self.path);
+        if let Some(on_cleanup) = self.on_cleanup {
+            on_cleanup(&self.path, copy_result(&result));
+        }
         self.path = PathBuf::new().into_boxed_path();
+        self.handle = None;
         mem::forget(self);
         result
     }
@@ -201,19 +410,25 @@ impl TempPath {
     ///
     /// [`PathPersistError`]: struct.PathPersistError.html
     pub fn persist<P: AsRef<Path>>(mut self, new_path: P) -> Result<(), PathPersistError> {
-        match imp::persist(&self.path, new_path.as_ref(), true) {
+        let new_path = new_path.as_ref();
+        match self.persist_to(new_path, true) {
             Ok(_) => {
+                log!(
+                    debug,
+                    "persisted {} to {}",
+                    self.path.display(),
+                    new_path.display()
+                );
                 // Don't drop `self`. We don't want to try deleting the old
                 // temporary file path. (It'll fail, but the failure is never
                 // seen.)
+                registry::unregister(&self.path);
                 self.path = PathBuf::new().into_boxed_path();
+                self.handle = None;
                 mem::forget(self);
                 Ok(())
             }
-            Err(e) => Err(PathPersistError {
-                error: e,
-                path: self,
-            }),
+            Err(e) => Err(self.persist_error(e, Some(new_path))),
         }
     }
 
@@ -222,9 +437,12 @@ impl TempPath {
     /// If a file exists at the target path, fail. If this method fails, it will
     /// return `self` in the resulting [`PathPersistError`].
     ///
-    /// Note: Temporary files cannot be persisted across filesystems. Also Note:
-    /// This method is not atomic. It can leave the original link to the
-    /// temporary file behind.
+    /// Note: Temporary files cannot be persisted across filesystems.
+    ///
+    /// On Linux and Android, this method atomically renames the file with
+    /// `renameat2(RENAME_NOREPLACE)`. On other platforms, and on kernels or filesystems that don't
+    /// support it, the file is hard-linked to the new location and then unlinked. This fallback is
+    /// not atomic: it can leave the original link to the temporary file behind.
     ///
     /// # Security
     ///
@@ -256,22 +474,115 @@ impl TempPath {
         mut self,
         new_path: P,
     ) -> Result<(), PathPersistError> {
-        match imp::persist(&self.path, new_path.as_ref(), false) {
+        let new_path = new_path.as_ref();
+        match self.persist_to(new_path, false) {
             Ok(_) => {
+                log!(
+                    debug,
+                    "persisted {} to {}",
+                    self.path.display(),
+                    new_path.display()
+                );
                 // Don't drop `self`. We don't want to try deleting the old
                 // temporary file path. (It'll fail, but the failure is never
                 // seen.)
+                registry::unregister(&self.path);
                 self.path = PathBuf::new().into_boxed_path();
+                self.handle = None;
                 mem::forget(self);
                 Ok(())
             }
-            Err(e) => Err(PathPersistError {
-                error: e,
-                path: self,
-            }),
+            Err(e) => Err(self.persist_error(e, Some(new_path))),
         }
     }
 
+    /// Persist the temporary file in the directory `dir` under a unique name, returning its new
+    /// path.
+    ///
+    /// The file keeps its current name if no file by that name exists in `dir`. Otherwise, a
+    /// random string is appended to the name's stem (before the extension, if any), retrying with
+    /// a new one until an unused name is found. Existing files are never replaced, as with
+    /// [`TempPath::persist_noclobber`].
+    ///
+    /// Note: Temporary files cannot be persisted across filesystems.
+    ///
+    /// # Security
+    ///
+    /// Only use this method if you're positive that a temporary file cleaner
+    /// won't have deleted your file. Otherwise, you might end up persisting an
+    /// attacker controlled file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to `dir` or no unused name can be found, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let path = NamedTempFile::new()?.into_temp_path();
+    /// let new_path = path.persist_in("./outbox")?;
+    /// println!("published {}", new_path.display());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist_in<P: AsRef<Path>>(self, dir: P) -> Result<PathBuf, PathPersistError> {
+        let dir = dir.as_ref();
+        let name = match self.path.file_name() {
+            Some(name) => name.to_owned(),
+            None => {
+                return Err(self.persist_error(
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "temporary file has no file name",
+                    ),
+                    None,
+                ))
+            }
+        };
+        let target = dir.join(&name);
+        let err = match self.persist_noclobber(&target) {
+            Ok(()) => return Ok(target),
+            Err(err) if err.error.kind() == io::ErrorKind::AlreadyExists => err,
+            Err(err) => return Err(err),
+        };
+
+        // The name is taken, pick a new one.
+        let name = Path::new(&name);
+        let prefix = match name.file_stem() {
+            Some(stem) => {
+                let mut prefix = stem.to_owned();
+                prefix.push("-");
+                prefix
+            }
+            None => OsStr::new("").to_owned(),
+        };
+        let suffix = match name.extension() {
+            Some(extension) => {
+                let mut suffix = OsStr::new(".").to_owned();
+                suffix.push(extension);
+                suffix
+            }
+            None => OsStr::new("").to_owned(),
+        };
+        let mut tmp = Some(err.path);
+        let result =
+            util::create_helper(
+                dir,
+                &prefix,
+                &suffix,
+                crate::NUM_RAND_CHARS,
+                |target| match tmp.take().unwrap().persist_noclobber(&target) {
+                    Ok(()) => Ok(target),
+                    Err(err) => {
+                        tmp = Some(err.path);
+                        Err(err.error)
+                    }
+                },
+            );
+        result.map_err(|error| tmp.take().unwrap().persist_error(error, None))
+    }
+
     /// Keep the temporary file from being deleted. This function will turn the
     /// temporary file into a non-temporary file without moving it.
     ///
@@ -296,22 +607,143 @@ impl TempPath {
     ///
     /// [`PathPersistError`]: struct.PathPersistError.html
     pub fn keep(mut self) -> Result<PathBuf, PathPersistError> {
-        match imp::keep(&self.path) {
+        match self.backend.0.keep(&self.path) {
             Ok(_) => {
                 // Don't drop `self`. We don't want to try deleting the old
                 // temporary file path. (It'll fail, but the failure is never
                 // seen.)
+                registry::unregister(&self.path);
                 let path = mem::replace(&mut self.path, PathBuf::new().into_boxed_path());
+                self.handle = None;
                 mem::forget(self);
                 Ok(path.into())
             }
-            Err(e) => Err(PathPersistError {
-                error: e,
-                path: self,
-            }),
+            Err(e) => Err(self.persist_error(e, None)),
         }
     }
 
+    /// Leak the temporary file path, returning a `'static` reference to it.
+    ///
+    /// The `TempPath` is never dropped, so the file isn't deleted when the program exits (unless
+    /// it was registered for cleanup on exit or on a signal). This is intended for process-global
+    /// fixtures, where destructors never run anyway.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let path = NamedTempFile::new()?.into_temp_path().leak();
+    /// assert!(path.exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn leak(self) -> &'static Path {
+        &Box::leak(Box::new(self)).path
+    }
+
+    /// Disable cleanup of the temporary file. If `disable_cleanup` is `true`, the temporary file
+    /// will not be deleted when this `TempPath` is dropped. This method is equivalent to calling
+    /// [`Builder::disable_cleanup`] when creating the original `TempPath`.
+    ///
+    /// **NOTE:** this method is primarily useful for testing/debugging. If you want to simply
+    /// turn a temporary file-path into a non-temporary file-path, prefer [`TempPath::keep`].
+    pub fn disable_cleanup(&mut self, disable_cleanup: bool) {
+        self.keep = disable_cleanup;
+    }
+
+    /// Delete the temporary file if the process is interrupted by `SIGINT` or `SIGTERM`.
+    ///
+    /// Destructors don't run when a process is killed by a signal so, by default, temporary files
+    /// are leaked in that case. This function registers the temporary file with a process-global
+    /// registry and installs a signal handler (once) that deletes all files and directories
+    /// registered this way before terminating the process with the default signal behavior. The
+    /// file is removed from the registry when it's deleted, persisted, or kept.
+    ///
+    /// This function is only available on Unix when the `signal-cleanup` feature is enabled.
+    ///
+    /// **NOTE:** The installed handler terminates the process after cleaning up. Don't use this if
+    /// your application handles `SIGINT`/`SIGTERM` itself (e.g., for a graceful shutdown). The
+    /// file is deleted on signal even if cleanup has been disabled with
+    /// [`TempPath::disable_cleanup`].
+    ///
+    /// # Errors
+    ///
+    /// If the signal handler cannot be installed, `Err` is returned.
+    #[cfg(all(feature = "signal-cleanup", unix))]
+    pub fn cleanup_on_signal(&self) -> io::Result<()> {
+        registry::signal::install()?;
+        registry::register(&self.path, false, registry::Trigger::Signal);
+        Ok(())
+    }
+
+    /// Delete the temporary file when the process exits, even if its destructor doesn't run.
+    ///
+    /// Destructors don't run when the process exits through [`std::process::exit`] so, by
+    /// default, temporary files are leaked in that case. This function registers the temporary
+    /// file with a process-global registry and installs an `atexit` hook (once) that deletes all
+    /// files and directories registered this way (but not those only registered with
+    /// `cleanup_on_signal`) that are still alive when the process exits. The file is removed from
+    /// the registry when it's deleted, persisted, or kept, so registration never causes a
+    /// double-delete.
+    ///
+    /// This function is only available on Unix and Windows.
+    ///
+    /// **NOTE:** The file is deleted at exit even if cleanup has been disabled with
+    /// [`TempPath::disable_cleanup`].
+    ///
+    /// # Errors
+    ///
+    /// If the exit hook cannot be installed, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let file = NamedTempFile::new()?;
+    /// file.cleanup_on_exit()?;
+    ///
+    /// // The file's destructor won't run, but the file is deleted anyways.
+    /// std::process::exit(0);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(any(unix, windows))]
+    pub fn cleanup_on_exit(&self) -> io::Result<()> {
+        registry::exit::install()?;
+        registry::register(&self.path, false, registry::Trigger::Exit);
+        Ok(())
+    }
+
+    /// Keep temporary file cleaners (e.g., `systemd-tmpfiles`) from deleting this file while it's
+    /// still in use.
+    ///
+    /// Such cleaners usually delete files in the temporary directory that haven't been accessed
+    /// or modified for a while (see the [security](NamedTempFile#security) docs on
+    /// `NamedTempFile`). This function refreshes the file's access and modification times now and
+    /// registers it with a background thread (started once) that refreshes them every hour,
+    /// until the file is deleted, persisted, or kept.
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// # Errors
+    ///
+    /// If the background thread cannot be started, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let file = NamedTempFile::new()?;
+    /// // This file is used by a long-running service.
+    /// file.keep_alive()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn keep_alive(&self) -> io::Result<()> {
+        registry::keepalive::register(&self.path, false)
+    }
+
     /// Create a new TempPath from an existing path. This can be done even if no
     /// file exists at the given path.
     ///
@@ -322,14 +754,64 @@ impl TempPath {
         Self {
             path: path.into().into_boxed_path(),
             keep: false,
+            wipe: false,
+            handle: None,
+            persist_target: None,
+            on_cleanup: None,
+            backend: BackendRef::OS,
         }
     }
 
-    pub(crate) fn new(path: PathBuf, keep: bool) -> Self {
+    pub(crate) fn new(path: PathBuf, builder: &Builder<'_, '_>) -> Self {
         Self {
             path: path.into_boxed_path(),
-            keep,
+            keep: builder.keep,
+            wipe: builder.wipe,
+            handle: None,
+            persist_target: None,
+            on_cleanup: builder.on_cleanup,
+            backend: builder.backend,
+        }
+    }
+
+    /// Returns a [`PathPersistError`] reporting that persisting `self` to `target` failed.
+    fn persist_error(mut self, error: io::Error, target: Option<&Path>) -> PathPersistError {
+        self.persist_target = target.map(Into::into);
+        PathPersistError { error, path: self }
+    }
+
+    /// Move the file to `new_path`, through the handle it was created with if possible.
+    fn persist_to(&self, new_path: &Path, overwrite: bool) -> io::Result<()> {
+        #[cfg(windows)]
+        if let (Some(file), true) = (&self.handle, self.backend.is_os()) {
+            return imp::persist_file(file, &self.path, new_path, overwrite);
+        }
+        self.backend.0.persist(&self.path, new_path, overwrite)
+    }
+
+    /// Overwrite the file with zeros if [`Builder::wipe_on_drop`] was set, returning the handle it
+    /// was overwritten through so that it can be truncated once it has been deleted.
+    ///
+    /// The file is wiped through the handle opened when it was created if possible. Otherwise,
+    /// it's reopened without following symbolic links, and only wiped if it's still a regular file.
+    fn wipe(&self) -> io::Result<Option<File>> {
+        if !self.wipe {
+            return Ok(None);
+        }
+        match &self.handle {
+            Some(file) => file.try_clone().map(Some),
+            None => fs::symlink_metadata(&self.path).and_then(|meta| {
+                if !meta.is_file() {
+                    return Ok(None);
+                }
+                imp::open_nofollow(&self.path).map(Some)
+            }),
         }
+        .and_then(|file| match file {
+            Some(file) if file.metadata()?.is_file() => overwrite(&file).map(|()| Some(file)),
+            _ => Ok(None),
+        })
+        .with_err_path(|| &*self.path)
     }
 }
 
@@ -341,9 +823,19 @@ impl fmt::Debug for TempPath {
 
 impl Drop for TempPath {
     fn drop(&mut self) {
-        if !self.keep {
-            let _ = fs::remove_file(&self.path);
+        if !self.keep && !env::retain_on_drop(&self.path) {
+            // Best-effort: the file is deleted even if it can't be wiped.
+            let wiped = self.wipe();
+            let result = self.backend.0.remove_file(&self.path);
+            if let Ok(Some(file)) = wiped {
+                let _ = file.set_len(0);
+            }
+            crate::error::log_cleanup(&self.path, &result);
+            if let Some(on_cleanup) = self.on_cleanup {
+                on_cleanup(&self.path, result);
+            }
         }
+        registry::unregister(&self.path);
     }
 }
 
@@ -475,6 +967,22 @@ pub struct PersistError<F = File> {
     pub file: NamedTempFile<F>,
 }
 
+impl<F> PersistError<F> {
+    pub(crate) fn new(
+        error: io::Error,
+        mut file: NamedTempFile<F>,
+        target: Option<&Path>,
+    ) -> PersistError<F> {
+        file.path.persist_target = target.map(Into::into);
+        PersistError { error, file }
+    }
+
+    /// Returns the path the temporary file was to be persisted to, if any.
+    pub fn target(&self) -> Option<&Path> {
+        self.file.path.persist_target.as_deref()
+    }
+}
+
 impl<F> fmt::Debug for PersistError<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "PersistError({:?})", self.error)
@@ -497,16 +1005,66 @@ impl<F> From<PersistError<F>> for NamedTempFile<F> {
 
 impl<F> fmt::Display for PersistError<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "failed to persist temporary file: {}", self.error)
+        fmt_persist_error(f, self.file.path(), self.target(), &self.error)
     }
 }
 
+/// Format a persist error as "failed to persist {path} to {target}: {error}".
+pub(crate) fn fmt_persist_error(
+    f: &mut fmt::Formatter<'_>,
+    path: &Path,
+    target: Option<&Path>,
+    error: &io::Error,
+) -> fmt::Result {
+    write!(f, "failed to persist {}", path.display())?;
+    if let Some(target) = target {
+        write!(f, " to {}", target.display())?;
+    }
+    write!(f, ": {}", error)
+}
+
 impl<F> error::Error for PersistError<F> {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         Some(&self.error)
     }
 }
 
+/// Error returned by [`NamedTempFile::from_parts_checked`] when the file handle doesn't refer to
+/// the temporary file.
+pub struct FromPartsError {
+    /// The underlying IO error.
+    pub error: io::Error,
+    /// The file handle that was passed in.
+    pub file: File,
+    /// The temporary file path that was passed in.
+    pub path: TempPath,
+}
+
+impl fmt::Debug for FromPartsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FromPartsError({:?})", self.error)
+    }
+}
+
+impl From<FromPartsError> for io::Error {
+    #[inline]
+    fn from(error: FromPartsError) -> io::Error {
+        error.error
+    }
+}
+
+impl fmt::Display for FromPartsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl error::Error for FromPartsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
 impl NamedTempFile<File> {
     /// Create a new named temporary file.
     ///
@@ -738,9 +1296,12 @@ impl<F> NamedTempFile<F> {
     /// If a file exists at the target path, fail. If this method fails, it will
     /// return `self` in the resulting PersistError.
     ///
-    /// Note: Temporary files cannot be persisted across filesystems. Also Note:
-    /// This method is not atomic. It can leave the original link to the
-    /// temporary file behind.
+    /// Note: Temporary files cannot be persisted across filesystems.
+    ///
+    /// On Linux and Android, this method atomically renames the file with
+    /// `renameat2(RENAME_NOREPLACE)`. On other platforms, and on kernels or filesystems that don't
+    /// support it, the file is hard-linked to the new location and then unlinked. This fallback is
+    /// not atomic: it can leave the original link to the temporary file behind.
     ///
     /// # Security
     ///
@@ -779,9 +1340,60 @@ impl<F> NamedTempFile<F> {
         }
     }
 
+    /// Persist the temporary file in the directory `dir` under a unique name, returning the file
+    /// and its new path.
+    ///
+    /// The file keeps its current name if no file by that name exists in `dir`. Otherwise, a
+    /// random string is appended to the name's stem (before the extension, if any), retrying with
+    /// a new one until an unused name is found. Existing files are never replaced, as with
+    /// [`NamedTempFile::persist_noclobber`].
+    ///
+    /// Note: Temporary files cannot be persisted across filesystems.
+    ///
+    /// # Security
+    ///
+    /// This method persists the temporary file using its path and may not be
+    /// secure in all cases. Please read the security section on the top
+    /// level documentation of this type for details.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to `dir` or no unused name can be found, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::Write;
+    /// use tempfile::Builder;
+    ///
+    /// let mut file = Builder::new().suffix(".csv").tempfile()?;
+    /// writeln!(file, "id,name")?;
+    ///
+    /// let (_, path) = file.persist_in("./outbox")?;
+    /// println!("published {}", path.display());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist_in<P: AsRef<Path>>(self, dir: P) -> Result<(F, PathBuf), PersistError<F>> {
+        let NamedTempFile { path, file } = self;
+        match path.persist_in(dir) {
+            Ok(new_path) => Ok((file, new_path)),
+            Err(err) => {
+                let PathPersistError { error, path } = err;
+                Err(PersistError {
+                    file: NamedTempFile { path, file },
+                    error,
+                })
+            }
+        }
+    }
+
     /// Keep the temporary file from being deleted. This function will turn the
     /// temporary file into a non-temporary file without moving it.
     ///
+    /// Unlike [`NamedTempFile::persist`], the file is not renamed. This is useful when the
+    /// temporary file was already created at its final location (e.g., with
+    /// [`Builder::tempfile_in`] and the target directory) and you just want to stop the
+    /// destructor from removing it. Both the open file and its path are returned.
     ///
     /// # Errors
     ///
@@ -813,6 +1425,56 @@ impl<F> NamedTempFile<F> {
         }
     }
 
+    /// Delete the temporary file if the process is interrupted by `SIGINT` or `SIGTERM`.
+    ///
+    /// This function is only available on Unix when the `signal-cleanup` feature is enabled. See
+    /// [`TempPath::cleanup_on_signal`] for details.
+    ///
+    /// # Errors
+    ///
+    /// If the signal handler cannot be installed, `Err` is returned.
+    #[cfg(all(feature = "signal-cleanup", unix))]
+    pub fn cleanup_on_signal(&self) -> io::Result<()> {
+        self.path.cleanup_on_signal()
+    }
+
+    /// Delete the temporary file when the process exits, even if its destructor doesn't run.
+    ///
+    /// This function is only available on Unix and Windows. See [`TempPath::cleanup_on_exit`]
+    /// for details.
+    ///
+    /// # Errors
+    ///
+    /// If the exit hook cannot be installed, `Err` is returned.
+    #[cfg(any(unix, windows))]
+    pub fn cleanup_on_exit(&self) -> io::Result<()> {
+        self.path.cleanup_on_exit()
+    }
+
+    /// Keep temporary file cleaners from deleting this file while it's still in use.
+    ///
+    /// See [`TempPath::keep_alive`] for details.
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// # Errors
+    ///
+    /// If the background thread cannot be started, `Err` is returned.
+    #[cfg(unix)]
+    pub fn keep_alive(&self) -> io::Result<()> {
+        self.path.keep_alive()
+    }
+
+    /// Disable cleanup of the temporary file. If `disable_cleanup` is `true`, the temporary file
+    /// will not be deleted when this `NamedTempFile` is dropped. This method is equivalent to
+    /// calling [`Builder::disable_cleanup`] when creating the original `NamedTempFile`.
+    ///
+    /// **NOTE:** this method is primarily useful for testing/debugging. If you want to simply
+    /// turn a temporary file into a non-temporary file, prefer [`NamedTempFile::keep`].
+    pub fn disable_cleanup(&mut self, disable_cleanup: bool) {
+        self.path.disable_cleanup(disable_cleanup)
+    }
+
     /// Get a reference to the underlying file.
     pub fn as_file(&self) -> &F {
         &self.file
@@ -835,14 +1497,16 @@ impl<F> NamedTempFile<F> {
     /// This is useful when another process must be able to open the temporary
     /// file.
     pub fn into_temp_path(self) -> TempPath {
-        self.path
+        self.into_parts().1
     }
 
     /// Converts the named temporary file into its constituent parts.
     ///
     /// Note: When the path is dropped, the file is deleted but the file handle
     /// is still usable.
-    pub fn into_parts(self) -> (F, TempPath) {
+    pub fn into_parts(mut self) -> (F, TempPath) {
+        // The path no longer holds the file open.
+        self.path.handle = None;
         (self.file, self.path)
     }
 
@@ -853,6 +1517,125 @@ impl<F> NamedTempFile<F> {
     pub fn from_parts(file: F, path: TempPath) -> Self {
         Self { file, path }
     }
+
+    /// Leak the named temporary file, returning `'static` references to the file and its path.
+    ///
+    /// See [`TempPath::leak`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::Write;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let (file, path) = NamedTempFile::new()?.leak();
+    /// writeln!(file, "Brian was here. Forever.")?;
+    /// assert!(path.exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn leak(self) -> (&'static mut F, &'static Path)
+    where
+        F: 'static,
+    {
+        let this = Box::leak(Box::new(self));
+        (&mut this.file, &this.path)
+    }
+
+    /// Transform the underlying file (e.g., wrap it in a `BufWriter` or an encoder), keeping the
+    /// same temporary path guard.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufWriter, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let mut file = NamedTempFile::new()?.map(BufWriter::new);
+    /// writeln!(file, "Brian was here. Briefly.")?;
+    /// let file = file.try_map(BufWriter::into_inner)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn map<F2>(self, f: impl FnOnce(F) -> F2) -> NamedTempFile<F2> {
+        NamedTempFile {
+            file: f(self.file),
+            path: self.path,
+        }
+    }
+
+    /// Fallibly transform the underlying file, keeping the same temporary path guard.
+    ///
+    /// # Errors
+    ///
+    /// If `f` fails, its error is returned and the temporary file is deleted.
+    pub fn try_map<F2, E>(
+        self,
+        f: impl FnOnce(F) -> Result<F2, E>,
+    ) -> Result<NamedTempFile<F2>, E> {
+        Ok(NamedTempFile {
+            file: f(self.file)?,
+            path: self.path,
+        })
+    }
+
+    pub(crate) fn backend(&self) -> &'static dyn TempBackend {
+        self.path.backend.0
+    }
+}
+
+impl NamedTempFile<BufWriter<File>> {
+    /// Create a new named temporary file wrapped in a [`BufWriter`].
+    ///
+    /// Unbuffered small writes to a file are slow. Use [`NamedTempFile::flush_and_persist`] to
+    /// make sure buffered data isn't lost when persisting the file.
+    ///
+    /// See [`NamedTempFile::new`] for details and [`Builder::tempfile_buffered`] for more
+    /// configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::Write;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let mut file = NamedTempFile::new_buffered()?;
+    /// for i in 0..1000 {
+    ///     writeln!(file, "line {}", i)?;
+    /// }
+    /// file.flush_and_persist("./lines.txt")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn new_buffered() -> io::Result<NamedTempFile<BufWriter<File>>> {
+        Builder::new().tempfile_buffered()
+    }
+
+    /// Create a new named temporary file wrapped in a [`BufWriter`] in the specified directory.
+    ///
+    /// See [`NamedTempFile::new_buffered`].
+    pub fn new_buffered_in<P: AsRef<Path>>(dir: P) -> io::Result<NamedTempFile<BufWriter<File>>> {
+        Builder::new().tempfile_buffered_in(dir)
+    }
+
+    /// Flush any buffered data, then persist the temporary file at the target path, replacing any
+    /// existing file, and return the underlying file.
+    ///
+    /// See [`NamedTempFile::persist`] for details.
+    ///
+    /// # Errors
+    ///
+    /// If the buffered data can't be written out or the file can't be moved to the new location,
+    /// `Err` is returned along with the (still buffered) temporary file.
+    #[allow(clippy::result_large_err)] // The error mirrors `persist`.
+    pub fn flush_and_persist<P: AsRef<Path>>(
+        mut self,
+        new_path: P,
+    ) -> Result<File, PersistError<BufWriter<File>>> {
+        if let Err(error) = self.flush() {
+            return Err(PersistError::new(error, self, Some(new_path.as_ref())));
+        }
+        // The buffer is empty so there's nothing to lose.
+        let (file, _) = self.persist(new_path)?.into_parts();
+        Ok(file)
+    }
 }
 
 impl NamedTempFile<File> {
@@ -884,9 +1667,120 @@ impl NamedTempFile<File> {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     pub fn reopen(&self) -> io::Result<File> {
-        imp::reopen(self.as_file(), NamedTempFile::path(self))
+        self.backend()
+            .reopen(self.as_file(), NamedTempFile::path(self))
             .with_err_path(|| NamedTempFile::path(self))
     }
+
+    /// Securely reopen the temporary file for reading only.
+    ///
+    /// This is like [`NamedTempFile::reopen`] (including the guarantee that the re-opened file is
+    /// the _same_ file), but the returned handle can't be used to modify the file. Use it to hand
+    /// out a read-only view of the file to other components.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be reopened, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::{Read, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let mut file = NamedTempFile::new()?;
+    /// write!(file, "shared data")?;
+    ///
+    /// let mut view = file.reopen_readonly()?;
+    /// let mut contents = String::new();
+    /// view.read_to_string(&mut contents)?;
+    /// assert!(view.write_all(b"oops").is_err());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn reopen_readonly(&self) -> io::Result<File> {
+        self.reopen_with(OpenOptions::new().read(true))
+    }
+
+    /// Securely reopen the temporary file with the given options.
+    ///
+    /// This is like [`NamedTempFile::reopen`], but the new handle is opened with `options`
+    /// instead of read and write access, e.g., to get an append-only handle or to pass
+    /// platform-specific flags. For a read-only handle, use [`NamedTempFile::reopen_readonly`].
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be opened with the given options, or the file at the temporary path has
+    /// been replaced, `Err` is returned.
+    ///
+    /// # Security
+    ///
+    /// Like [`NamedTempFile::reopen`], this guarantees that the re-opened file is the _same_ file.
+    /// However, the check happens after the file has been opened, so options with side effects
+    /// (e.g., [`OpenOptions::truncate`] or [`OpenOptions::create`]) may be applied to another file
+    /// if the temporary file has been replaced. Don't use them.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::OpenOptions;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let file = NamedTempFile::new()?;
+    ///
+    /// let appender = file.reopen_with(OpenOptions::new().append(true))?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn reopen_with(&self, options: &OpenOptions) -> io::Result<File> {
+        self.backend()
+            .reopen_with(self.as_file(), NamedTempFile::path(self), options)
+            .with_err_path(|| NamedTempFile::path(self))
+    }
+
+    /// Creates a `NamedTempFile` from its constituent parts, checking that `file` actually refers
+    /// to the file at `path`.
+    ///
+    /// Unlike [`NamedTempFile::from_parts`], this catches mismatched pairs that would later
+    /// delete a file other than the one written through the handle. The check compares the
+    /// device and inode numbers on Unix and the volume serial number and file index on Windows.
+    ///
+    /// # Errors
+    ///
+    /// If `file` doesn't refer to the file at `path` (or the check fails), `Err` is returned. Both
+    /// parts are returned in the resulting [`FromPartsError`], so nothing is deleted or closed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let (file, path) = NamedTempFile::new()?.into_parts();
+    /// let file = NamedTempFile::from_parts_checked(file, path)?;
+    ///
+    /// let (_, other_path) = NamedTempFile::new()?.into_parts();
+    /// let (file, _) = file.into_parts();
+    /// let err = NamedTempFile::from_parts_checked(file, other_path).unwrap_err();
+    /// // Both parts are still usable.
+    /// let (file, other_path) = (err.file, err.path);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn from_parts_checked(file: File, path: TempPath) -> Result<NamedTempFile, FromPartsError> {
+        let checked = imp::same_file(&file, &path)
+            .and_then(|same| {
+                if same {
+                    Ok(())
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "file handle doesn't refer to the temporary file",
+                    ))
+                }
+            })
+            .with_err_path(|| &*path);
+        match checked {
+            Ok(()) => Ok(NamedTempFile { file, path }),
+            Err(error) => Err(FromPartsError { error, file, path }),
+        }
+    }
 }
 
 impl<F: Read> Read for NamedTempFile<F> {
@@ -1042,24 +1936,95 @@ impl<F: AsRawHandle> AsRawHandle for NamedTempFile<F> {
     }
 }
 
+/// Close the file, reporting any error returned by the OS.
+pub(crate) fn close(file: File) -> io::Result<()> {
+    imp::close(file)
+}
+
+/// Overwrite the contents of the file with zeros, sync them to disk, and truncate the file.
+pub(crate) fn wipe(file: &File) -> io::Result<()> {
+    overwrite(file)?;
+    file.set_len(0)
+}
+
+/// Overwrite the contents of the file with zeros and sync them to disk.
+///
+/// The file must not be in append mode.
+fn overwrite(mut file: &File) -> io::Result<()> {
+    static ZEROS: [u8; 8192] = [0; 8192];
+    let mut remaining = file.metadata()?.len();
+    file.seek(SeekFrom::Start(0))?;
+    while remaining > 0 {
+        let n = remaining.min(ZEROS.len() as u64) as usize;
+        file.write_all(&ZEROS[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_data()
+}
+
 pub(crate) fn create_named(
     mut path: PathBuf,
     open_options: &mut OpenOptions,
-    permissions: Option<&std::fs::Permissions>,
-    keep: bool,
+    builder: &Builder<'_, '_>,
 ) -> io::Result<NamedTempFile> {
     // Make the path absolute. Otherwise, changing directories could cause us to
     // delete the wrong file.
     if !path.is_absolute() {
         path = std::env::current_dir()?.join(path)
     }
-    imp::create_named(&path, open_options, permissions)
+    #[cfg(windows)]
+    let result = match crate::security::SecurityDescriptor::new(&builder.security)? {
+        Some(_) if builder.open_options.is_some() => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "custom open options can't be combined with a security descriptor",
+        )),
+        Some(descriptor) => {
+            crate::security::create_file(&path, builder.append, builder.attributes, &descriptor)
+        }
+        None => builder
+            .backend
+            .0
+            .create_file(&path, open_options, builder.permissions.as_ref()),
+    };
+    #[cfg(not(windows))]
+    let result = builder
+        .backend
+        .0
+        .create_file(&path, open_options, builder.permissions.as_ref());
+    let mut file = result
         .with_err_path(|| path.clone())
         .map(|file| NamedTempFile {
-            path: TempPath {
-                path: path.into_boxed_path(),
-                keep,
-            },
+            path: TempPath::new(path, builder),
             file,
-        })
+        })?;
+    if builder.wipe {
+        // The file may have been opened in append mode, which would append the zeros instead of
+        // overwriting the contents, so wipe it through a new write-only handle.
+        let handle = builder
+            .backend
+            .0
+            .reopen_with(file.as_file(), file.path(), OpenOptions::new().write(true))
+            .or_else(|e| {
+                if builder.append || builder.open_options.is_some() {
+                    Err(e)
+                } else {
+                    file.as_file().try_clone()
+                }
+            })
+            .with_err_path(|| file.path())?;
+        file.path.handle = Some(handle);
+    }
+    #[cfg(windows)]
+    if file.path.handle.is_none() && builder.backend.is_os() {
+        file.path.handle = Some(file.as_file().try_clone().with_err_path(|| file.path())?);
+    }
+    #[cfg(unix)]
+    builder.apply_owner(file.as_fd(), file.path())?;
+    builder.apply_exact_permissions(file.path(), builder.permissions.as_ref(), |permissions| {
+        file.as_file().set_permissions(permissions)
+    })?;
+    if builder.preallocate > 0 {
+        imp::preallocate(file.as_file(), builder.preallocate).with_err_path(|| file.path())?;
+    }
+    Ok(file)
 }
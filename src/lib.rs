@@ -61,7 +61,7 @@
 //! Temporary _files_ created with this library are private by default on all operating systems.
 //! However, temporary _directories_ are created with the default permissions and will therefore be
 //! world-readable by default unless the user has changed their umask and/or default temporary
-//! directory.
+//! directory. Use [`Builder::dir_permissions`] to restrict them.
 //!
 //! ### Denial of Service
 //!
@@ -78,6 +78,17 @@
 //!    create temporary a file (when the `getrandom` feature is enabled as it is by default on all
 //!    major platforms).
 //!
+//! Users on shared multi-user systems can make names unpredictable altogether with
+//! `Builder::secure_names`, which draws them from the operating system's secure random number
+//! generator.
+//!
+//! Building without the default `fastrand` feature and with the `sequential-names` feature instead
+//! removes the random number generator entirely: names are then derived from the PID, the time,
+//! and a counter (see [`Alphanumeric`]). This keeps the dependency tree minimal and the naming
+//! auditable, at the cost of making names predictable. Disabling `fastrand` without enabling
+//! `sequential-names` is a compile error, so that this is never done by accident (e.g., by
+//! disabling default features).
+//!
 //! ## Early drop pitfall
 //!
 //! Because `TempDir` and `NamedTempFile` rely on their destructors for cleanup, this can lead
@@ -107,6 +118,19 @@
 //!
 //! The `touch` command would fail with an `No such file or directory` error.
 //!
+//! [`with_tempdir`] sidesteps this pitfall by scoping the directory to a closure.
+//!
+//! ## Logging
+//!
+//! With the `log` feature enabled, creating, persisting, and automatically deleting temporary
+//! files and directories is logged with the [`log`](https://docs.rs/log) crate at the `debug`
+//! level. Failures to delete temporary files and directories on drop, which are otherwise silently
+//! ignored, are logged at the `warn` level.
+//!
+//! With the `debug-registry` feature enabled, `tempfile::debug::live_resources()` lists the
+//! temporary files and directories that are still alive, which helps track down leaks in
+//! long-running processes.
+//!
 //! ## Examples
 //!
 //! Create a temporary file and write some data into it:
@@ -195,48 +219,191 @@
 #[cfg(doctest)]
 doc_comment::doctest!("../README.md");
 
+#[cfg(not(any(feature = "fastrand", feature = "sequential-names")))]
+compile_error!(
+    "the `fastrand` feature is required for random names; enable `sequential-names` instead to \
+     use predictable, counter-based names"
+);
+
 const NUM_RETRIES: u32 = 65536;
 const NUM_RAND_CHARS: usize = 6;
 
+use std::borrow::Cow;
 use std::ffi::OsStr;
-use std::fs::OpenOptions;
-use std::io;
-use std::path::Path;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::backend::BackendRef;
+use crate::error::IoResultExt;
+use crate::name::GeneratorRef;
+use crate::util::RetryPolicy;
 
+/// Log with the [`log`](https://docs.rs/log) crate if the `log` feature is enabled. Otherwise, the
+/// arguments are type-checked but never evaluated.
+macro_rules! log {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        ::log::$level!($($arg)+);
+        #[cfg(not(feature = "log"))]
+        if false {
+            let _ = ::std::format_args!($($arg)+);
+        }
+    }};
+}
+
+mod atomic;
+mod backend;
 mod dir;
+#[cfg(all(feature = "encryption", any(unix, windows, target_os = "wasi")))]
+mod encrypted;
 mod error;
 mod file;
+#[cfg(feature = "memmap")]
+mod mapped;
+mod name;
+mod pid;
+#[cfg(windows)]
+mod pipe;
+mod registry;
+#[cfg(windows)]
+mod security;
+mod sequence;
+#[cfg(feature = "serde")]
+mod serde;
+mod set;
 mod spooled;
+#[cfg(target_os = "linux")]
+mod unnamed;
 mod util;
 
+#[cfg(feature = "debug-registry")]
+pub mod debug;
 pub mod env;
+#[cfg(feature = "tokio")]
+pub mod tokio;
 
-pub use crate::dir::{tempdir, tempdir_in, TempDir};
+pub use crate::atomic::{atomic_copy, persist_all, write_atomic, AtomicWriteFile, PersistAllError};
+pub use crate::backend::{OsBackend, TempBackend};
+#[doc(hidden)]
+pub use crate::dir::{__caller_prefix, __type_name_of};
+pub use crate::dir::{
+    tempdir, tempdir_in, try_with_tempdir, with_tempdir, CleanupError, DirPersistError, DirUsage,
+    OpenInOptions, SharedTempDir, TempDir, TempDirPath,
+};
+#[cfg(all(feature = "encryption", any(unix, windows, target_os = "wasi")))]
+pub use crate::encrypted::EncryptedTempFile;
+pub use crate::env::app_temp_dir;
+pub use crate::error::Error;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub use crate::file::memfile;
 pub use crate::file::{
-    tempfile, tempfile_in, NamedTempFile, PathPersistError, PersistError, TempPath,
+    tempfile, tempfile_in, tempfile_shared, tempfile_shared_in, tempfile_sized, try_with_tempfile,
+    with_tempfile, FromPartsError, NamedTempFile, PathPersistError, PersistError, TempPath,
 };
-pub use crate::spooled::{spooled_tempfile, SpooledData, SpooledTempFile};
+#[cfg(feature = "memmap")]
+pub use crate::mapped::MappedTempFile;
+pub use crate::name::{seed_rng, Alphanumeric, Charset, NameGenerator};
+#[cfg(windows)]
+pub use crate::pipe::TempNamedPipe;
+#[cfg(windows)]
+pub use crate::security::WindowsSecurity;
+pub use crate::sequence::TempSequence;
+pub use crate::set::TempFileSet;
+pub use crate::spooled::{
+    spooled_tempfile, spooled_tempfile_auto, SpooledData, SpooledNamedTempFile,
+    SpooledPersistError, SpooledPool, SpooledTempFile,
+};
+#[cfg(target_os = "linux")]
+pub use crate::unnamed::{UnnamedPersistError, UnnamedTempFile};
+
+/// A callback invoked with the path of a temporary file or directory and the result of deleting it.
+pub(crate) type OnCleanup = fn(&Path, io::Result<()>);
 
 /// Create a new temporary file or directory with custom options.
+// Hooks are compared by address. This can produce false negatives but that's acceptable here.
+#[allow(unknown_lints, unpredictable_function_pointer_comparisons)]
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Builder<'a, 'b> {
     random_len: usize,
     prefix: &'a OsStr,
     suffix: &'b OsStr,
     append: bool,
+    open_options: Option<fn(&mut OpenOptions)>,
     permissions: Option<std::fs::Permissions>,
+    dir_permissions: Option<std::fs::Permissions>,
+    exact_permissions: bool,
+    preallocate: u64,
+    wipe: bool,
+    #[cfg(unix)]
+    owner: Option<u32>,
+    #[cfg(unix)]
+    group: Option<u32>,
+    #[cfg(windows)]
+    attributes: u32,
+    #[cfg(windows)]
+    security: WindowsSecurity,
     keep: bool,
+    background_cleanup: bool,
+    recursive_cleanup: bool,
+    prefer_memory: bool,
+    tag_pid: bool,
+    on_create: Option<fn(&Path)>,
+    on_cleanup: Option<OnCleanup>,
+    backend: BackendRef,
+    name_generator: GeneratorRef,
+    #[cfg(all(feature = "getrandom", any(unix, windows, target_os = "wasi")))]
+    secure_names: bool,
+    retry: RetryPolicy,
+    validate_dir: bool,
+    subdirs: Vec<PathBuf>,
 }
 
 impl Default for Builder<'_, '_> {
     fn default() -> Self {
+        env::default_builder()
+            .cloned()
+            .unwrap_or_else(Builder::builtin_default)
+    }
+}
+
+impl Builder<'static, 'static> {
+    /// The built-in defaults, ignoring [`env::override_default_builder`].
+    fn builtin_default() -> Self {
         Builder {
             random_len: crate::NUM_RAND_CHARS,
             prefix: OsStr::new(".tmp"),
             suffix: OsStr::new(""),
             append: false,
+            open_options: None,
             permissions: None,
+            dir_permissions: None,
+            exact_permissions: false,
+            preallocate: 0,
+            wipe: false,
+            #[cfg(unix)]
+            owner: None,
+            #[cfg(unix)]
+            group: None,
+            #[cfg(windows)]
+            attributes: windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_TEMPORARY,
+            #[cfg(windows)]
+            security: WindowsSecurity::Inherit,
             keep: false,
+            background_cleanup: false,
+            recursive_cleanup: true,
+            prefer_memory: false,
+            tag_pid: false,
+            on_create: None,
+            on_cleanup: None,
+            backend: BackendRef::OS,
+            name_generator: GeneratorRef::DEFAULT,
+            #[cfg(all(feature = "getrandom", any(unix, windows, target_os = "wasi")))]
+            secure_names: false,
+            retry: RetryPolicy::default(),
+            validate_dir: false,
+            subdirs: Vec::new(),
         }
     }
 }
@@ -244,6 +411,8 @@ impl Default for Builder<'_, '_> {
 impl<'a, 'b> Builder<'a, 'b> {
     /// Create a new `Builder`.
     ///
+    /// The defaults can be changed program-wide with [`env::override_default_builder`].
+    ///
     /// # Examples
     ///
     /// Create a named temporary file and write some data into it:
@@ -371,6 +540,185 @@ impl<'a, 'b> Builder<'a, 'b> {
         self
     }
 
+    /// Set the character set used for the random part of names.
+    ///
+    /// Mixed-case names effectively lose entropy on case-insensitive filesystems (e.g., FAT and the
+    /// default macOS and Windows volumes), and some tools don't cope well with uppercase letters.
+    /// When using a smaller character set, consider increasing [`Builder::rand_bytes`] to
+    /// compensate.
+    ///
+    /// This replaces any [`Builder::name_generator`] (and vice versa).
+    ///
+    /// Default: [`Charset::Alphanumeric`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::{Builder, Charset};
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .prefix("")
+    ///     .rand_bytes(8)
+    ///     .rand_charset(Charset::Hex)
+    ///     .tempfile()?;
+    /// let name = named_tempfile.path().file_name().unwrap().to_str().unwrap();
+    /// assert!(name.bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase()));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn rand_charset(&mut self, charset: Charset) -> &mut Self {
+        self.name_generator = GeneratorRef(charset.as_static(), Some(charset));
+        self
+    }
+
+    /// Draw the random characters of names from the operating system's cryptographically secure
+    /// random number generator instead of the (seedable) userspace one.
+    ///
+    /// By default, names are random but predictable to a determined attacker on the same system,
+    /// who could use this to prevent temporary file creation by creating files with the predicted
+    /// names first (see the crate-level documentation). Secure names are unpredictable, at the
+    /// cost of a system call per name. [`seed_rng`] has no effect on secure names.
+    ///
+    /// This applies to the built-in generators (see [`Builder::rand_charset`]) and is ignored when
+    /// a custom [`Builder::name_generator`] is set.
+    ///
+    /// Only available with the `getrandom` feature (enabled by default).
+    ///
+    /// Default: `false`.
+    ///
+    /// If the operating system's random number generator fails, names fall back on the default,
+    /// predictable generator (logging a warning with the `log` feature).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let named_tempfile = Builder::new().secure_names(true).tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(all(feature = "getrandom", any(unix, windows, target_os = "wasi")))]
+    pub fn secure_names(&mut self, secure_names: bool) -> &mut Self {
+        self.secure_names = secure_names;
+        self
+    }
+
+    /// Returns the name generator to use, taking [`Builder::secure_names`] into account.
+    pub(crate) fn generator(&self) -> &'static dyn NameGenerator {
+        #[cfg(all(feature = "getrandom", any(unix, windows, target_os = "wasi")))]
+        if let (true, Some(charset)) = (self.secure_names, self.name_generator.1) {
+            return crate::name::Secure::for_charset(charset);
+        }
+        self.name_generator.0
+    }
+
+    /// Set the maximum number of times to retry with a new name when a temporary file or directory
+    /// can't be created because the name is already taken.
+    ///
+    /// By default, many retries are made (unless [`Builder::rand_bytes`] is `0`), as names can
+    /// collide by chance or be taken deliberately by other users. Callers on exotic filesystems
+    /// where `AlreadyExists` indicates a misconfiguration rather than a collision may want to fail
+    /// fast instead. With `0`, only a single attempt is made and its error is returned as-is.
+    ///
+    /// When giving up after exhausting all retries, the returned error wraps an
+    /// [`Error::TooManyCollisions`] reporting the directory, the number of attempts made, and the
+    /// last name that was tried.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let named_tempfile = Builder::new().max_retries(3).tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn max_retries(&mut self, max_retries: u32) -> &mut Self {
+        self.retry.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Also retry with a new name when creating a temporary file or directory fails with an error
+    /// for which `retry_on` returns `true`.
+    ///
+    /// By default, only [`std::io::ErrorKind::AlreadyExists`] and
+    /// [`std::io::ErrorKind::AddrInUse`] are treated as name collisions. Some resources created
+    /// with [`Builder::make`] report collisions differently (e.g., with
+    /// [`std::io::ErrorKind::PermissionDenied`] or a platform-specific error code).
+    ///
+    /// As such errors may also be persistent, only a few attempts are made when they're the reason
+    /// for retrying, unless [`Builder::max_retries`] is set explicitly. Once the attempts are
+    /// exhausted, the last error is returned as-is.
+    ///
+    /// Default: none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io;
+    /// use tempfile::Builder;
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .retry_on(|e| e.kind() == io::ErrorKind::PermissionDenied)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn retry_on(&mut self, retry_on: fn(&io::Error) -> bool) -> &mut Self {
+        self.retry.retry_on = Some(retry_on);
+        self
+    }
+
+    /// Check that the target directory is usable before creating a temporary file or directory in
+    /// it (see [`env::validate_temp_dir`]).
+    ///
+    /// When enabled, creating a temporary file or directory in a missing, non-directory, or
+    /// read-only directory fails with an error describing the problem instead of whatever error
+    /// the underlying system call happens to return. This costs an extra file creation and
+    /// deletion, so it's best reserved for directories that may be misconfigured.
+    ///
+    /// This is ignored by [`Builder::make_async`] and [`Builder::make_in_async`], which can't
+    /// block.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let err = Builder::new()
+    ///     .validate_dir(true)
+    ///     .tempfile_in("/does/not/exist")
+    ///     .unwrap_err();
+    /// assert!(err.to_string().contains("inaccessible"));
+    /// ```
+    pub fn validate_dir(&mut self, validate: bool) -> &mut Self {
+        self.validate_dir = validate;
+        self
+    }
+
+    /// Set how long to wait before each retry (see [`Builder::max_retries`]).
+    ///
+    /// This is ignored by [`Builder::make_async`] and [`Builder::make_in_async`], which can't sleep
+    /// without blocking the executor.
+    ///
+    /// Default: no delay.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use tempfile::Builder;
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .max_retries(10)
+    ///     .retry_backoff(Duration::from_millis(1))
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn retry_backoff(&mut self, backoff: std::time::Duration) -> &mut Self {
+        self.retry.backoff = backoff;
+        self
+    }
+
     /// Set the file to be opened in append mode.
     ///
     /// Default: `false`.
@@ -390,8 +738,47 @@ impl<'a, 'b> Builder<'a, 'b> {
         self
     }
 
+    /// Customize the options used to open temporary files created by this builder.
+    ///
+    /// The callback is invoked with the [`OpenOptions`] (with [`Builder::append`] and, on Windows,
+    /// `Builder::attributes` already applied) before each attempt to create a file, and can be
+    /// used to set platform-specific flags such as `O_NONBLOCK` or custom Windows share modes. The
+    /// file is always created for reading and writing and must not already exist (i.e.,
+    /// `create_new`, `read`, and `write` are always set afterwards and can't be overridden).
+    /// Similarly, the Unix mode is always set from [`Builder::permissions`]. The Windows file
+    /// attributes, on the other hand, can be overridden by the callback.
+    ///
+    /// This option is ignored when using [`Builder::make`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// # #[cfg(target_os = "linux")]
+    /// # {
+    /// use std::os::unix::fs::OpenOptionsExt;
+    ///
+    /// const O_NONBLOCK: i32 = 0o4000;
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .open_options(|options| {
+    ///         options.custom_flags(O_NONBLOCK);
+    ///     })
+    ///     .tempfile()?;
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn open_options(&mut self, open_options: fn(&mut OpenOptions)) -> &mut Self {
+        self.open_options = Some(open_options);
+        self
+    }
+
     /// The permissions to create the tempfile or [tempdir](Self::tempdir) with.
     ///
+    /// Directories are created with [`Builder::dir_permissions`] instead, if set. Otherwise, on
+    /// Unix, directories are created with the execute bit added wherever the read bit is set.
+    ///
     /// # Security
     ///
     /// By default, the permissions of tempfiles on Unix are set for it to be
@@ -459,196 +846,1131 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// ```
     pub fn permissions(&mut self, permissions: std::fs::Permissions) -> &mut Self {
         self.permissions = Some(permissions);
+        self.exact_permissions = false;
         self
     }
 
-    /// Set the file/folder to be kept even when the [`NamedTempFile`]/[`TempDir`] goes out of
-    /// scope.
+    /// Like [`Builder::permissions`], but the permissions are applied exactly, ignoring the
+    /// process `umask`.
     ///
-    /// By default, the file/folder is automatically cleaned up in the destructor of
-    /// [`NamedTempFile`]/[`TempDir`]. When `keep` is set to `true`, this behavior is supressed.
+    /// On Unix, the permissions are set again (with `fchmod` for files) right after the tempfile
+    /// or [tempdir](Self::tempdir) is created, so the `umask` can't narrow them. This also applies
+    /// to [`Builder::dir_permissions`] and [subdirectories](Self::subdirs). Whichever of this
+    /// method, [`Builder::permissions`], and [`Builder::mode`] is called last takes effect.
+    ///
+    /// On other platforms, this is equivalent to [`Builder::permissions`].
+    ///
+    /// # Errors
+    ///
+    /// If the permissions can't be set, creating the tempfile or tempdir fails and it's deleted.
     ///
     /// # Examples
     ///
+    /// Create a world-readable file for another service, regardless of the `umask`.
+    ///
     /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::fs::Permissions;
+    /// use std::os::unix::fs::PermissionsExt;
     /// use tempfile::Builder;
     ///
-    /// let named_tempfile = Builder::new()
-    ///     .keep(true)
+    /// let tempfile = Builder::new()
+    ///     .permissions_exact(Permissions::from_mode(0o644))
     ///     .tempfile()?;
+    /// let mode = tempfile.as_file().metadata()?.permissions().mode();
+    /// assert_eq!(mode & 0o777, 0o644);
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    pub fn keep(&mut self, keep: bool) -> &mut Self {
-        self.keep = keep;
+    pub fn permissions_exact(&mut self, permissions: std::fs::Permissions) -> &mut Self {
+        self.permissions(permissions);
+        self.exact_permissions = true;
         self
     }
 
-    /// Create the named temporary file.
+    /// Set the Unix mode to create the tempfile or [tempdir](Self::tempdir) with.
     ///
-    /// # Security
+    /// This is a shortcut for [`Builder::permissions`] with
+    /// [`Permissions::from_mode`](std::os::unix::fs::PermissionsExt::from_mode); whichever of the
+    /// two is called last takes effect. As with `permissions`, the process `umask` still applies.
     ///
-    /// See [the security][security] docs on `NamedTempFile`.
+    /// # Examples
     ///
-    /// # Resource leaking
+    /// ```
+    /// use std::os::unix::fs::PermissionsExt;
+    /// use tempfile::Builder;
     ///
-    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    /// let tempfile = Builder::new().mode(0o640).tempfile()?;
+    /// let mode = tempfile.as_file().metadata()?.permissions().mode();
+    /// assert_eq!(mode & 0o777, 0o640);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        use std::os::unix::fs::PermissionsExt;
+        self.permissions(std::fs::Permissions::from_mode(mode))
+    }
+
+    /// The permissions to create [tempdirs](Self::tempdir) with, overriding
+    /// [`Builder::permissions`] for directories.
     ///
-    /// # Errors
+    /// This lets a single builder create private files and directories (e.g., `0o600` and `0o700`
+    /// on Unix), and keeps file permissions (which usually lack the execute bit) from making
+    /// directories unusable. If unset, directories are created with [`Builder::permissions`] if
+    /// set (on Unix, with the execute bit added wherever the read bit is set, e.g., `0o600` becomes
+    /// `0o700`), or the platform default otherwise. [Subdirectories](Self::subdirs) are created
+    /// with the same permissions.
     ///
-    /// If the file cannot be created, `Err` is returned.
+    /// See [`Builder::permissions`] for platform notes.
     ///
     /// # Examples
     ///
     /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::fs::Permissions;
+    /// use std::os::unix::fs::PermissionsExt;
     /// use tempfile::Builder;
     ///
-    /// let tempfile = Builder::new().tempfile()?;
+    /// let mut builder = Builder::new();
+    /// builder
+    ///     .permissions(Permissions::from_mode(0o600))
+    ///     .dir_permissions(Permissions::from_mode(0o700));
+    ///
+    /// let tmp_dir = builder.tempdir()?;
+    /// let mode = tmp_dir.path().metadata()?.permissions().mode();
+    /// assert_eq!(mode & 0o777, 0o700);
+    ///
+    /// let tmp_file = builder.tempfile_in(&tmp_dir)?;
+    /// let mode = tmp_file.as_file().metadata()?.permissions().mode();
+    /// assert_eq!(mode & 0o777, 0o600);
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    ///
-    /// [security]: struct.NamedTempFile.html#security
-    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
-    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
-        self.tempfile_in(env::temp_dir())
+    pub fn dir_permissions(&mut self, permissions: std::fs::Permissions) -> &mut Self {
+        self.dir_permissions = Some(permissions);
+        self
     }
 
-    /// Create the named temporary file in the specified directory.
-    ///
-    /// # Security
+    /// Reserve `bytes` of disk space for each named tempfile when it's created.
     ///
-    /// See [the security][security] docs on `NamedTempFile`.
+    /// The space is reserved with `fallocate` (or `posix_fallocate`) on Unix and by setting the end
+    /// of the file on Windows, so creating the tempfile fails up front if there isn't enough space
+    /// instead of failing midway through writing it. The file is extended to `bytes` (zero-filled)
+    /// bytes, so write to it from the start and [truncate](std::fs::File::set_len) it if less
+    /// data is written. On filesystems that don't support preallocation, the file is only extended.
     ///
-    /// # Resource leaking
+    /// See [`tempfile_sized`] for unnamed tempfiles.
     ///
-    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    /// Default: `0` (nothing is reserved).
     ///
     /// # Errors
     ///
-    /// If the file cannot be created, `Err` is returned.
+    /// If the space can't be reserved, creating the tempfile fails and it's deleted.
     ///
     /// # Examples
     ///
     /// ```
     /// use tempfile::Builder;
     ///
-    /// let tempfile = Builder::new().tempfile_in("./")?;
+    /// let tempfile = Builder::new().preallocate(4096).tempfile()?;
+    /// assert_eq!(tempfile.as_file().metadata()?.len(), 4096);
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    ///
-    /// [security]: struct.NamedTempFile.html#security
-    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
-    pub fn tempfile_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<NamedTempFile> {
-        util::create_helper(
-            dir.as_ref(),
-            self.prefix,
-            self.suffix,
-            self.random_len,
-            |path| {
-                file::create_named(
-                    path,
-                    OpenOptions::new().append(self.append),
-                    self.permissions.as_ref(),
-                    self.keep,
-                )
-            },
-        )
+    pub fn preallocate(&mut self, bytes: u64) -> &mut Self {
+        self.preallocate = bytes;
+        self
     }
 
-    /// Attempts to make a temporary directory inside of [`env::temp_dir()`] whose
-    /// name will have the prefix, `prefix`. The directory and
-    /// everything inside it will be automatically deleted once the
-    /// returned `TempDir` is destroyed.
+    /// Overwrite the contents of each named tempfile with zeros before deleting it.
     ///
-    /// # Resource leaking
+    /// When the [`NamedTempFile`]/[`TempPath`] is dropped or closed, the file is overwritten with
+    /// zeros, synced to disk, and truncated before it's deleted. This is useful for tempfiles
+    /// holding credentials, keys, or other secrets. Files that are persisted or kept are left
+    /// alone. See [`SpooledTempFile::wipe_on_drop`] for spooled tempfiles.
     ///
-    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    /// The file is wiped through a handle held since it was created. Once the handle has been
+    /// given up (with [`NamedTempFile::into_temp_path`] or [`NamedTempFile::into_parts`]), the
+    /// file is reopened without following symbolic links, and isn't wiped if something other than
+    /// a regular file has been put in its place.
     ///
-    /// # Errors
+    /// Default: `false`.
     ///
-    /// If the directory can not be created, `Err` is returned.
+    /// # Security
+    ///
+    /// Wiping is best-effort. Journaling and copy-on-write filesystems (e.g., btrfs, ZFS, APFS)
+    /// write the zeros to new blocks, and SSDs remap writes internally, so the original contents
+    /// may survive on disk until they're overwritten by later writes. Use full-disk encryption or
+    /// keep secrets in memory if that's a concern. Wiping is skipped if the process is killed
+    /// before the destructor runs. Errors while wiping are ignored when dropping the file; call
+    /// [`TempPath::close`] (or [`NamedTempFile::close`]) to detect them.
     ///
     /// # Examples
     ///
     /// ```
     /// use tempfile::Builder;
+    /// use std::io::Write;
     ///
-    /// let tmp_dir = Builder::new().tempdir()?;
+    /// let mut secret = Builder::new().wipe_on_drop(true).tempfile()?;
+    /// secret.write_all(b"hunter2")?;
+    /// // Overwrites "hunter2" with zeros before deleting the file.
+    /// secret.close()?;
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    ///
-    /// [resource-leaking]: struct.TempDir.html#resource-leaking
-    pub fn tempdir(&self) -> io::Result<TempDir> {
-        self.tempdir_in(env::temp_dir())
+    pub fn wipe_on_drop(&mut self, wipe: bool) -> &mut Self {
+        self.wipe = wipe;
+        self
     }
 
-    /// Attempts to make a temporary directory inside of `dir`.
-    /// The directory and everything inside it will be automatically
-    /// deleted once the returned `TempDir` is destroyed.
+    /// Set the owner and/or group of the tempfile or [tempdir](Self::tempdir) after creating it.
     ///
-    /// # Resource leaking
+    /// The ownership is changed with `fchown` on a handle to the new file or directory (including
+    /// any [subdirectories](Self::subdirs)), so it can't be redirected by replacing the path.
+    /// `None` leaves the owner or group unchanged. Changing the owner usually requires elevated
+    /// privileges, while the group can be changed to any group the current user is a member of.
     ///
-    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    /// Default: unchanged.
     ///
     /// # Errors
     ///
-    /// If the directory can not be created, `Err` is returned.
+    /// If the ownership can't be changed, creating the tempfile or tempdir fails and it's deleted.
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::os::unix::fs::MetadataExt;
     /// use tempfile::Builder;
     ///
-    /// let tmp_dir = Builder::new().tempdir_in("./")?;
+    /// // Any group the current user belongs to (here, the default one).
+    /// let gid = tempfile::tempfile()?.metadata()?.gid();
+    /// let tempfile = Builder::new().owner(None, Some(gid)).tempfile()?;
+    /// assert_eq!(tempfile.as_file().metadata()?.gid(), gid);
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    ///
-    /// [resource-leaking]: struct.TempDir.html#resource-leaking
-    pub fn tempdir_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<TempDir> {
-        let storage;
-        let mut dir = dir.as_ref();
-        if !dir.is_absolute() {
-            let cur_dir = std::env::current_dir()?;
-            storage = cur_dir.join(dir);
-            dir = &storage;
-        }
-
-        util::create_helper(dir, self.prefix, self.suffix, self.random_len, |path| {
-            dir::create(path, self.permissions.as_ref(), self.keep)
-        })
+    #[cfg(unix)]
+    pub fn owner(&mut self, uid: Option<u32>, gid: Option<u32>) -> &mut Self {
+        self.owner = uid;
+        self.group = gid;
+        self
     }
 
-    /// Attempts to create a temporary file (or file-like object) using the
-    /// provided closure. The closure is passed a temporary file path and
-    /// returns an [`std::io::Result`]. The path provided to the closure will be
-    /// inside of [`env::temp_dir()`]. Use [`Builder::make_in`] to provide
-    /// a custom temporary directory. If the closure returns one of the
-    /// following errors, then another randomized file path is tried:
-    ///  - [`std::io::ErrorKind::AlreadyExists`]
-    ///  - [`std::io::ErrorKind::AddrInUse`]
-    ///
-    /// This can be helpful for taking full control over the file creation, but
-    /// leaving the temporary file path construction up to the library. This
-    /// also enables creating a temporary UNIX domain socket, since it is not
-    /// possible to bind to a socket that already exists.
+    /// Set the subdirectories to create inside each new [tempdir](Self::tempdir), replacing any
+    /// previously configured ones.
     ///
-    /// Note that [`Builder::append`] is ignored when using [`Builder::make`].
+    /// The subdirectories are relative paths, created (along with any missing parents) with the
+    /// configured [directory permissions](Self::dir_permissions) before the [`TempDir`] is
+    /// returned. If any of them can't be created, the temporary directory is deleted and `Err` is
+    /// returned.
     ///
-    /// # Security
-    ///
-    /// This has the same [security implications][security] as
-    /// [`NamedTempFile`], but with additional caveats. Specifically, it is up
-    /// to the closure to ensure that the file does not exist and that such a
-    /// check is *atomic*. Otherwise, a [time-of-check to time-of-use
-    /// bug][TOCTOU] could be introduced.
+    /// Default: none.
     ///
-    /// For example, the following is **not** secure:
+    /// # Examples
     ///
     /// ```
-    /// use std::fs::File;
     /// use tempfile::Builder;
     ///
-    /// // This is NOT secure!
-    /// let tempfile = Builder::new().make(|path| {
+    /// let tmp_dir = Builder::new()
+    ///     .subdirs(["in", "out", "cache/objects"])
+    ///     .tempdir()?;
+    /// assert!(tmp_dir.path().join("in").is_dir());
+    /// assert!(tmp_dir.path().join("cache/objects").is_dir());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn subdirs<I>(&mut self, subdirs: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: Into<PathBuf>,
+    {
+        self.subdirs = subdirs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the file/folder to be kept even when the [`NamedTempFile`]/[`TempDir`] goes out of
+    /// scope.
+    ///
+    /// This is an alias for [`Builder::disable_cleanup`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .keep(true)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn keep(&mut self, keep: bool) -> &mut Self {
+        self.disable_cleanup(keep)
+    }
+
+    /// Disable cleanup of the files/folders created by this builder, even when the
+    /// [`NamedTempFile`]/[`TempDir`] goes out of scope.
+    ///
+    /// By default, the file/folder is automatically cleaned up in the destructor of
+    /// [`NamedTempFile`]/[`TempDir`]. When `disable_cleanup` is set to `true`, this behavior is
+    /// suppressed for every file and directory created by this builder. This is primarily useful
+    /// for testing/debugging (e.g., to inspect outputs after a failure). Cleanup can be
+    /// re-enabled on individual files and directories with [`NamedTempFile::disable_cleanup`],
+    /// [`TempPath::disable_cleanup`], and [`TempDir::disable_cleanup`].
+    ///
+    /// Explicitly closing the file/folder (e.g., with [`TempDir::close`]) still deletes it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    /// builder.disable_cleanup(true);
+    ///
+    /// let named_tempfile = builder.tempfile()?;
+    /// let tempdir = builder.tempdir()?;
+    /// # let (file_path, dir_path) = (named_tempfile.path().to_owned(), tempdir.path().to_owned());
+    /// # drop((named_tempfile, tempdir));
+    /// # std::fs::remove_file(file_path)?;
+    /// # std::fs::remove_dir(dir_path)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn disable_cleanup(&mut self, disable_cleanup: bool) -> &mut Self {
+        self.keep = disable_cleanup;
+        self
+    }
+
+    /// Delete temporary directories created by this builder on a background thread when they're
+    /// dropped, instead of blocking the dropping thread.
+    ///
+    /// Recursively deleting a large directory tree can take a long time. With this option, the
+    /// [`TempDir`] destructor hands the directory off to a shared background thread and returns
+    /// immediately. Cleanup can be moved to the background for individual directories with
+    /// [`TempDir::background_cleanup`].
+    ///
+    /// Explicitly closing the directory (e.g., with [`TempDir::close`]) still deletes it on the
+    /// calling thread. This option has no effect on temporary files.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Resource Leaking
+    ///
+    /// Directories still queued for deletion when the process exits are left behind unless
+    /// registered with [`TempDir::cleanup_on_exit`]. If the background thread can't be started,
+    /// directories are deleted on the dropping thread instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let tmp_dir = Builder::new()
+    ///     .background_cleanup(true)
+    ///     .tempdir()?;
+    /// // Returns immediately, deleting the directory in the background.
+    /// drop(tmp_dir);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn background_cleanup(&mut self, background_cleanup: bool) -> &mut Self {
+        self.background_cleanup = background_cleanup;
+        self
+    }
+
+    /// Set whether [`TempDir`]s are deleted along with their contents.
+    ///
+    /// With `false`, temporary directories are only deleted if they're empty, as if closed with
+    /// [`TempDir::close_empty`]: if anything is left inside when the [`TempDir`] is dropped (or
+    /// closed), the directory and its contents are left behind and, when closing, `Err` is
+    /// returned listing the leftover entries. This is useful in environments that forbid
+    /// recursively deleting files not created by the program itself.
+    ///
+    /// Default: `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let tmp_dir = Builder::new()
+    ///     .recursive_cleanup(false)
+    ///     .tempdir()?;
+    /// let path = tmp_dir.path().join("leftover.txt");
+    /// std::fs::write(&path, "still here")?;
+    ///
+    /// assert!(tmp_dir.close().is_err());
+    /// assert!(path.exists());
+    /// # std::fs::remove_dir_all(path.parent().unwrap())?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn recursive_cleanup(&mut self, recursive: bool) -> &mut Self {
+        self.recursive_cleanup = recursive;
+        self
+    }
+
+    /// Set the Windows file attributes (`FILE_ATTRIBUTE_*`) temporary files are created with.
+    ///
+    /// By default, files are created with `FILE_ATTRIBUTE_TEMPORARY`, which hints to the system
+    /// that the file is short-lived and should be kept in memory if possible. Use this to, e.g.,
+    /// additionally set `FILE_ATTRIBUTE_NOT_CONTENT_INDEXED` to keep the search indexer away from
+    /// temporary files, or pass `FILE_ATTRIBUTE_NORMAL` for regular files. When a file is
+    /// persisted or kept, its attributes are reset to `FILE_ATTRIBUTE_NORMAL`.
+    ///
+    /// This option only applies to named temporary files and has no effect on directories.
+    ///
+    /// Default: `FILE_ATTRIBUTE_TEMPORARY`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::Builder;
+    ///
+    /// const FILE_ATTRIBUTE_TEMPORARY: u32 = 0x100;
+    /// const FILE_ATTRIBUTE_NOT_CONTENT_INDEXED: u32 = 0x2000;
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .attributes(FILE_ATTRIBUTE_TEMPORARY | FILE_ATTRIBUTE_NOT_CONTENT_INDEXED)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(windows)]
+    pub fn attributes(&mut self, attributes: u32) -> &mut Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Set the security descriptor temporary files and directories are created with on Windows.
+    ///
+    /// By default, temporary files and directories inherit their security descriptor from the
+    /// parent directory. On Windows, [`Builder::permissions`] can't restrict access to other
+    /// users so services that share a temporary directory should use
+    /// [`WindowsSecurity::CurrentUserOnly`] to create temporary files and directories only
+    /// accessible to the current user. The security descriptor is applied atomically on creation.
+    ///
+    /// When a security descriptor is set, files and directories are created directly with the
+    /// Windows APIs, bypassing any custom [`Builder::backend`], and [`Builder::open_options`]
+    /// can't be used.
+    ///
+    /// Default: [`WindowsSecurity::Inherit`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::{Builder, WindowsSecurity};
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .security(WindowsSecurity::CurrentUserOnly)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(windows)]
+    pub fn security(&mut self, security: WindowsSecurity) -> &mut Self {
+        self.security = security;
+        self
+    }
+
+    /// Register a callback invoked with the path of every temporary file or directory created by
+    /// this builder, immediately after it has been created.
+    ///
+    /// This is useful for registering temporary files with external trackers (e.g., a
+    /// crash-recovery manifest) at the exact moment they come into existence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use tempfile::Builder;
+    ///
+    /// fn created(path: &Path) {
+    ///     println!("created {}", path.display());
+    /// }
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .on_create(created)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn on_create(&mut self, on_create: fn(&Path)) -> &mut Self {
+        self.on_create = Some(on_create);
+        self
+    }
+
+    /// Register a callback invoked whenever this crate deletes a temporary file or directory
+    /// created by this builder, either when it's dropped or explicitly closed.
+    ///
+    /// The callback receives the path of the deleted resource along with the result of the
+    /// deletion. It's not invoked when the resource is persisted, kept, or when cleanup has been
+    /// disabled with [`Builder::keep`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io;
+    /// use std::path::Path;
+    /// use tempfile::Builder;
+    ///
+    /// fn cleaned_up(path: &Path, result: io::Result<()>) {
+    ///     if let Err(e) = result {
+    ///         eprintln!("failed to delete {}: {}", path.display(), e);
+    ///     }
+    /// }
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .on_cleanup(cleaned_up)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn on_cleanup(&mut self, on_cleanup: fn(&Path, io::Result<()>)) -> &mut Self {
+        self.on_cleanup = Some(on_cleanup);
+        self
+    }
+
+    /// Set the [`TempBackend`] used to create, persist, and delete the temporary files and
+    /// directories created by this builder.
+    ///
+    /// Default: [`OsBackend`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::{Builder, OsBackend};
+    ///
+    /// static BACKEND: OsBackend = OsBackend;
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .backend(&BACKEND)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn backend(&mut self, backend: &'static dyn TempBackend) -> &mut Self {
+        self.backend = BackendRef::custom(backend);
+        self
+    }
+
+    /// Set the [`NameGenerator`] used to generate the unique part of the names of temporary files
+    /// and directories created by this builder.
+    ///
+    /// Names have the form `{prefix}{unique}{suffix}`; the prefix and suffix still apply.
+    ///
+    /// Default: [`Alphanumeric`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::OsString;
+    /// use std::time::{SystemTime, UNIX_EPOCH};
+    /// use tempfile::{Builder, NameGenerator};
+    ///
+    /// #[derive(Debug)]
+    /// struct Timestamp;
+    ///
+    /// impl NameGenerator for Timestamp {
+    ///     fn generate(&self, _len: usize) -> OsString {
+    ///         let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    ///         // On collision, we'll be called again (at a later time).
+    ///         now.as_nanos().to_string().into()
+    ///     }
+    /// }
+    ///
+    /// static TIMESTAMP: Timestamp = Timestamp;
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .name_generator(&TIMESTAMP)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn name_generator(&mut self, generator: &'static dyn NameGenerator) -> &mut Self {
+        self.name_generator = GeneratorRef(generator, None);
+        self
+    }
+
+    /// Create the named temporary file.
+    ///
+    /// # Security
+    ///
+    /// See [the security][security] docs on `NamedTempFile`.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let tempfile = Builder::new().tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [security]: struct.NamedTempFile.html#security
+    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
+    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
+        self.tempfile_in(env::temp_dir())
+    }
+
+    /// Create the named temporary file in the specified directory.
+    ///
+    /// # Security
+    ///
+    /// See [the security][security] docs on `NamedTempFile`.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let tempfile = Builder::new().tempfile_in("./")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [security]: struct.NamedTempFile.html#security
+    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
+    pub fn tempfile_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<NamedTempFile> {
+        self.check_dir(dir.as_ref())?;
+        util::create_helper_with(
+            dir.as_ref(),
+            &self.name_prefix(),
+            self.suffix,
+            self.random_len,
+            self.generator(),
+            self.retry,
+            |path| file::create_named(path, &mut self.open_options_for_file(), self),
+        )
+        .map(|file| self.created(file))
+    }
+
+    /// Create a named temporary file wrapped in a [`BufWriter`] in
+    /// [`env::temp_dir()`].
+    ///
+    /// See [`Builder::tempfile`] and [`NamedTempFile::new_buffered`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::Builder;
+    ///
+    /// let mut file = Builder::new().suffix(".log").tempfile_buffered()?;
+    /// writeln!(file, "buffered")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn tempfile_buffered(&self) -> io::Result<NamedTempFile<BufWriter<File>>> {
+        self.tempfile_buffered_in(env::temp_dir())
+    }
+
+    /// Create a named temporary file wrapped in a [`BufWriter`] in the
+    /// specified directory.
+    ///
+    /// See [`Builder::tempfile_in`] and [`NamedTempFile::new_buffered`] for details.
+    pub fn tempfile_buffered_in<P: AsRef<Path>>(
+        &self,
+        dir: P,
+    ) -> io::Result<NamedTempFile<BufWriter<File>>> {
+        self.tempfile_in(dir).map(|file| file.map(BufWriter::new))
+    }
+
+    /// Validate `dir` if [`Builder::validate_dir`] is enabled.
+    pub(crate) fn check_dir(&self, dir: &Path) -> io::Result<()> {
+        if self.validate_dir {
+            env::validate_dir(dir)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The permissions to create directories with.
+    ///
+    /// When falling back on the file permissions, the execute bit is added wherever the read bit
+    /// is set on Unix, so that, e.g., `0o600` yields a usable `0o700` directory.
+    pub(crate) fn permissions_for_dir(&self) -> Option<std::fs::Permissions> {
+        if let Some(permissions) = &self.dir_permissions {
+            return Some(permissions.clone());
+        }
+        let permissions = self.permissions.clone()?;
+        #[cfg(unix)]
+        let permissions = {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = permissions.mode();
+            std::fs::Permissions::from_mode(mode | (mode & 0o444) >> 2)
+        };
+        Some(permissions)
+    }
+
+    /// Change the ownership of the newly created file or directory open at `fd` (located at
+    /// `path`) as configured with [`Builder::owner`].
+    #[cfg(unix)]
+    pub(crate) fn apply_owner(
+        &self,
+        fd: std::os::unix::io::BorrowedFd<'_>,
+        path: &Path,
+    ) -> io::Result<()> {
+        if self.owner.is_none() && self.group.is_none() {
+            return Ok(());
+        }
+        // SAFETY: these are only passed to `fchown`, which accepts any ID.
+        let (owner, group) = unsafe {
+            (
+                self.owner.map(|uid| rustix::process::Uid::from_raw(uid)),
+                self.group.map(|gid| rustix::process::Gid::from_raw(gid)),
+            )
+        };
+        rustix::fs::fchown(fd, owner, group)
+            .map_err(io::Error::from)
+            .with_err_path(|| path)
+    }
+
+    /// Set the permissions of the newly created file or directory at `path` if they must be
+    /// applied exactly (see [`Builder::permissions_exact`]).
+    pub(crate) fn apply_exact_permissions(
+        &self,
+        path: &Path,
+        permissions: Option<&std::fs::Permissions>,
+        set: impl FnOnce(std::fs::Permissions) -> io::Result<()>,
+    ) -> io::Result<()> {
+        match permissions {
+            Some(permissions) if cfg!(unix) && self.exact_permissions => {
+                set(permissions.clone()).with_err_path(|| path)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn open_options_for_file(&self) -> OpenOptions {
+        let mut options = OpenOptions::new();
+        options.append(self.append);
+        #[cfg(windows)]
+        std::os::windows::fs::OpenOptionsExt::attributes(&mut options, self.attributes);
+        if let Some(open_options) = self.open_options {
+            open_options(&mut options);
+        }
+        options
+    }
+
+    /// Create an anonymous temporary file, like [`tempfile()`].
+    ///
+    /// If [`Builder::prefer_memory`] is set, the file is created in a memory-backed location when
+    /// one is available. All other options (including the name options) are ignored as the file
+    /// has no name.
+    ///
+    /// # Resource leaking
+    ///
+    /// The temporary file will be automatically removed by the OS when the last handle to it is
+    /// closed.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::Builder;
+    ///
+    /// let mut file = Builder::new().prefer_memory(true).anonymous_tempfile()?;
+    /// writeln!(file, "Brian was here. Briefly.")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn anonymous_tempfile(&self) -> io::Result<std::fs::File> {
+        if self.prefer_memory {
+            if let Some(dir) = env::memory_temp_dir() {
+                // The memory-backed location may not be writable (or may be full).
+                if let Ok(file) = tempfile_in(dir) {
+                    return Ok(file);
+                }
+            }
+        }
+        tempfile()
+    }
+
+    /// Attempts to create a temporary directory (or directory-like resource) using the provided
+    /// closure, like [`Builder::make`].
+    ///
+    /// The closure is passed a path inside of [`env::temp_dir()`] and must create a directory there
+    /// (e.g., with special flags, or a mount point). It's retried with another path if it fails
+    /// with [`std::io::ErrorKind::AlreadyExists`] or [`std::io::ErrorKind::AddrInUse`] (see also
+    /// [`Builder::retry_on`]). Use [`Builder::make_dir_in`] to provide a custom parent directory.
+    ///
+    /// The directory is then managed by the returned [`TempDir`] and is recursively deleted
+    /// (according to the builder's cleanup options) when it's dropped or closed. The value
+    /// returned by the closure is returned alongside it; if it must be released before the
+    /// directory is deleted (e.g., a mount), make sure to drop it first.
+    ///
+    /// [`Builder::permissions`] and [`Builder::subdirs`] are ignored: the closure is responsible
+    /// for creating the directory the way it needs. If the closure fails after creating the
+    /// directory, the directory is deleted (unless it isn't owned by the current user, on Unix).
+    ///
+    /// # Security
+    ///
+    /// As with [`Builder::make`], the closure must atomically fail if the path already exists.
+    /// Otherwise, a [time-of-check to time-of-use bug][TOCTOU] could be introduced.
+    ///
+    /// # Errors
+    ///
+    /// If the closure returns any error besides [`std::io::ErrorKind::AlreadyExists`],
+    /// [`std::io::ErrorKind::AddrInUse`], or one accepted by [`Builder::retry_on`], then `Err` is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::Builder;
+    ///
+    /// let (tmp_dir, ()) = Builder::new().make_dir(|path| {
+    ///     fs::create_dir(path)?;
+    ///     fs::write(path.join("README"), "skeleton")
+    /// })?;
+    /// assert!(tmp_dir.path().join("README").exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [TOCTOU]: https://en.wikipedia.org/wiki/Time-of-check_to_time-of-use
+    pub fn make_dir<F, R>(&self, f: F) -> io::Result<(TempDir, R)>
+    where
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        self.make_dir_in(env::temp_dir(), f)
+    }
+
+    /// This is the same as [`Builder::make_dir`], except `dir` is used as the parent directory.
+    ///
+    /// See [`Builder::make_dir`] for more details and security implications.
+    ///
+    /// # Errors
+    ///
+    /// If the closure returns any error besides [`std::io::ErrorKind::AlreadyExists`],
+    /// [`std::io::ErrorKind::AddrInUse`], or one accepted by [`Builder::retry_on`], then `Err` is
+    /// returned.
+    pub fn make_dir_in<F, R, P>(&self, dir: P, mut f: F) -> io::Result<(TempDir, R)>
+    where
+        F: FnMut(&Path) -> io::Result<R>,
+        P: AsRef<Path>,
+    {
+        let storage;
+        let mut dir = dir.as_ref();
+        if !dir.is_absolute() {
+            let cur_dir = std::env::current_dir()?;
+            storage = cur_dir.join(dir);
+            dir = &storage;
+        }
+
+        self.check_dir(dir)?;
+        util::create_helper_with(
+            dir,
+            &self.name_prefix(),
+            self.suffix,
+            self.random_len,
+            self.generator(),
+            self.retry,
+            move |path| {
+                let existed = fs::symlink_metadata(&path).is_ok();
+                match f(&path) {
+                    Ok(value) => Ok((dir::adopt(path, self), value)),
+                    Err(e) => {
+                        if !existed {
+                            dir::discard(&path, self);
+                        }
+                        Err(e)
+                    }
+                }
+            },
+        )
+        .map(|(dir, value)| (self.created(dir), value))
+    }
+
+    /// Create a [`TempNamedPipe`] with a unique name in the named pipe namespace (`\\.\pipe\`).
+    ///
+    /// The prefix, suffix, number of random characters, name generator, retry options, and
+    /// [security](Builder::security) apply as usual. Options specific to files and directories are
+    /// ignored.
+    ///
+    /// # Errors
+    ///
+    /// If the pipe can not be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let pipe = Builder::new().prefix("my-service-").named_pipe()?;
+    /// assert!(pipe.path().to_str().unwrap().starts_with(r"\\.\pipe\my-service-"));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(windows)]
+    pub fn named_pipe(&self) -> io::Result<TempNamedPipe> {
+        util::create_helper_with(
+            Path::new(pipe::PIPE_NAMESPACE),
+            &self.name_prefix(),
+            self.suffix,
+            self.random_len,
+            self.generator(),
+            self.retry,
+            |path| pipe::create(path, self),
+        )
+    }
+
+    /// Prefer memory-backed locations (e.g., `tmpfs`) when creating anonymous temporary files with
+    /// [`Builder::anonymous_tempfile`].
+    ///
+    /// On Linux, the default temporary directory is used if it's already on a `tmpfs`. Otherwise,
+    /// `/dev/shm` is used if it's a `tmpfs`. If no memory-backed location is available (including
+    /// on other platforms), files are created in the default temporary directory as usual.
+    ///
+    /// Memory-backed files are much faster for small, short-lived files but count against the
+    /// system's memory (and swap).
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let file = Builder::new().prefer_memory(true).anonymous_tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn prefer_memory(&mut self, prefer_memory: bool) -> &mut Self {
+        self.prefer_memory = prefer_memory;
+        self
+    }
+
+    /// Attempts to make a temporary directory inside of [`env::temp_dir()`] whose
+    /// name will have the prefix, `prefix`. The directory and
+    /// everything inside it will be automatically deleted once the
+    /// returned `TempDir` is destroyed.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let tmp_dir = Builder::new().tempdir()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [resource-leaking]: struct.TempDir.html#resource-leaking
+    pub fn tempdir(&self) -> io::Result<TempDir> {
+        self.tempdir_in(env::temp_dir())
+    }
+
+    /// Attempts to make a temporary directory inside of `dir`.
+    /// The directory and everything inside it will be automatically
+    /// deleted once the returned `TempDir` is destroyed.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let tmp_dir = Builder::new().tempdir_in("./")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [resource-leaking]: struct.TempDir.html#resource-leaking
+    pub fn tempdir_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<TempDir> {
+        let storage;
+        let mut dir = dir.as_ref();
+        if !dir.is_absolute() {
+            let cur_dir = std::env::current_dir()?;
+            storage = cur_dir.join(dir);
+            dir = &storage;
+        }
+
+        self.check_dir(dir)?;
+        util::create_helper_with(
+            dir,
+            &self.name_prefix(),
+            self.suffix,
+            self.random_len,
+            self.generator(),
+            self.retry,
+            |path| dir::create(path, self),
+        )
+        .map(|dir| self.created(dir))
+    }
+
+    /// Create `count` named temporary files in [`env::temp_dir()`] in one call.
+    ///
+    /// See [`Builder::tempfiles_in`].
+    ///
+    /// # Errors
+    ///
+    /// If any of the files can not be created, the files created so far are deleted and `Err` is
+    /// returned.
+    pub fn tempfiles(&self, count: usize) -> io::Result<Vec<NamedTempFile>> {
+        self.tempfiles_in(env::temp_dir(), count)
+    }
+
+    /// Create `count` named temporary files in `dir` in one call.
+    ///
+    /// This is equivalent to calling [`Builder::tempfile_in`] `count` times, except that the
+    /// per-call setup (resolving and [validating](Builder::validate_dir) `dir`, computing the name
+    /// prefix, and preparing the open options) is only done once. Each file is still created by
+    /// its full path, one after the other: the parent directory isn't held open, so resolving
+    /// `dir` isn't saved, and the files aren't guaranteed to end up in the same directory if `dir`
+    /// is renamed or replaced concurrently.
+    ///
+    /// # Errors
+    ///
+    /// If any of the files can not be created, the files created so far are deleted and `Err` is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let shards = Builder::new().prefix("shard-").tempfiles_in(&dir, 16)?;
+    /// assert_eq!(shards.len(), 16);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn tempfiles_in<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        count: usize,
+    ) -> io::Result<Vec<NamedTempFile>> {
+        let dir = util::absolute(dir.as_ref())?;
+        self.check_dir(&dir)?;
+        let prefix = self.name_prefix();
+        let generator = self.generator();
+        let mut open_options = self.open_options_for_file();
+        (0..count)
+            .map(|_| {
+                util::create_helper_with(
+                    &dir,
+                    &prefix,
+                    self.suffix,
+                    self.random_len,
+                    generator,
+                    self.retry,
+                    |path| file::create_named(path, &mut open_options, self),
+                )
+                .map(|file| self.created(file))
+            })
+            .collect()
+    }
+
+    /// Create `count` temporary directories in [`env::temp_dir()`] in one call.
+    ///
+    /// See [`Builder::tempdirs_in`].
+    ///
+    /// # Errors
+    ///
+    /// If any of the directories can not be created, the directories created so far are deleted
+    /// and `Err` is returned.
+    pub fn tempdirs(&self, count: usize) -> io::Result<Vec<TempDir>> {
+        self.tempdirs_in(env::temp_dir(), count)
+    }
+
+    /// Create `count` temporary directories in `dir` in one call.
+    ///
+    /// This is equivalent to calling [`Builder::tempdir_in`] `count` times, except that the
+    /// per-call setup (resolving and [validating](Builder::validate_dir) `dir` and computing the
+    /// name prefix) is only done once. Each directory is still created by its full path, one after
+    /// the other: the parent directory isn't held open, so resolving `dir` isn't saved, and the
+    /// directories aren't guaranteed to end up in the same directory if `dir` is renamed or
+    /// replaced concurrently.
+    ///
+    /// # Errors
+    ///
+    /// If any of the directories can not be created, the directories created so far are deleted
+    /// and `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let workers = Builder::new().prefix("worker-").tempdirs_in(&dir, 4)?;
+    /// assert!(workers.iter().all(|worker| worker.path().is_dir()));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn tempdirs_in<P: AsRef<Path>>(&self, dir: P, count: usize) -> io::Result<Vec<TempDir>> {
+        let dir = util::absolute(dir.as_ref())?;
+        self.check_dir(&dir)?;
+        let prefix = self.name_prefix();
+        let generator = self.generator();
+        (0..count)
+            .map(|_| {
+                util::create_helper_with(
+                    &dir,
+                    &prefix,
+                    self.suffix,
+                    self.random_len,
+                    generator,
+                    self.retry,
+                    |path| dir::create(path, self),
+                )
+                .map(|dir| self.created(dir))
+            })
+            .collect()
+    }
+
+    /// Attempts to create a temporary file (or file-like object) using the
+    /// provided closure. The closure is passed a temporary file path and
+    /// returns an [`std::io::Result`]. The path provided to the closure will be
+    /// inside of [`env::temp_dir()`]. Use [`Builder::make_in`] to provide
+    /// a custom temporary directory. If the closure returns one of the
+    /// following errors, then another randomized file path is tried:
+    ///  - [`std::io::ErrorKind::AlreadyExists`]
+    ///  - [`std::io::ErrorKind::AddrInUse`]
+    ///  - any error accepted by [`Builder::retry_on`]
+    ///
+    /// This can be helpful for taking full control over the file creation, but
+    /// leaving the temporary file path construction up to the library. This
+    /// also enables creating a temporary UNIX domain socket, since it is not
+    /// possible to bind to a socket that already exists.
+    ///
+    /// Note that [`Builder::append`] is ignored when using [`Builder::make`], as are
+    /// [`Builder::permissions`] unless the resource is created with
+    /// [`Builder::make_with_permissions`].
+    ///
+    /// # Security
+    ///
+    /// This has the same [security implications][security] as
+    /// [`NamedTempFile`], but with additional caveats. Specifically, it is up
+    /// to the closure to ensure that the file does not exist and that such a
+    /// check is *atomic*. Otherwise, a [time-of-check to time-of-use
+    /// bug][TOCTOU] could be introduced.
+    ///
+    /// For example, the following is **not** secure:
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use tempfile::Builder;
+    ///
+    /// // This is NOT secure!
+    /// let tempfile = Builder::new().make(|path| {
     ///     if path.is_file() {
     ///         return Err(std::io::ErrorKind::AlreadyExists.into());
     ///     }
@@ -684,8 +2006,9 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// # Errors
     ///
     /// If the closure returns any error besides
-    /// [`std::io::ErrorKind::AlreadyExists`] or
-    /// [`std::io::ErrorKind::AddrInUse`], then `Err` is returned.
+    /// [`std::io::ErrorKind::AlreadyExists`],
+    /// [`std::io::ErrorKind::AddrInUse`], or one accepted by
+    /// [`Builder::retry_on`], then `Err` is returned.
     ///
     /// # Examples
     /// ```
@@ -730,17 +2053,271 @@ impl<'a, 'b> Builder<'a, 'b> {
         F: FnMut(&Path) -> io::Result<R>,
         P: AsRef<Path>,
     {
-        util::create_helper(
+        self.make_with_permissions_in(dir, move |path, _| f(path))
+    }
+
+    /// Like [`Builder::make`], but the closure is also passed the permissions configured with
+    /// [`Builder::permissions`], if any.
+    ///
+    /// This lets custom resources (e.g., sockets) honor the builder's permissions. As with the
+    /// files created by [`Builder::tempfile`], `None` means the resource should be private to the
+    /// current user where possible.
+    ///
+    /// See [`Builder::make`] for more details and security implications.
+    ///
+    /// # Errors
+    ///
+    /// If the closure returns any error besides [`std::io::ErrorKind::AlreadyExists`],
+    /// [`std::io::ErrorKind::AddrInUse`], or one accepted by [`Builder::retry_on`], then `Err` is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::fs::{self, Permissions};
+    /// use std::os::unix::fs::PermissionsExt;
+    /// use std::os::unix::net::UnixListener;
+    /// use tempfile::Builder;
+    ///
+    /// let tempsock = Builder::new()
+    ///     .permissions(Permissions::from_mode(0o660))
+    ///     .make_with_permissions(|path, permissions| {
+    ///         let listener = UnixListener::bind(path)?;
+    ///         if let Some(permissions) = permissions {
+    ///             fs::set_permissions(path, permissions.clone())?;
+    ///         }
+    ///         Ok(listener)
+    ///     })?;
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn make_with_permissions<F, R>(&self, f: F) -> io::Result<NamedTempFile<R>>
+    where
+        F: FnMut(&Path, Option<&std::fs::Permissions>) -> io::Result<R>,
+    {
+        self.make_with_permissions_in(env::temp_dir(), f)
+    }
+
+    /// This is the same as [`Builder::make_with_permissions`], except `dir` is used as the base
+    /// directory for the temporary file path.
+    ///
+    /// See [`Builder::make`] for more details and security implications.
+    ///
+    /// # Errors
+    ///
+    /// If the closure returns any error besides [`std::io::ErrorKind::AlreadyExists`],
+    /// [`std::io::ErrorKind::AddrInUse`], or one accepted by [`Builder::retry_on`], then `Err` is
+    /// returned.
+    pub fn make_with_permissions_in<F, R, P>(
+        &self,
+        dir: P,
+        mut f: F,
+    ) -> io::Result<NamedTempFile<R>>
+    where
+        F: FnMut(&Path, Option<&std::fs::Permissions>) -> io::Result<R>,
+        P: AsRef<Path>,
+    {
+        self.check_dir(dir.as_ref())?;
+        util::create_helper_with(
             dir.as_ref(),
-            self.prefix,
+            &self.name_prefix(),
             self.suffix,
             self.random_len,
+            self.generator(),
+            self.retry,
             move |path| {
                 Ok(NamedTempFile::from_parts(
-                    f(&path)?,
-                    TempPath::new(path, self.keep),
+                    f(&path, self.permissions.as_ref())?,
+                    TempPath::new(path, self),
                 ))
             },
         )
+        .map(|file| self.created(file))
+    }
+
+    /// The async equivalent of [`Builder::make`].
+    ///
+    /// The closure is called with a candidate path and returns a future that attempts to create
+    /// the resource at that path. As with [`Builder::make`], the operation is retried with a new
+    /// name if the future fails with [`std::io::ErrorKind::AlreadyExists`],
+    /// [`std::io::ErrorKind::AddrInUse`], or an error accepted by [`Builder::retry_on`].
+    ///
+    /// This function is executor-agnostic, but the temporary path is deleted synchronously when
+    /// the returned [`NamedTempFile`] is dropped.
+    ///
+    /// # Security
+    ///
+    /// This has the same [security implications][security] as [`Builder::make`]: it is up to the
+    /// future to ensure that the resource does not exist and that such a check is *atomic*.
+    ///
+    /// # Errors
+    ///
+    /// If the future resolves to any error besides [`std::io::ErrorKind::AlreadyExists`] or
+    /// [`std::io::ErrorKind::AddrInUse`], then `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::os::unix::net::UnixListener;
+    /// use tempfile::Builder;
+    ///
+    /// # async fn example() -> std::io::Result<()> {
+    /// let tempsock = Builder::new()
+    ///     .make_async(|path| {
+    ///         let path = path.to_owned();
+    ///         async move { UnixListener::bind(path) }
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// # }
+    /// ```
+    ///
+    /// [security]: struct.NamedTempFile.html#security
+    pub async fn make_async<F, Fut, R>(&self, f: F) -> io::Result<NamedTempFile<R>>
+    where
+        F: FnMut(&Path) -> Fut,
+        Fut: Future<Output = io::Result<R>>,
+    {
+        self.make_in_async(env::temp_dir(), f).await
+    }
+
+    /// This is the same as [`Builder::make_async`], except `dir` is used as the base directory
+    /// for the temporary file path.
+    ///
+    /// See [`Builder::make_async`] for more details and security implications.
+    pub async fn make_in_async<F, Fut, R, P>(
+        &self,
+        dir: P,
+        mut f: F,
+    ) -> io::Result<NamedTempFile<R>>
+    where
+        F: FnMut(&Path) -> Fut,
+        Fut: Future<Output = io::Result<R>>,
+        P: AsRef<Path>,
+    {
+        util::create_helper_async(
+            dir.as_ref(),
+            &self.name_prefix(),
+            self.suffix,
+            self.random_len,
+            self.generator(),
+            self.retry,
+            |path| {
+                let resource = f(&path);
+                async move {
+                    Ok(NamedTempFile::from_parts(
+                        resource.await?,
+                        TempPath::new(path, self),
+                    ))
+                }
+            },
+        )
+        .await
+        .map(|file| self.created(file))
+    }
+
+    /// Embed the ID of the creating process in the names of temporary files and directories.
+    ///
+    /// When enabled, names have the form `{prefix}{pid}-{random}{suffix}`. Leftovers from
+    /// processes that exited without cleaning up (e.g., because they crashed) can then be deleted
+    /// with [`Builder::reap_orphans`] by any process using the same prefix, suffix, and number of
+    /// random bytes. This lets multiple instances of a service share a temporary directory.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Security
+    ///
+    /// Names with a PID tag are partially predictable. They're still created securely (with
+    /// retries on collision) but, if this matters, prefer a private temporary directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .prefix("my-service-")
+    ///     .tag_pid(true)
+    ///     .tempfile()?;
+    /// let name = named_tempfile.path().file_name().unwrap().to_str().unwrap();
+    /// assert!(name.starts_with(&format!("my-service-{}-", std::process::id())));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn tag_pid(&mut self, tag_pid: bool) -> &mut Self {
+        self.tag_pid = tag_pid;
+        self
+    }
+
+    /// Delete PID-tagged temporary files and directories in [`env::temp_dir()`] left behind by
+    /// processes that are no longer running.
+    ///
+    /// See [`Builder::reap_orphans_in`] for details.
+    pub fn reap_orphans(&self) -> io::Result<usize> {
+        self.reap_orphans_in(env::temp_dir())
+    }
+
+    /// Delete PID-tagged temporary files and directories in `dir` left behind by processes that
+    /// are no longer running, returning the number of deleted entries.
+    ///
+    /// Only entries whose names match this builder's configuration (i.e., the names that would be
+    /// generated with [`Builder::tag_pid`] enabled) are considered. Entries owned by live
+    /// processes, including the current process, are left alone. On platforms where process
+    /// liveness can't be determined, nothing is deleted.
+    ///
+    /// On Unix, only entries owned by the current user are considered; elsewhere, ownership isn't
+    /// checked.
+    ///
+    /// **NOTE:** PIDs can be reused, so an orphan may survive until the process that reused its
+    /// PID exits. Orphans are never deleted while their creator is still running.
+    ///
+    /// **NOTE:** PIDs are only meaningful within a PID namespace. Don't share `dir` with processes
+    /// running under the same user in other PID namespaces (e.g., in other containers): their
+    /// entries would appear orphaned and be deleted while still in use.
+    ///
+    /// # Errors
+    ///
+    /// If `dir` can't be read or an orphan can't be deleted, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    /// builder.prefix("my-service-").tag_pid(true);
+    ///
+    /// // Clean up after previous instances that crashed.
+    /// let reaped = builder.reap_orphans()?;
+    ///
+    /// let named_tempfile = builder.tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn reap_orphans_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<usize> {
+        pid::reap_orphans(dir.as_ref(), self.prefix, self.suffix, self.random_len)
+    }
+
+    /// The prefix to use when generating names, including the PID tag (if enabled).
+    pub(crate) fn name_prefix(&self) -> Cow<'a, OsStr> {
+        if self.tag_pid {
+            Cow::Owned(pid::tagged_prefix(self.prefix))
+        } else {
+            Cow::Borrowed(self.prefix)
+        }
+    }
+
+    /// Invoke the `on_create` hook (if any) on a newly created resource.
+    pub(crate) fn created<T: AsRef<Path>>(&self, resource: T) -> T {
+        log!(debug, "created {}", resource.as_ref().display());
+        #[cfg(feature = "debug-registry")]
+        registry::register_live(resource.as_ref(), resource.as_ref().is_dir());
+        if let Some(on_create) = self.on_create {
+            on_create(resource.as_ref());
+        }
+        resource
     }
 }
@@ -0,0 +1,193 @@
+use std::fs::File;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+
+use memmap2::MmapMut;
+
+use crate::error::IoResultExt;
+use crate::file::imp;
+use crate::{env, tempfile_in, Builder, TempPath};
+
+/// A temporary file of a fixed size, mapped into memory.
+///
+/// This is useful for scratch buffers that may be larger than the available RAM: the contents are
+/// paged to and from the temporary file by the operating system as needed. The mapping can be
+/// accessed as a byte slice through [`Deref`] and [`DerefMut`].
+///
+/// The file is either anonymous ([`MappedTempFile::new`]), in which case it's deleted by the
+/// operating system when closed, or named ([`MappedTempFile::named`]), in which case it's deleted
+/// when the `MappedTempFile` is dropped and can be [persisted](MappedTempFile::persist).
+///
+/// This type is only available when the `memmap` feature is enabled.
+///
+/// # Security
+///
+/// No access to the underlying file is given out while it's mapped, because resizing or writing
+/// to it would change the mapping behind Rust's back. [Persist](MappedTempFile::persist) the file
+/// to get it back.
+///
+/// Modifying the underlying file by other means (e.g., from another process opening a named
+/// temporary file by path) while it's mapped changes the contents of the mapping behind Rust's
+/// back. Anonymous temporary files are only reachable through the handle held by this type.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::MappedTempFile;
+///
+/// let mut buf = MappedTempFile::new(1024 * 1024)?;
+/// buf[..5].copy_from_slice(b"abcde");
+/// assert_eq!(&buf[..5], b"abcde");
+/// assert_eq!(buf.len(), 1024 * 1024);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct MappedTempFile {
+    // Declared first so the mapping is dropped before the file is deleted.
+    map: MmapMut,
+    file: File,
+    path: Option<TempPath>,
+}
+
+impl MappedTempFile {
+    /// Create an anonymous temporary file of `len` bytes in [`env::temp_dir()`] and map it into
+    /// memory.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or mapped, or the space can't be reserved, `Err` is
+    /// returned.
+    pub fn new(len: u64) -> io::Result<MappedTempFile> {
+        MappedTempFile::new_in(env::temp_dir(), len)
+    }
+
+    /// Create an anonymous temporary file of `len` bytes in `dir` and map it into memory.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or mapped, or the space can't be reserved, `Err` is
+    /// returned.
+    pub fn new_in<P: AsRef<Path>>(dir: P, len: u64) -> io::Result<MappedTempFile> {
+        let file = tempfile_in(dir)?;
+        imp::preallocate(&file, len)?;
+        MappedTempFile::map(file, None)
+    }
+
+    /// Create a named temporary file of `len` bytes in [`env::temp_dir()`] and map it into
+    /// memory.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or mapped, or the space can't be reserved, `Err` is
+    /// returned.
+    pub fn named(len: u64) -> io::Result<MappedTempFile> {
+        MappedTempFile::named_in(env::temp_dir(), len)
+    }
+
+    /// Create a named temporary file of `len` bytes in `dir` and map it into memory.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or mapped, or the space can't be reserved, `Err` is
+    /// returned.
+    pub fn named_in<P: AsRef<Path>>(dir: P, len: u64) -> io::Result<MappedTempFile> {
+        let (file, path) = Builder::new()
+            .preallocate(len)
+            .tempfile_in(dir)?
+            .into_parts();
+        MappedTempFile::map(file, Some(path))
+    }
+
+    fn map(file: File, path: Option<TempPath>) -> io::Result<MappedTempFile> {
+        // SAFETY: the file was just created and, if it's anonymous, can't be opened by anyone
+        // else. Modifying named files behind our back is documented above.
+        let map = unsafe { MmapMut::map_mut(&file) };
+        let map = match &path {
+            Some(path) => map.with_err_path(|| &**path)?,
+            None => map?,
+        };
+        Ok(MappedTempFile { map, file, path })
+    }
+
+    /// Returns the path of the temporary file, or `None` if it's anonymous.
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Synchronously flush modifications of the mapping to the file.
+    ///
+    /// This is only needed to make the contents visible to readers of the file (or durable, for
+    /// named files). Reading the mapping always returns the latest contents.
+    ///
+    /// # Errors
+    ///
+    /// If the modifications can't be flushed, `Err` is returned.
+    pub fn flush(&self) -> io::Result<()> {
+        self.map.flush()
+    }
+
+    /// Flush the mapping, unmap it, and persist the named temporary file at the target path,
+    /// returning the file.
+    ///
+    /// See [`NamedTempFile::persist`](crate::NamedTempFile::persist) for details.
+    ///
+    /// # Errors
+    ///
+    /// If the file is anonymous, or can't be flushed or persisted, `Err` is returned and the
+    /// file is deleted.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::MappedTempFile;
+    ///
+    /// let mut buf = MappedTempFile::named(4)?;
+    /// buf.copy_from_slice(b"abcd");
+    /// buf.persist("./saved_file.bin")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist<P: AsRef<Path>>(self, new_path: P) -> io::Result<File> {
+        let MappedTempFile { map, file, path } = self;
+        let path = match path {
+            Some(path) => path,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "anonymous temporary files can't be persisted",
+                ))
+            }
+        };
+        map.flush().with_err_path(|| &*path)?;
+        drop(map);
+        path.persist(new_path)?;
+        Ok(file)
+    }
+}
+
+impl Deref for MappedTempFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl DerefMut for MappedTempFile {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.map
+    }
+}
+
+impl AsRef<[u8]> for MappedTempFile {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl AsMut<[u8]> for MappedTempFile {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
@@ -0,0 +1,364 @@
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::fmt;
+use std::iter::repeat_with;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+
+#[cfg(not(feature = "fastrand"))]
+use self::sequential::Rng;
+#[cfg(feature = "fastrand")]
+use fastrand::Rng;
+
+thread_local! {
+    static SEEDED_RNG: RefCell<Option<Rng>> = const { RefCell::new(None) };
+}
+
+/// Seed the random number generator used to generate names on the current thread.
+///
+/// After calling this function, the random names generated by the built-in generators
+/// ([`Alphanumeric`] and the [`Charset`]s) on the current thread are reproducible: seeding with
+/// the same value yields the same sequence of names. This is intended for tests, e.g., snapshot
+/// tests that embed temporary paths in their output. Other threads are unaffected.
+///
+/// With `sequential-names` instead of the `fastrand` feature, names are derived from `seed` plus a
+/// per-thread counter instead of the PID, the time, and a process-wide counter.
+///
+/// # Security
+///
+/// Seeded names are predictable. Temporary files and directories are still created securely (an
+/// existing file is never reused), but other users on the system can prevent you from creating
+/// temporary files by creating files with the predicted names first. Don't use this outside of
+/// tests.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::{seed_rng, tempdir, Builder};
+///
+/// let dir = tempdir()?;
+///
+/// seed_rng(42);
+/// let first = Builder::new().tempfile_in(&dir)?.path().to_owned();
+/// seed_rng(42);
+/// let second = Builder::new().tempfile_in(&dir)?.path().to_owned();
+/// assert_eq!(first, second);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn seed_rng(seed: u64) {
+    SEEDED_RNG.with(|rng| *rng.borrow_mut() = Some(Rng::with_seed(seed)));
+}
+
+/// Call `f` with the current thread's seeded random number generator or, if it hasn't been seeded,
+/// a randomly seeded one.
+fn with_rng<T>(f: impl FnOnce(&mut Rng) -> T) -> T {
+    SEEDED_RNG.with(|rng| match &mut *rng.borrow_mut() {
+        Some(rng) => f(rng),
+        None => f(&mut unseeded_rng()),
+    })
+}
+
+/// Returns a randomly seeded generator.
+///
+/// The PID is mixed into the seed: a forked child inherits its parent's generator state and would
+/// otherwise generate the same names as its parent, with both processes colliding on every name.
+#[cfg(feature = "fastrand")]
+fn unseeded_rng() -> Rng {
+    Rng::with_seed(Rng::new().u64(..) ^ u64::from(std::process::id()))
+}
+
+#[cfg(not(feature = "fastrand"))]
+fn unseeded_rng() -> Rng {
+    Rng::new()
+}
+
+/// A deterministic stand-in for `fastrand::Rng`, used when the `fastrand` feature is disabled.
+///
+/// Each name is derived from a per-process base (the PID and the time the first name was
+/// generated) plus a monotonic counter, so consecutive names never collide within a process. The
+/// "random" characters of a name are the digits of `base + counter`, least significant first,
+/// in the requested character set.
+#[cfg(not(feature = "fastrand"))]
+mod sequential {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use once_cell::sync::OnceCell as OnceLock;
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    fn process_base() -> u64 {
+        static START: OnceLock<u64> = OnceLock::new();
+        let micros = *START.get_or_init(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_micros() as u64)
+        });
+        // Look up the PID every time so that forked children don't share their parent's names.
+        micros ^ (u64::from(std::process::id()) << 40)
+    }
+
+    pub(super) struct Rng {
+        /// The counter, for seeded generators.
+        next: u64,
+        /// The remaining digits of the current name.
+        value: u64,
+        seeded: bool,
+    }
+
+    impl Rng {
+        pub(super) fn new() -> Rng {
+            Rng {
+                next: 0,
+                value: process_base().wrapping_add(COUNTER.fetch_add(1, Ordering::Relaxed)),
+                seeded: false,
+            }
+        }
+
+        pub(super) const fn with_seed(seed: u64) -> Rng {
+            Rng {
+                next: seed,
+                value: 0,
+                seeded: true,
+            }
+        }
+
+        /// Start a new name. Seeded generators advance their own counter so that the sequence of
+        /// names is reproducible.
+        pub(super) fn start(&mut self) {
+            if self.seeded {
+                self.value = self.next;
+                self.next = self.next.wrapping_add(1);
+            }
+        }
+
+        pub(super) fn usize(&mut self, range: std::ops::RangeTo<usize>) -> usize {
+            let base = range.end as u64;
+            let digit = self.value % base;
+            self.value /= base;
+            digit as usize
+        }
+
+        pub(super) fn alphanumeric(&mut self) -> char {
+            const CHARS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+            CHARS[self.usize(..CHARS.len())] as char
+        }
+    }
+}
+
+/// A strategy for generating the unique part of temporary file and directory names.
+///
+/// Temporary file and directory names have the form `{prefix}{unique}{suffix}`. By default, the
+/// unique part consists of random alphanumeric characters (see [`Alphanumeric`]). A custom
+/// generator can be configured per-[`Builder`] with [`Builder::name_generator`] to, e.g., use
+/// UUIDs, timestamps, or monotonic counters.
+///
+/// If a file or directory with the generated name already exists, the generator is asked for
+/// another name (up to some limit), so generators should return a different name each time.
+/// Predictable names are safe (temporary files and directories are always created exclusively)
+/// but allow other users on the system to prevent you from creating temporary files by creating
+/// files with the same names first.
+///
+/// # Examples
+///
+/// ```
+/// use std::ffi::OsString;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use tempfile::{Builder, NameGenerator};
+///
+/// #[derive(Debug)]
+/// struct Counter(AtomicUsize);
+///
+/// impl NameGenerator for Counter {
+///     fn generate(&self, _len: usize) -> OsString {
+///         format!("{:06}", self.0.fetch_add(1, Ordering::Relaxed)).into()
+///     }
+/// }
+///
+/// static COUNTER: Counter = Counter(AtomicUsize::new(0));
+///
+/// let dir = tempfile::tempdir()?;
+/// let named_tempfile = Builder::new()
+///     .prefix("job-")
+///     .name_generator(&COUNTER)
+///     .tempfile_in(&dir)?;
+/// assert_eq!(named_tempfile.path().file_name().unwrap(), "job-000000");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// [`Builder`]: crate::Builder
+/// [`Builder::name_generator`]: crate::Builder::name_generator
+pub trait NameGenerator: fmt::Debug + Send + Sync {
+    /// Generate a candidate for the unique part of a name, placed between the prefix and the
+    /// suffix.
+    ///
+    /// `len` is the number of random characters configured with
+    /// [`Builder::rand_bytes`](crate::Builder::rand_bytes), which generators are free to ignore.
+    ///
+    /// The result must not contain path separators or make the name `.` or `..`: creating the
+    /// temporary file or directory fails with [`std::io::ErrorKind::InvalidInput`] otherwise.
+    fn generate(&self, len: usize) -> OsString;
+}
+
+/// The default [`NameGenerator`], generating `len` random alphanumeric characters.
+///
+/// When built with the `sequential-names` feature instead of `fastrand` (enabled by default), this
+/// crate doesn't use a random number generator at all: the characters instead encode the PID and
+/// the time of the first generated name plus a process-wide counter. Such names are predictable,
+/// see the crate-level documentation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Alphanumeric;
+
+impl NameGenerator for Alphanumeric {
+    fn generate(&self, len: usize) -> OsString {
+        let mut buf = String::with_capacity(len);
+        with_rng(|rng| {
+            #[cfg(not(feature = "fastrand"))]
+            rng.start();
+            buf.extend(repeat_with(|| rng.alphanumeric()).take(len));
+        });
+        buf.into()
+    }
+}
+
+/// A character set for randomly generated names (see [`Builder::rand_charset`]).
+///
+/// Each variant is also a [`NameGenerator`] generating `len` random characters from the set.
+///
+/// [`Builder::rand_charset`]: crate::Builder::rand_charset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Charset {
+    /// Mixed-case letters and digits (`[A-Za-z0-9]`), the default.
+    ///
+    /// On case-insensitive filesystems, names effectively only contain 36 distinct characters.
+    Alphanumeric,
+    /// Lowercase letters and digits (`[a-z0-9]`).
+    LowercaseAlphanumeric,
+    /// Lowercase hexadecimal digits (`[0-9a-f]`).
+    Hex,
+}
+
+impl Charset {
+    /// Returns the characters in this set.
+    #[cfg_attr(
+        not(all(feature = "getrandom", any(unix, windows, target_os = "wasi"))),
+        allow(dead_code)
+    )]
+    fn chars(self) -> &'static [u8] {
+        match self {
+            Charset::Alphanumeric => {
+                b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz"
+            }
+            Charset::LowercaseAlphanumeric => LOWERCASE_ALPHANUMERIC,
+            Charset::Hex => &LOWERCASE_ALPHANUMERIC[..16],
+        }
+    }
+
+    /// Returns a `'static` reference to this character set, for use as a [`NameGenerator`].
+    pub(crate) fn as_static(self) -> &'static Charset {
+        match self {
+            Charset::Alphanumeric => &Charset::Alphanumeric,
+            Charset::LowercaseAlphanumeric => &Charset::LowercaseAlphanumeric,
+            Charset::Hex => &Charset::Hex,
+        }
+    }
+}
+
+const LOWERCASE_ALPHANUMERIC: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+impl NameGenerator for Charset {
+    fn generate(&self, len: usize) -> OsString {
+        if *self == Charset::Alphanumeric {
+            return Alphanumeric.generate(len);
+        }
+        let chars = self.chars();
+        let mut buf = String::with_capacity(len);
+        with_rng(|rng| {
+            #[cfg(not(feature = "fastrand"))]
+            rng.start();
+            buf.extend(repeat_with(|| chars[rng.usize(..chars.len())] as char).take(len));
+        });
+        buf.into()
+    }
+}
+
+/// A [`NameGenerator`] drawing characters from the operating system's CSPRNG (see
+/// [`Builder::secure_names`](crate::Builder::secure_names)).
+#[cfg(all(feature = "getrandom", any(unix, windows, target_os = "wasi")))]
+#[derive(Debug)]
+pub(crate) struct Secure(Charset);
+
+#[cfg(all(feature = "getrandom", any(unix, windows, target_os = "wasi")))]
+impl Secure {
+    pub(crate) fn for_charset(charset: Charset) -> &'static Secure {
+        match charset {
+            Charset::Alphanumeric => &Secure(Charset::Alphanumeric),
+            Charset::LowercaseAlphanumeric => &Secure(Charset::LowercaseAlphanumeric),
+            Charset::Hex => &Secure(Charset::Hex),
+        }
+    }
+}
+
+#[cfg(all(feature = "getrandom", any(unix, windows, target_os = "wasi")))]
+impl NameGenerator for Secure {
+    fn generate(&self, len: usize) -> OsString {
+        let chars = self.0.chars();
+        // Reject bytes past the largest multiple of the set's size to avoid bias.
+        let limit = 256 - 256 % chars.len();
+        let mut buf = String::with_capacity(len);
+        let mut bytes = [0u8; 64];
+        while buf.len() < len {
+            if let Err(e) = getrandom::fill(&mut bytes) {
+                // Names only need to be unique, so a predictable one beats failing outright.
+                log!(warn, "failed to generate a secure random name: {}", e);
+                buf.push_str(&self.0.generate(len - buf.len()).to_string_lossy());
+                break;
+            }
+            buf.extend(
+                bytes
+                    .iter()
+                    .filter(|&&b| usize::from(b) < limit)
+                    .map(|&b| chars[usize::from(b) % chars.len()] as char)
+                    .take(len - buf.len()),
+            );
+        }
+        buf.into()
+    }
+}
+
+/// A reference to a name generator, compared by address, along with its character set if it's one
+/// of the built-in generators.
+#[derive(Clone, Copy)]
+pub(crate) struct GeneratorRef(
+    pub(crate) &'static dyn NameGenerator,
+    #[cfg_attr(
+        not(all(feature = "getrandom", any(unix, windows, target_os = "wasi"))),
+        allow(dead_code)
+    )]
+    pub(crate) Option<Charset>,
+);
+
+impl GeneratorRef {
+    pub(crate) const DEFAULT: GeneratorRef =
+        GeneratorRef(&Alphanumeric, Some(Charset::Alphanumeric));
+}
+
+// Generators are shared, immutable (`Sync`) references; don't let them make `Builder`
+// non-unwind-safe.
+impl UnwindSafe for GeneratorRef {}
+impl RefUnwindSafe for GeneratorRef {}
+
+impl fmt::Debug for GeneratorRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl PartialEq for GeneratorRef {
+    fn eq(&self, other: &Self) -> bool {
+        // See `BackendRef`.
+        std::ptr::eq(self.0, other.0) && self.1 == other.1
+    }
+}
+
+impl Eq for GeneratorRef {}
@@ -0,0 +1,127 @@
+//! Support for PID-tagged temporary file names (see [`crate::Builder::tag_pid`]).
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::error::IoResultExt;
+
+/// Returns `prefix` followed by the current process's PID tag.
+pub(crate) fn tagged_prefix(prefix: &OsStr) -> OsString {
+    let mut tagged = prefix.to_owned();
+    tagged.push(format!("{}-", std::process::id()));
+    tagged
+}
+
+/// Parse the PID out of a PID-tagged file name, returning `None` if `name` doesn't match.
+fn parse_pid(name: &OsStr, prefix: &OsStr, suffix: &OsStr, random_len: usize) -> Option<u32> {
+    let name = name.to_str()?;
+    let rest = name
+        .strip_prefix(prefix.to_str()?)?
+        .strip_suffix(suffix.to_str()?)?;
+    let (pid, random) = rest.split_once('-')?;
+    if pid.is_empty()
+        || !pid.bytes().all(|b| b.is_ascii_digit())
+        || random.len() != random_len
+        || !random.bytes().all(|b| b.is_ascii_alphanumeric())
+    {
+        return None;
+    }
+    pid.parse().ok()
+}
+
+/// Delete all PID-tagged temporary files and directories in `dir` whose owning process is no longer
+/// alive, returning the number of deleted entries.
+pub(crate) fn reap_orphans(
+    dir: &Path,
+    prefix: &OsStr,
+    suffix: &OsStr,
+    random_len: usize,
+) -> io::Result<usize> {
+    let mut reaped = 0;
+    for entry in fs::read_dir(dir).with_err_path(|| dir)? {
+        let entry = entry.with_err_path(|| dir)?;
+        let pid = match parse_pid(&entry.file_name(), prefix, suffix, random_len) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        if is_alive(pid) || !is_ours(&entry) {
+            continue;
+        }
+        let path = entry.path();
+        let result = match entry.file_type() {
+            Ok(ty) if ty.is_dir() => fs::remove_dir_all(&path),
+            Ok(_) => fs::remove_file(&path),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => reaped += 1,
+            // Another process may have reaped it first.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_err_path(|| path),
+        }
+    }
+    Ok(reaped)
+}
+
+/// Returns `true` if `entry` belongs to the current user, who may therefore have created it.
+#[cfg(unix)]
+fn is_ours(entry: &fs::DirEntry) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    entry.metadata().map_or(false, |metadata| {
+        metadata.uid() == rustix::process::geteuid().as_raw()
+    })
+}
+
+/// Returns `true` if `entry` belongs to the current user, who may therefore have created it.
+#[cfg(not(unix))]
+fn is_ours(_entry: &fs::DirEntry) -> bool {
+    // Ownership isn't checked on other platforms.
+    true
+}
+
+/// Returns `false` if the process with the given PID definitely isn't running.
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    use rustix::io::Errno;
+    use rustix::process::{test_kill_process, Pid};
+
+    let pid = match i32::try_from(pid).ok().and_then(Pid::from_raw) {
+        Some(pid) => pid,
+        None => return true,
+    };
+    // EPERM means the process exists but belongs to someone else.
+    !matches!(test_kill_process(pid), Err(Errno::SRCH))
+}
+
+/// Returns `false` if the process with the given PID definitely isn't running.
+#[cfg(windows)]
+fn is_alive(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{
+        CloseHandle, GetLastError, ERROR_INVALID_PARAMETER, HANDLE, STILL_ACTIVE,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle == 0 as HANDLE {
+            // Any other error (e.g., access denied) means the process exists.
+            return GetLastError() != ERROR_INVALID_PARAMETER;
+        }
+        let mut code = 0;
+        let ok = GetExitCodeProcess(handle, &mut code);
+        CloseHandle(handle);
+        ok == 0 || code == STILL_ACTIVE as u32
+    }
+}
+
+/// Returns `false` if the process with the given PID definitely isn't running.
+#[cfg(not(any(unix, windows)))]
+fn is_alive(_pid: u32) -> bool {
+    // We can't tell, so never reap anything.
+    true
+}
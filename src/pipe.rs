@@ -0,0 +1,128 @@
+//! Temporary named pipes (Windows only).
+
+use std::os::windows::io::{
+    AsHandle, AsRawHandle, BorrowedHandle, FromRawHandle, OwnedHandle, RawHandle,
+};
+use std::path::{Path, PathBuf};
+use std::{io, ptr};
+
+use windows_sys::Win32::Foundation::{ERROR_ACCESS_DENIED, INVALID_HANDLE_VALUE};
+use windows_sys::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX};
+use windows_sys::Win32::System::Pipes::{
+    CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+    PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+
+use crate::error::IoResultExt;
+use crate::security::{self, SecurityDescriptor};
+use crate::Builder;
+
+/// The namespace all named pipes live in.
+pub(crate) const PIPE_NAMESPACE: &str = r"\\.\pipe\";
+
+const BUFFER_SIZE: u32 = 4096;
+
+/// A uniquely named pipe that exists as long as its server handle is open.
+///
+/// Named pipes live in their own namespace (`\\.\pipe\`) instead of the filesystem, so there's
+/// nothing to delete: the pipe disappears once the server handle (owned by this guard) and all
+/// client handles are closed. Clients connect by opening [`TempNamedPipe::path`] like a file.
+///
+/// The pipe is a duplex byte-stream pipe that rejects remote clients. Its name is generated from
+/// the [`Builder`]'s prefix, suffix, and name generator, and its security descriptor is taken from
+/// [`Builder::security`].
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::{File, OpenOptions};
+/// use std::io::{Read, Write};
+/// use tempfile::TempNamedPipe;
+///
+/// let pipe = TempNamedPipe::new()?;
+/// let mut client = OpenOptions::new().read(true).write(true).open(pipe.path())?;
+/// client.write_all(b"ping")?;
+///
+/// let mut server = File::from(pipe.into_handle());
+/// let mut buf = [0; 4];
+/// server.read_exact(&mut buf)?;
+/// assert_eq!(&buf, b"ping");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct TempNamedPipe {
+    path: PathBuf,
+    handle: OwnedHandle,
+}
+
+impl TempNamedPipe {
+    /// Create a new temporary named pipe with the default [`Builder`] options.
+    ///
+    /// # Errors
+    ///
+    /// If the pipe can not be created, `Err` is returned.
+    pub fn new() -> io::Result<TempNamedPipe> {
+        Builder::new().named_pipe()
+    }
+
+    /// Returns the name of the pipe (`\\.\pipe\...`), for clients to open.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Convert the `TempNamedPipe` into the pipe's server handle.
+    ///
+    /// The pipe still exists until the handle is closed.
+    #[must_use]
+    pub fn into_handle(self) -> OwnedHandle {
+        self.handle
+    }
+}
+
+impl AsHandle for TempNamedPipe {
+    fn as_handle(&self) -> BorrowedHandle<'_> {
+        self.handle.as_handle()
+    }
+}
+
+impl AsRawHandle for TempNamedPipe {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.handle.as_raw_handle()
+    }
+}
+
+/// Create the server end of a new named pipe at `path`, failing if it already exists.
+pub(crate) fn create(path: PathBuf, builder: &Builder<'_, '_>) -> io::Result<TempNamedPipe> {
+    let descriptor = SecurityDescriptor::new(&builder.security)?;
+    let attributes = descriptor.as_ref().map(SecurityDescriptor::attributes);
+    let name = security::to_utf16(path.as_os_str());
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            attributes
+                .as_ref()
+                .map_or(ptr::null(), |attributes| attributes),
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        let err = io::Error::last_os_error();
+        // With `FILE_FLAG_FIRST_PIPE_INSTANCE`, this is how an existing pipe is reported.
+        let err = if err.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) {
+            io::Error::new(io::ErrorKind::AlreadyExists, err)
+        } else {
+            err
+        };
+        return Err(err).with_err_path(|| path);
+    }
+    Ok(TempNamedPipe {
+        path,
+        handle: unsafe { OwnedHandle::from_raw_handle(handle as RawHandle) },
+    })
+}
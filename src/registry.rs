@@ -0,0 +1,271 @@
+//! A process-global registry of temporary paths to delete if the process is about to die without
+//! running destructors, to keep alive, or (with the `debug-registry` feature) to list.
+//!
+//! Temporary files and directories are only ever added to the registry on request. They are
+//! removed from the registry when they're deleted, persisted, or kept.
+
+// Registration is only supported where we can hook process exit.
+#![cfg_attr(not(any(unix, windows)), allow(dead_code))]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+struct Entry {
+    path: PathBuf,
+    is_dir: bool,
+    // Delete this entry in `cleanup(Trigger::Signal)`.
+    on_signal: bool,
+    // Delete this entry in `cleanup(Trigger::Exit)`.
+    on_exit: bool,
+    // Refresh this entry's timestamps periodically (see `keepalive`).
+    #[cfg_attr(not(unix), allow(dead_code))]
+    keepalive: bool,
+    // List this entry in `live`.
+    #[cfg_attr(not(feature = "debug-registry"), allow(dead_code))]
+    live: bool,
+}
+
+/// The event that deletes registered paths.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Trigger {
+    /// The process was interrupted by `SIGINT` or `SIGTERM`.
+    #[cfg_attr(not(all(feature = "signal-cleanup", unix)), allow(dead_code))]
+    Signal,
+    /// The process is exiting.
+    Exit,
+}
+
+impl Entry {
+    fn deleted_on(&self, trigger: Trigger) -> bool {
+        match trigger {
+            Trigger::Signal => self.on_signal,
+            Trigger::Exit => self.on_exit,
+        }
+    }
+}
+
+static REGISTRY: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+// Lets us skip taking the lock when dropping temporary files if nothing has ever been registered.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+fn lock() -> std::sync::MutexGuard<'static, Vec<Entry>> {
+    // The registry is always left in a consistent state, so poisoning can be ignored.
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Register `path` for deletion by [`cleanup`] on `trigger`.
+pub(crate) fn register(path: &Path, is_dir: bool, trigger: Trigger) {
+    update(path, is_dir, |entry| match trigger {
+        Trigger::Signal => entry.on_signal = true,
+        Trigger::Exit => entry.on_exit = true,
+    });
+}
+
+fn update(path: &Path, is_dir: bool, f: impl FnOnce(&mut Entry)) {
+    let mut registry = lock();
+    ACTIVE.store(true, Ordering::Relaxed);
+    match registry.iter_mut().find(|e| e.path == path) {
+        Some(entry) => f(entry),
+        None => {
+            let mut entry = Entry {
+                path: path.to_owned(),
+                is_dir,
+                on_signal: false,
+                on_exit: false,
+                keepalive: false,
+                live: false,
+            };
+            f(&mut entry);
+            registry.push(entry);
+        }
+    }
+}
+
+/// Remove `path` from the registry, if present.
+pub(crate) fn unregister(path: &Path) {
+    if ACTIVE.load(Ordering::Relaxed) {
+        lock().retain(|e| e.path != path);
+    }
+}
+
+/// Register a newly created `path` to be listed by [`live`].
+#[cfg(feature = "debug-registry")]
+pub(crate) fn register_live(path: &Path, is_dir: bool) {
+    update(path, is_dir, |entry| entry.live = true);
+}
+
+/// Returns the paths registered with [`register_live`] that haven't been unregistered yet, and
+/// whether they're directories.
+#[cfg(feature = "debug-registry")]
+pub(crate) fn live() -> Vec<(PathBuf, bool)> {
+    lock()
+        .iter()
+        .filter(|e| e.live)
+        .map(|e| (e.path.clone(), e.is_dir))
+        .collect()
+}
+
+/// Delete all paths registered for `trigger`, ignoring errors.
+pub(crate) fn cleanup(trigger: Trigger) {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    // Don't block forever if the process is dying while another thread holds the lock.
+    let mut registry = match REGISTRY.try_lock() {
+        Ok(registry) => registry,
+        Err(std::sync::TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => return,
+    };
+    let (entries, rest) = std::mem::take(&mut *registry)
+        .into_iter()
+        .partition(|e| e.deleted_on(trigger));
+    *registry = rest;
+    drop(registry);
+    for entry in entries {
+        let _ = if entry.is_dir {
+            fs::remove_dir_all(&entry.path)
+        } else {
+            fs::remove_file(&entry.path)
+        };
+    }
+}
+
+#[cfg(any(unix, windows))]
+pub(crate) mod exit {
+    use std::io;
+    use std::os::raw::c_int;
+    use std::panic;
+
+    use once_cell::sync::OnceCell as OnceLock;
+
+    extern "C" {
+        fn atexit(cb: extern "C" fn()) -> c_int;
+    }
+
+    extern "C" fn run_cleanup() {
+        // Never unwind into C.
+        let _ = panic::catch_unwind(|| super::cleanup(super::Trigger::Exit));
+    }
+
+    static INSTALLED: OnceLock<()> = OnceLock::new();
+
+    /// Install the exit hook (once).
+    pub(crate) fn install() -> io::Result<()> {
+        INSTALLED
+            .get_or_try_init(|| {
+                // SAFETY: `run_cleanup` is a valid `extern "C"` function that never unwinds.
+                if unsafe { atexit(run_cleanup) } != 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "failed to register exit handler",
+                    ));
+                }
+                Ok(())
+            })
+            .map(|_| ())
+    }
+}
+
+#[cfg(all(feature = "signal-cleanup", unix))]
+pub(crate) mod signal {
+    use std::io;
+    use std::thread;
+
+    use once_cell::sync::OnceCell as OnceLock;
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+    use signal_hook::low_level::emulate_default_handler;
+
+    static INSTALLED: OnceLock<()> = OnceLock::new();
+
+    /// Install the SIGINT/SIGTERM handler (once).
+    pub(crate) fn install() -> io::Result<()> {
+        INSTALLED
+            .get_or_try_init(|| {
+                let mut signals = Signals::new([SIGINT, SIGTERM])?;
+                thread::Builder::new()
+                    .name("tempfile-signal-cleanup".into())
+                    .spawn(move || {
+                        for signal in signals.forever() {
+                            super::cleanup(super::Trigger::Signal);
+                            let _ = emulate_default_handler(signal);
+                        }
+                    })?;
+                Ok(())
+            })
+            .map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+pub(crate) mod keepalive {
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::Duration;
+
+    use once_cell::sync::OnceCell as OnceLock;
+    use rustix::fs::{utimensat, AtFlags, Timespec, Timestamps, CWD, UTIME_NOW};
+
+    /// How often the timestamps of registered entries are refreshed.
+    ///
+    /// Temporary file cleaners usually only delete entries that haven't been touched for days.
+    const INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+    static INSTALLED: OnceLock<()> = OnceLock::new();
+
+    /// Refresh the timestamps of `path` now and then periodically, until it's unregistered.
+    pub(crate) fn register(path: &Path, is_dir: bool) -> io::Result<()> {
+        INSTALLED.get_or_try_init(|| {
+            thread::Builder::new()
+                .name("tempfile-keepalive".into())
+                .spawn(|| loop {
+                    thread::sleep(INTERVAL);
+                    refresh_all();
+                })
+                .map(|_| ())
+        })?;
+        super::update(path, is_dir, |entry| entry.keepalive = true);
+        touch(path, is_dir);
+        Ok(())
+    }
+
+    fn refresh_all() {
+        // Don't hold the lock while touching the filesystem.
+        let entries: Vec<(PathBuf, bool)> = super::lock()
+            .iter()
+            .filter(|e| e.keepalive)
+            .map(|e| (e.path.clone(), e.is_dir))
+            .collect();
+        for (path, is_dir) in entries {
+            touch(&path, is_dir);
+        }
+    }
+
+    /// Set the access and modification times of `path` (and everything inside it, if it's a
+    /// directory) to now, ignoring errors. Symbolic links aren't followed.
+    fn touch(path: &Path, is_dir: bool) {
+        let now = Timespec {
+            tv_sec: 0,
+            tv_nsec: UTIME_NOW,
+        };
+        let times = Timestamps {
+            last_access: now,
+            last_modification: now,
+        };
+        if is_dir {
+            if let Ok(entries) = fs::read_dir(path) {
+                for entry in entries.flatten() {
+                    let is_dir = entry.file_type().map_or(false, |ty| ty.is_dir());
+                    touch(&entry.path(), is_dir);
+                }
+            }
+        }
+        // Touch directories after their contents as listing them may update their access times.
+        let _ = utimensat(CWD, path, &times, AtFlags::SYMLINK_NOFOLLOW);
+    }
+}
@@ -0,0 +1,180 @@
+//! Windows security descriptors for temporary files and directories (see
+//! [`crate::Builder::security`]).
+
+use std::ffi::{c_void, OsStr, OsString};
+use std::fs::File;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::os::windows::io::{FromRawHandle, RawHandle};
+use std::path::Path;
+use std::{io, iter, mem, ptr, slice};
+
+use windows_sys::Win32::Foundation::{CloseHandle, LocalFree, HANDLE, INVALID_HANDLE_VALUE};
+use windows_sys::Win32::Security::Authorization::{
+    ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+};
+use windows_sys::Win32::Security::{
+    GetTokenInformation, TokenUser, PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES, TOKEN_QUERY,
+    TOKEN_USER,
+};
+use windows_sys::Win32::Storage::FileSystem::{
+    CreateDirectoryW, CreateFileW, CREATE_NEW, FILE_GENERIC_READ, FILE_GENERIC_WRITE,
+    FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, FILE_WRITE_DATA,
+};
+use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+use crate::file::imp::to_wide_path;
+
+/// The security applied to temporary files and directories on Windows.
+///
+/// See [`Builder::security`](crate::Builder::security).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum WindowsSecurity {
+    /// Inherit the security descriptor from the parent directory (the default).
+    #[default]
+    Inherit,
+    /// Only grant access to the current user, ignoring inheritable entries from the parent
+    /// directory.
+    CurrentUserOnly,
+    /// Use the security descriptor described by the given [SDDL] string.
+    ///
+    /// [SDDL]: https://learn.microsoft.com/en-us/windows/win32/secauthz/security-descriptor-string-format
+    Sddl(String),
+}
+
+/// An owned security descriptor, allocated by the system.
+pub(crate) struct SecurityDescriptor(PSECURITY_DESCRIPTOR);
+
+impl SecurityDescriptor {
+    /// Build the security descriptor for `security`, or `None` to inherit the parent's.
+    pub(crate) fn new(security: &WindowsSecurity) -> io::Result<Option<SecurityDescriptor>> {
+        let sddl = match security {
+            WindowsSecurity::Inherit => return Ok(None),
+            WindowsSecurity::CurrentUserOnly => {
+                let mut sddl = OsString::from("D:P(A;OICI;FA;;;");
+                sddl.push(current_user_sid()?);
+                sddl.push(")");
+                sddl
+            }
+            WindowsSecurity::Sddl(sddl) => sddl.into(),
+        };
+        let sddl = to_utf16(&sddl);
+        let mut descriptor = ptr::null_mut();
+        unsafe {
+            if ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                ptr::null_mut(),
+            ) == 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(Some(SecurityDescriptor(descriptor)))
+    }
+
+    pub(crate) fn attributes(&self) -> SECURITY_ATTRIBUTES {
+        SECURITY_ATTRIBUTES {
+            nLength: mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: self.0,
+            bInheritHandle: 0,
+        }
+    }
+}
+
+impl Drop for SecurityDescriptor {
+    fn drop(&mut self) {
+        unsafe {
+            LocalFree(self.0 as _);
+        }
+    }
+}
+
+/// Create a new file at `path` with the given security descriptor, failing if it already exists.
+pub(crate) fn create_file(
+    path: &Path,
+    append: bool,
+    attributes: u32,
+    descriptor: &SecurityDescriptor,
+) -> io::Result<File> {
+    let path_w = to_wide_path(path)?;
+    let access = if append {
+        FILE_GENERIC_READ | (FILE_GENERIC_WRITE & !FILE_WRITE_DATA)
+    } else {
+        FILE_GENERIC_READ | FILE_GENERIC_WRITE
+    };
+    let security_attributes = descriptor.attributes();
+    unsafe {
+        let handle = CreateFileW(
+            path_w.as_ptr(),
+            access,
+            FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE,
+            &security_attributes,
+            CREATE_NEW,
+            attributes,
+            0 as HANDLE,
+        );
+        if handle == INVALID_HANDLE_VALUE {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(File::from_raw_handle(handle as RawHandle))
+        }
+    }
+}
+
+/// Create a new directory at `path` with the given security descriptor.
+pub(crate) fn create_dir(path: &Path, descriptor: &SecurityDescriptor) -> io::Result<()> {
+    let path_w = to_wide_path(path)?;
+    let security_attributes = descriptor.attributes();
+    unsafe {
+        if CreateDirectoryW(path_w.as_ptr(), &security_attributes) == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Returns the string form (`S-1-...`) of the current user's SID.
+fn current_user_sid() -> io::Result<OsString> {
+    unsafe {
+        let mut token = 0 as HANDLE;
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let result = token_user_sid(token);
+        CloseHandle(token);
+        result
+    }
+}
+
+unsafe fn token_user_sid(token: HANDLE) -> io::Result<OsString> {
+    let mut len = 0;
+    GetTokenInformation(token, TokenUser, ptr::null_mut(), 0, &mut len);
+    // Over-align the buffer for `TOKEN_USER`.
+    let mut buf = vec![0u64; (len as usize + 7) / 8];
+    if GetTokenInformation(
+        token,
+        TokenUser,
+        buf.as_mut_ptr() as *mut c_void,
+        len,
+        &mut len,
+    ) == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let user = &*(buf.as_ptr() as *const TOKEN_USER);
+
+    let mut sid = ptr::null_mut();
+    if ConvertSidToStringSidW(user.User.Sid, &mut sid) == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let len = (0..).take_while(|&i| *sid.add(i) != 0).count();
+    let result = OsString::from_wide(slice::from_raw_parts(sid, len));
+    LocalFree(sid as _);
+    Ok(result)
+}
+
+pub(crate) fn to_utf16(s: &OsStr) -> Vec<u16> {
+    s.encode_wide().chain(iter::once(0)).collect()
+}
@@ -0,0 +1,174 @@
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{dir, env, file, util, Builder, NamedTempFile, TempDir};
+
+/// A sequence of temporary files and directories sharing a random session name.
+///
+/// Every resource created through a `TempSequence` is named
+/// `{prefix}{session}-{counter}{suffix}` where `session` is a random string chosen once when the
+/// sequence is created and `counter` is a zero-padded, monotonically increasing number. This keeps
+/// all outputs of a single run grouped together and sorted by creation order on disk.
+///
+/// If a name is already taken, the counter is simply advanced until an unused name is found.
+///
+/// Construct a sequence with [`TempSequence::new`], or with [`Builder::sequence`] to customize the
+/// prefix, suffix, session length ([`Builder::rand_bytes`]), permissions, etc.
+///
+/// # Resource Leaking
+///
+/// The resources created by a `TempSequence` are regular [`NamedTempFile`]s and [`TempDir`]s and
+/// are cleaned up when dropped. Dropping the sequence itself doesn't delete anything.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::TempSequence;
+///
+/// let seq = TempSequence::new();
+/// let first = seq.tempfile()?;
+/// let second = seq.tempfile()?;
+/// assert!(first.path() < second.path());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct TempSequence<'a, 'b> {
+    builder: Builder<'a, 'b>,
+    dir: PathBuf,
+    session: OsString,
+    counter: AtomicUsize,
+}
+
+impl TempSequence<'static, 'static> {
+    /// Create a new sequence of temporary files in [`env::temp_dir()`].
+    ///
+    /// See [`Builder::sequence`] for more configuration.
+    #[must_use]
+    pub fn new() -> Self {
+        Builder::new().sequence()
+    }
+
+    /// Create a new sequence of temporary files in the specified directory.
+    ///
+    /// See [`Builder::sequence_in`] for more configuration.
+    #[must_use]
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> Self {
+        Builder::new().sequence_in(dir)
+    }
+}
+
+impl Default for TempSequence<'static, 'static> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, 'b> TempSequence<'a, 'b> {
+    pub(crate) fn from_builder(builder: &Builder<'a, 'b>, dir: &Path) -> Self {
+        TempSequence {
+            builder: builder.clone(),
+            dir: dir.to_owned(),
+            session: builder.generator().generate(builder.random_len),
+            counter: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the random session name shared by all resources in this sequence.
+    #[must_use]
+    pub fn session(&self) -> &OsStr {
+        &self.session
+    }
+
+    /// Create the next named temporary file in the sequence.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created, `Err` is returned.
+    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
+        self.create_in(&self.dir, |path| {
+            file::create_named(
+                path,
+                &mut self.builder.open_options_for_file(),
+                &self.builder,
+            )
+        })
+    }
+
+    /// Create the next temporary directory in the sequence.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created, `Err` is returned.
+    pub fn tempdir(&self) -> io::Result<TempDir> {
+        let storage;
+        let mut base = self.dir.as_path();
+        if !base.is_absolute() {
+            storage = std::env::current_dir()?.join(base);
+            base = &storage;
+        }
+        self.create_in(base, |path| dir::create(path, &self.builder))
+    }
+
+    fn create_in<R: AsRef<Path>>(
+        &self,
+        base: &Path,
+        mut f: impl FnMut(PathBuf) -> io::Result<R>,
+    ) -> io::Result<R> {
+        self.builder.check_dir(base)?;
+        let attempts = self.builder.retry.attempts(crate::NUM_RETRIES);
+        let mut last_attempt = PathBuf::new();
+        for i in 0..attempts {
+            self.builder.retry.wait(i);
+            let n = self.counter.fetch_add(1, Ordering::Relaxed);
+            let mut name = self.builder.name_prefix().into_owned();
+            name.push(&self.session);
+            name.push(format!("-{:08}", n));
+            name.push(self.builder.suffix);
+            last_attempt = base.join(name);
+            return match f(last_attempt.clone()) {
+                Err(ref e) if self.builder.retry.should_retry(e, i, attempts) => continue,
+                res => res
+                    .map(|resource| self.builder.created(resource))
+                    .map_err(|e| util::creation_error(e, base, &last_attempt)),
+            };
+        }
+
+        Err(util::too_many_retries(base, attempts, last_attempt))
+    }
+}
+
+impl<'a, 'b> Builder<'a, 'b> {
+    /// Create a [`TempSequence`] of temporary files and directories inside of
+    /// [`env::temp_dir()`].
+    ///
+    /// The sequence uses this builder's prefix and suffix, and a random session name of
+    /// [`Builder::rand_bytes`] characters. All other options (permissions, append, keep, hooks)
+    /// apply to every resource created by the sequence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let seq = Builder::new().prefix("shard-").suffix(".dat").sequence();
+    /// let shard = seq.tempfile()?;
+    /// let name = shard.path().file_name().unwrap().to_str().unwrap();
+    /// assert!(name.starts_with("shard-"));
+    /// assert!(name.ends_with("-00000000.dat"));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn sequence(&self) -> TempSequence<'a, 'b> {
+        self.sequence_in(env::temp_dir())
+    }
+
+    /// Create a [`TempSequence`] of temporary files and directories inside of `dir`.
+    ///
+    /// See [`Builder::sequence`] for details.
+    #[must_use]
+    pub fn sequence_in<P: AsRef<Path>>(&self, dir: P) -> TempSequence<'a, 'b> {
+        TempSequence::from_builder(self, dir.as_ref())
+    }
+}
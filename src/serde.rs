@@ -0,0 +1,32 @@
+//! [serde](https://serde.rs) support for temporary paths.
+//!
+//! This module is only available when the `serde` feature is enabled.
+
+use std::path::PathBuf;
+
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{NamedTempFile, TempPath};
+
+/// Serializes the temporary file's path.
+impl Serialize for TempPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+/// Adopts an existing path as a temporary path (see [`TempPath::from_path`]).
+///
+/// **NOTE:** The file at the deserialized path will be deleted when the `TempPath` is dropped.
+impl<'de> Deserialize<'de> for TempPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        PathBuf::deserialize(deserializer).map(TempPath::from_path)
+    }
+}
+
+/// Serializes the temporary file's path.
+impl<F> Serialize for NamedTempFile<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.path().serialize(serializer)
+    }
+}
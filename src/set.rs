@@ -0,0 +1,277 @@
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{env, file, util, Builder, NamedTempFile};
+
+/// A group of named temporary files sharing one random name, differing only by suffix.
+///
+/// Every file in the set is named `{prefix}{random}{suffix}{file suffix}`, where the prefix,
+/// random part, and [`Builder::suffix`] are shared by all files. This is useful for multi-file
+/// artifacts that must live and die together, e.g., a database and its write-ahead log
+/// (`data.tmpX.db` and `data.tmpX.db-wal`).
+///
+/// The files are created together: if any of them can't be created, the others are deleted
+/// and another random name is tried (for name collisions) or an error is returned. They're
+/// deleted together when the set is dropped or [closed](TempFileSet::close), and can be
+/// [persisted](TempFileSet::persist) together.
+///
+/// Construct a set with [`TempFileSet::new`], or with [`Builder::tempfile_set`] to customize the
+/// prefix, shared suffix, permissions, etc.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::Builder;
+///
+/// let set = Builder::new()
+///     .prefix("data.")
+///     .suffix(".db")
+///     .tempfile_set(["", "-wal"])?;
+/// let db = set.get("").unwrap();
+/// let wal = set.get("-wal").unwrap();
+/// assert_eq!(
+///     wal.path().to_str().unwrap(),
+///     format!("{}-wal", db.path().to_str().unwrap()),
+/// );
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct TempFileSet {
+    base: PathBuf,
+    files: Vec<(OsString, NamedTempFile)>,
+}
+
+impl TempFileSet {
+    /// Create a set of named temporary files with the given suffixes in [`env::temp_dir()`].
+    ///
+    /// See [`Builder::tempfile_set`] for more configuration.
+    ///
+    /// # Errors
+    ///
+    /// If any of the files can not be created, `Err` is returned.
+    pub fn new<I, S>(suffixes: I) -> io::Result<TempFileSet>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        Builder::new().tempfile_set(suffixes)
+    }
+
+    /// Create a set of named temporary files with the given suffixes in the specified directory.
+    ///
+    /// See [`Builder::tempfile_set_in`] for more configuration.
+    ///
+    /// # Errors
+    ///
+    /// If any of the files can not be created, `Err` is returned.
+    pub fn new_in<P, I, S>(dir: P, suffixes: I) -> io::Result<TempFileSet>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        Builder::new().tempfile_set_in(dir, suffixes)
+    }
+
+    /// Returns the path shared by all files in the set, without their individual suffixes.
+    #[must_use]
+    pub fn base_path(&self) -> &Path {
+        &self.base
+    }
+
+    /// Returns the file with the given suffix, if it's part of the set.
+    #[must_use]
+    pub fn get<S: AsRef<OsStr>>(&self, suffix: S) -> Option<&NamedTempFile> {
+        self.files
+            .iter()
+            .find(|(s, _)| s == suffix.as_ref())
+            .map(|(_, file)| file)
+    }
+
+    /// Returns the file with the given suffix mutably, if it's part of the set.
+    #[must_use]
+    pub fn get_mut<S: AsRef<OsStr>>(&mut self, suffix: S) -> Option<&mut NamedTempFile> {
+        self.files
+            .iter_mut()
+            .find(|(s, _)| s == suffix.as_ref())
+            .map(|(_, file)| file)
+    }
+
+    /// Returns an iterator over the suffixes and files in the set, in creation order.
+    pub fn iter(&self) -> impl Iterator<Item = (&OsStr, &NamedTempFile)> {
+        self.files.iter().map(|(s, file)| (s.as_os_str(), file))
+    }
+
+    /// Returns the number of files in the set.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns `true` if the set contains no files.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Converts the set into its files, in creation order. Each file is then deleted when it's
+    /// dropped on its own.
+    #[must_use]
+    pub fn into_files(self) -> Vec<NamedTempFile> {
+        self.files.into_iter().map(|(_, file)| file).collect()
+    }
+
+    /// Closes and removes all files in the set.
+    ///
+    /// # Errors
+    ///
+    /// Every file is removed even if removing another one fails. The first error is returned.
+    pub fn close(self) -> io::Result<()> {
+        self.files
+            .into_iter()
+            .map(|(_, file)| file.close())
+            .fold(Ok(()), Result::and)
+    }
+
+    /// Persist all files in the set, naming them `{new_base}{file suffix}`.
+    ///
+    /// Existing files at the target paths are replaced. Returns the files in creation order.
+    ///
+    /// # Errors
+    ///
+    /// If any file can't be persisted, the files persisted so far are rolled back, the remaining
+    /// temporary files are deleted, and `Err` is returned. See [`persist_all`](crate::persist_all)
+    /// for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempFileSet;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// let set = TempFileSet::new_in(&dir, [".db", ".db-wal"])?;
+    /// let files = set.persist(dir.path().join("data"))?;
+    /// assert_eq!(files.len(), 2);
+    /// assert!(dir.path().join("data.db-wal").exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist<P: AsRef<Path>>(self, new_base: P) -> io::Result<Vec<File>> {
+        let new_base = new_base.as_ref();
+        crate::persist_all(
+            self.files
+                .into_iter()
+                .map(|(suffix, file)| (file, with_suffix(new_base, &suffix))),
+        )
+        .map_err(io::Error::from)
+    }
+}
+
+/// Returns `base` with `suffix` appended to its file name.
+fn with_suffix(base: &Path, suffix: &OsStr) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+impl Builder<'_, '_> {
+    /// Create a [`TempFileSet`] of named temporary files with the given suffixes inside of
+    /// [`env::temp_dir()`].
+    ///
+    /// All files share this builder's prefix, random name, and suffix, followed by their own
+    /// suffix from `suffixes`. All other options (permissions, append, keep, hooks) apply to every
+    /// file in the set.
+    ///
+    /// # Errors
+    ///
+    /// If the suffixes aren't unique or any of the files can not be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let set = Builder::new().prefix("index-").tempfile_set([".idx", ".dat"])?;
+    /// assert_eq!(set.len(), 2);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn tempfile_set<I, S>(&self, suffixes: I) -> io::Result<TempFileSet>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.tempfile_set_in(env::temp_dir(), suffixes)
+    }
+
+    /// Create a [`TempFileSet`] of named temporary files with the given suffixes inside of `dir`.
+    ///
+    /// See [`Builder::tempfile_set`] for details.
+    ///
+    /// # Errors
+    ///
+    /// If the suffixes aren't unique or any of the files can not be created, `Err` is returned.
+    pub fn tempfile_set_in<P, I, S>(&self, dir: P, suffixes: I) -> io::Result<TempFileSet>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let suffixes: Vec<OsString> = suffixes
+            .into_iter()
+            .map(|s| s.as_ref().to_owned())
+            .collect();
+        if (1..suffixes.len()).any(|i| suffixes[..i].contains(&suffixes[i])) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the suffixes of a temporary file set must be unique",
+            ));
+        }
+        self.check_dir(dir.as_ref())?;
+        let prefix = self.name_prefix();
+        // Leave room for the longest file suffix when shortening the random part to fit.
+        let mut longest = self.suffix.to_owned();
+        longest.push(
+            suffixes
+                .iter()
+                .max_by_key(|s| s.len())
+                .map_or(OsStr::new(""), |s| s),
+        );
+        let random_len = util::fit_name(dir.as_ref(), &prefix, &longest, self.random_len)?;
+        util::create_helper_with(
+            dir.as_ref(),
+            &prefix,
+            self.suffix,
+            random_len,
+            self.generator(),
+            self.retry,
+            |mut base| {
+                // Make the path absolute, like the paths of the files themselves.
+                if !base.is_absolute() {
+                    base = std::env::current_dir()?.join(base);
+                }
+                // If any file fails, the ones created so far are deleted when dropped.
+                let files = suffixes
+                    .iter()
+                    .map(|suffix| {
+                        file::create_named(
+                            with_suffix(&base, suffix),
+                            &mut self.open_options_for_file(),
+                            self,
+                        )
+                        .map(|file| (suffix.clone(), file))
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+                Ok(TempFileSet { base, files })
+            },
+        )
+        .map(|set| TempFileSet {
+            files: set
+                .files
+                .into_iter()
+                .map(|(suffix, file)| (suffix, self.created(file)))
+                .collect(),
+            ..set
+        })
+    }
+}
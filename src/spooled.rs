@@ -1,11 +1,30 @@
 use crate::file::tempfile;
+use crate::{env, Builder, NamedTempFile};
 use std::fs::File;
 use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::{error, fmt, mem};
+
+// Once rust 1.70 is wide-spread (Debian stable), we can use OnceLock from stdlib.
+use once_cell::sync::OnceCell as OnceLock;
+
+/// The fraction of the available memory [`spooled_tempfile_auto`] is willing to buffer per file.
+const AUTO_MEMORY_FRACTION: u64 = 64;
+/// Lower bound for the automatically derived `max_size`.
+const AUTO_MIN_SIZE: u64 = 64 * 1024;
+/// Upper bound for the automatically derived `max_size`.
+const AUTO_MAX_SIZE: u64 = 64 * 1024 * 1024;
+/// The `max_size` used when the memory limit can't be determined.
+const AUTO_DEFAULT_SIZE: u64 = 1024 * 1024;
 
 /// A wrapper for the two states of a `SpooledTempFile`.
 #[derive(Debug)]
 pub enum SpooledData {
+    /// The data is buffered in memory.
     InMemory(Cursor<Vec<u8>>),
+    /// The data has been rolled over to a temporary file on disk.
     OnDisk(File),
 }
 
@@ -17,6 +36,232 @@ pub enum SpooledData {
 pub struct SpooledTempFile {
     max_size: usize,
     inner: SpooledData,
+    pool: Option<Reservation>,
+    wipe: bool,
+}
+
+/// A memory budget shared by multiple [`SpooledTempFile`]s.
+///
+/// Each `SpooledTempFile` created by [`SpooledPool::spooled_tempfile`] still rolls over to disk
+/// once it exceeds its own `max_size`, but additionally, when growing a file's in-memory buffer
+/// would exceed the pool's total budget, the largest files in the pool are rolled over to make
+/// room. This bounds the memory used by, e.g., a server buffering thousands of simultaneous
+/// uploads. Memory is returned to the pool when a file rolls over, shrinks, or is dropped.
+///
+/// If the growing file is the largest, it rolls over immediately. Otherwise, as files can only be
+/// rolled over by their owner, the larger files are marked and roll over the next time they're
+/// read, written, or seeked, and the growing file stays in memory. Until then, the pool's
+/// [used](SpooledPool::used) memory may exceed its capacity by at most the size of the marked
+/// files.
+///
+/// `SpooledPool` is cheap to clone; clones share the same budget.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::SpooledPool;
+/// use std::io::Write;
+///
+/// let pool = SpooledPool::new(20);
+/// let mut a = pool.spooled_tempfile(15);
+/// let mut b = pool.spooled_tempfile(15);
+///
+/// a.write_all(b"0123456789")?;
+/// b.write_all(b"0123456789")?;
+/// assert!(!a.is_rolled() && !b.is_rolled());
+/// assert_eq!(pool.used(), 20);
+///
+/// // The pool is exhausted and `a` is the largest file, so it rolls over to disk, releasing its
+/// // memory.
+/// a.write_all(b"abc")?;
+/// assert!(a.is_rolled());
+/// assert_eq!(pool.used(), 10);
+///
+/// // `b` is the largest file, so it's rolled over the next time it's used, instead of `c`.
+/// b.write_all(b"abcd")?;
+/// let mut c = pool.spooled_tempfile(15);
+/// c.write_all(b"01234567")?;
+/// assert!(!c.is_rolled());
+/// assert_eq!(pool.used(), 22);
+/// b.flush()?;
+/// assert!(b.is_rolled());
+/// assert_eq!(pool.used(), 8);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpooledPool {
+    inner: Arc<PoolInner>,
+}
+
+#[derive(Debug)]
+struct PoolInner {
+    capacity: usize,
+    used: AtomicUsize,
+    members: Mutex<Vec<Weak<Member>>>,
+}
+
+/// The state of a pool's file shared with the other files in the pool.
+#[derive(Debug, Default)]
+struct Member {
+    /// The number of bytes reserved by the file.
+    reserved: AtomicUsize,
+    /// Set when the file should roll over to make room for others.
+    spill: AtomicBool,
+}
+
+impl SpooledPool {
+    /// Create a new pool with a budget of `total_bytes` bytes.
+    #[must_use]
+    pub fn new(total_bytes: usize) -> SpooledPool {
+        SpooledPool {
+            inner: Arc::new(PoolInner {
+                capacity: total_bytes,
+                used: AtomicUsize::new(0),
+                members: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Returns the pool's total budget in bytes.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity
+    }
+
+    /// Returns the number of bytes currently buffered in memory by the pool's files.
+    ///
+    /// This may temporarily exceed the [capacity](SpooledPool::capacity) while files marked to
+    /// roll over haven't been used since.
+    #[must_use]
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::Relaxed)
+    }
+
+    /// Create a new spooled temporary file drawing from this pool's budget.
+    #[must_use]
+    pub fn spooled_tempfile(&self, max_size: usize) -> SpooledTempFile {
+        let member = Arc::new(Member::default());
+        let mut members = self.members();
+        members.retain(|member| member.strong_count() > 0);
+        members.push(Arc::downgrade(&member));
+        drop(members);
+
+        let mut file = SpooledTempFile::new(max_size);
+        file.pool = Some(Reservation {
+            pool: self.clone(),
+            member,
+        });
+        file
+    }
+
+    fn members(&self) -> std::sync::MutexGuard<'_, Vec<Weak<Member>>> {
+        // The list stays consistent even if a thread panicked while holding the lock.
+        self.inner
+            .members
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The memory a `SpooledTempFile` has reserved from its pool, released on drop.
+#[derive(Debug)]
+struct Reservation {
+    pool: SpooledPool,
+    member: Arc<Member>,
+}
+
+impl Reservation {
+    fn reserved(&self) -> usize {
+        self.member.reserved.load(Ordering::Relaxed)
+    }
+
+    /// Reserve another `n` bytes, returning false if the file should roll over instead.
+    ///
+    /// If the pool is exhausted, the largest files are marked to roll over until they free
+    /// enough memory. If this file is among them, nothing is marked and false is returned.
+    fn try_grow(&mut self, n: usize) -> bool {
+        let pool = &self.pool.inner;
+        let reserved = pool
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(n).filter(|&used| used <= pool.capacity)
+            })
+            .is_ok();
+        if !reserved && !self.make_room(n) {
+            return false;
+        }
+        self.member.reserved.fetch_add(n, Ordering::Relaxed);
+        true
+    }
+
+    /// Mark the files larger than this one (once grown by `n` bytes) to roll over until they
+    /// cover the pool's deficit, reserving `n` bytes past the capacity if they do.
+    fn make_room(&self, n: usize) -> bool {
+        let size = self.reserved().saturating_add(n);
+        let members = self.pool.members();
+        let mut others: Vec<_> = members
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|member| !Arc::ptr_eq(member, &self.member))
+            .collect();
+        // Files already marked will release their memory soon.
+        let pending: usize = others
+            .iter()
+            .filter(|member| member.spill.load(Ordering::Relaxed))
+            .map(|member| member.reserved.load(Ordering::Relaxed))
+            .sum();
+        let used = self.pool.inner.used.load(Ordering::Relaxed);
+        let mut deficit = used
+            .saturating_add(n)
+            .saturating_sub(self.pool.inner.capacity)
+            .saturating_sub(pending);
+        others.retain(|member| !member.spill.load(Ordering::Relaxed));
+        others.sort_by_key(|member| std::cmp::Reverse(member.reserved.load(Ordering::Relaxed)));
+
+        let mut marked = 0;
+        for member in &others {
+            let reserved = member.reserved.load(Ordering::Relaxed);
+            if deficit == 0 || reserved <= size {
+                break;
+            }
+            deficit = deficit.saturating_sub(reserved);
+            marked += 1;
+        }
+        if deficit > 0 {
+            return false;
+        }
+        for member in &others[..marked] {
+            member.spill.store(true, Ordering::Relaxed);
+        }
+        self.pool.inner.used.fetch_add(n, Ordering::Relaxed);
+        true
+    }
+
+    /// Returns true if the file has been marked to roll over to make room for others.
+    fn should_spill(&self) -> bool {
+        self.member.spill.load(Ordering::Relaxed)
+    }
+
+    /// Release all but `n` reserved bytes.
+    fn shrink_to(&mut self, n: usize) {
+        let reserved = self.reserved();
+        if n < reserved {
+            self.pool
+                .inner
+                .used
+                .fetch_sub(reserved - n, Ordering::Relaxed);
+            self.member.reserved.store(n, Ordering::Relaxed);
+        }
+        if n == 0 {
+            self.member.spill.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.shrink_to(0);
+    }
 }
 
 /// Create a new spooled temporary file.
@@ -55,12 +300,175 @@ pub fn spooled_tempfile(max_size: usize) -> SpooledTempFile {
     SpooledTempFile::new(max_size)
 }
 
+/// Create a new spooled temporary file, deriving the maximum in-memory size from the memory
+/// available to the current process.
+///
+/// On Linux, this is derived from the process's cgroup memory limit (if any) or the total amount of
+/// physical memory otherwise. The in-memory buffer is limited to 1/64th of that amount, clamped
+/// between 64KiB and 64MiB. On other platforms, or if the limit can't be determined, the maximum
+/// size defaults to 1MiB.
+///
+/// The limit is computed once and cached for the lifetime of the process.
+///
+/// # Security
+///
+/// This variant is secure/reliable in the presence of a pathological temporary
+/// file cleaner.
+///
+/// # Resource Leaking
+///
+/// The temporary file will be automatically removed by the OS when the last
+/// handle to it is closed. This doesn't rely on Rust destructors being run, so
+/// will (almost) never fail to clean up the temporary file.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::spooled_tempfile_auto;
+/// use std::io::Write;
+///
+/// let mut file = spooled_tempfile_auto();
+///
+/// writeln!(file, "short line")?;
+/// assert!(!file.is_rolled());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn spooled_tempfile_auto() -> SpooledTempFile {
+    static AUTO_SIZE: OnceLock<usize> = OnceLock::new();
+    let max_size = *AUTO_SIZE.get_or_init(|| {
+        let size = match memory_limit() {
+            Some(limit) => (limit / AUTO_MEMORY_FRACTION).clamp(AUTO_MIN_SIZE, AUTO_MAX_SIZE),
+            None => AUTO_DEFAULT_SIZE,
+        };
+        usize::try_from(size).unwrap_or(usize::MAX)
+    });
+    SpooledTempFile::new(max_size)
+}
+
+/// Returns the amount of memory available to this process, if known.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn memory_limit() -> Option<u64> {
+    use std::fs;
+
+    // cgroup v2 reports "max" when unlimited, cgroup v1 reports a very large number. In both cases,
+    // fall back on the total amount of memory.
+    let cgroup_limit = [
+        "/sys/fs/cgroup/memory.max",
+        "/sys/fs/cgroup/memory/memory.limit_in_bytes",
+    ]
+    .iter()
+    .filter_map(|path| fs::read_to_string(path).ok())
+    .find_map(|limit| limit.trim().parse::<u64>().ok());
+
+    let total = fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|meminfo| {
+            meminfo
+                .lines()
+                .find_map(|line| line.strip_prefix("MemTotal:"))
+                .and_then(|rest| rest.trim().strip_suffix("kB"))
+                .and_then(|kb| kb.trim().parse::<u64>().ok())
+                .map(|kb| kb.saturating_mul(1024))
+        });
+
+    match (cgroup_limit, total) {
+        (Some(limit), Some(total)) => Some(limit.min(total)),
+        (limit, total) => limit.or(total),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn memory_limit() -> Option<u64> {
+    None
+}
+
 impl SpooledTempFile {
     #[must_use]
     pub fn new(max_size: usize) -> SpooledTempFile {
         SpooledTempFile {
             max_size,
             inner: SpooledData::InMemory(Cursor::new(Vec::new())),
+            pool: None,
+            wipe: false,
+        }
+    }
+
+    /// Overwrite the contents with zeros when the file is dropped, closed, or rolled over.
+    ///
+    /// The in-memory buffer is zeroed before it's freed, and the temporary file on disk (once
+    /// rolled over) is overwritten with zeros before it's closed. This is the spooled counterpart
+    /// of [`Builder::wipe_on_drop`], and is useful when buffering credentials or keys. Contents
+    /// handed off with [`SpooledTempFile::into_file`] or [`SpooledTempFile::into_inner`] are no
+    /// longer wiped.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Security
+    ///
+    /// Wiping is best-effort. Growing the in-memory buffer may reallocate it, leaving earlier
+    /// copies of the contents in freed memory; call [`SpooledTempFile::set_len`] with the final
+    /// size first to avoid that. The caveats of [`Builder::wipe_on_drop`] about SSDs and
+    /// journaling filesystems apply once the file is rolled over to disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::SpooledTempFile;
+    /// use std::io::Write;
+    ///
+    /// let mut file = SpooledTempFile::new(1024);
+    /// file.wipe_on_drop(true);
+    /// file.write_all(b"hunter2")?;
+    /// // Zeroes the buffer before freeing it.
+    /// drop(file);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn wipe_on_drop(&mut self, wipe: bool) -> &mut Self {
+        self.wipe = wipe;
+        self
+    }
+
+    /// Overwrite the buffered or on-disk contents with zeros.
+    fn wipe(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            SpooledData::InMemory(cursor) => {
+                zeroize(cursor.get_mut());
+                Ok(())
+            }
+            SpooledData::OnDisk(file) => crate::file::wipe(file),
+        }
+    }
+
+    /// Take the contents out of `self`, leaving an empty buffer behind.
+    fn take_inner(&mut self) -> SpooledData {
+        mem::replace(
+            &mut self.inner,
+            SpooledData::InMemory(Cursor::new(Vec::new())),
+        )
+    }
+
+    /// Returns true if the in-memory buffer may grow to `len` bytes, reserving the memory from the
+    /// pool (if any).
+    fn reserve(&mut self, len: u64) -> bool {
+        let cur_len = match &self.inner {
+            SpooledData::InMemory(cursor) => cursor.get_ref().len() as u64,
+            SpooledData::OnDisk(_) => return true,
+        };
+        if len > self.max_size as u64 {
+            return false;
+        }
+        match &mut self.pool {
+            // `len` is at most `max_size` so this can't truncate.
+            Some(pool) if len > cur_len => pool.try_grow((len - cur_len) as usize),
+            _ => true,
+        }
+    }
+
+    /// Roll over to disk if another file in the pool marked this one to make room.
+    fn spill_if_marked(&mut self) -> io::Result<()> {
+        match &self.pool {
+            Some(pool) if pool.should_spill() => self.roll(),
+            _ => Ok(()),
         }
     }
 
@@ -73,42 +481,260 @@ impl SpooledTempFile {
         }
     }
 
+    /// Returns the file on disk, or `None` if the data is still in memory.
+    ///
+    /// Use this to pass a rolled over file to APIs that need a file handle
+    /// (e.g., via `AsFd`/`AsHandle`). Call [`SpooledTempFile::roll`] first to
+    /// force the data onto disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::SpooledTempFile;
+    ///
+    /// let mut file = SpooledTempFile::new(1024);
+    /// assert!(file.as_file().is_none());
+    /// file.roll()?;
+    /// assert!(file.as_file().unwrap().metadata()?.is_file());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn as_file(&self) -> Option<&File> {
+        match &self.inner {
+            SpooledData::InMemory(_) => None,
+            SpooledData::OnDisk(file) => Some(file),
+        }
+    }
+
+    /// Returns the file on disk mutably, or `None` if the data is still in
+    /// memory.
+    ///
+    /// See [`SpooledTempFile::as_file`].
+    #[must_use]
+    pub fn as_file_mut(&mut self) -> Option<&mut File> {
+        match &mut self.inner {
+            SpooledData::InMemory(_) => None,
+            SpooledData::OnDisk(file) => Some(file),
+        }
+    }
+
+    /// Returns the buffered contents, or `None` if the file has been rolled
+    /// over to disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::SpooledTempFile;
+    /// use std::io::Write;
+    ///
+    /// let mut file = SpooledTempFile::new(1024);
+    /// write!(file, "short line")?;
+    /// assert_eq!(file.as_slice(), Some(&b"short line"[..]));
+    ///
+    /// file.roll()?;
+    /// assert_eq!(file.as_slice(), None);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        match &self.inner {
+            SpooledData::InMemory(cursor) => Some(cursor.get_ref()),
+            SpooledData::OnDisk(_) => None,
+        }
+    }
+
     /// Rolls over to a file on disk, regardless of current size. Does nothing
     /// if already rolled over.
+    ///
+    /// The in-memory buffer is released before this function returns, so this
+    /// can be used to free memory deterministically (e.g., before forking or
+    /// before handing the file off to another thread for a long time).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::SpooledTempFile;
+    /// use std::io::Write;
+    ///
+    /// let mut file = SpooledTempFile::new(1024);
+    /// writeln!(file, "short line")?;
+    /// assert!(!file.is_rolled());
+    ///
+    /// file.roll()?;
+    /// assert!(file.is_rolled());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
     pub fn roll(&mut self) -> io::Result<()> {
         if !self.is_rolled() {
             let mut file = tempfile()?;
             if let SpooledData::InMemory(cursor) = &mut self.inner {
                 file.write_all(cursor.get_ref())?;
                 file.seek(SeekFrom::Start(cursor.position()))?;
+                if self.wipe {
+                    zeroize(cursor.get_mut());
+                }
             }
             self.inner = SpooledData::OnDisk(file);
+            if let Some(pool) = &mut self.pool {
+                pool.shrink_to(0);
+            }
         }
         Ok(())
     }
 
+    /// Truncates or extends the file to `size` bytes, like [`File::set_len`].
+    ///
+    /// While in memory, the buffer is truncated or zero-extended. If `size` exceeds the maximum
+    /// in-memory size, the file is first rolled over to disk. The cursor position is unchanged in
+    /// either case (even if it's now past the end of the file).
+    ///
+    /// # Errors
+    ///
+    /// If rolling over or resizing the file on disk fails, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::SpooledTempFile;
+    /// use std::io::Write;
+    ///
+    /// let mut file = SpooledTempFile::new(15);
+    /// file.write_all(b"hello world")?;
+    /// file.set_len(5)?;
+    /// assert!(!file.is_rolled());
+    ///
+    /// file.set_len(20)?;
+    /// assert!(file.is_rolled());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
     pub fn set_len(&mut self, size: u64) -> Result<(), io::Error> {
-        if size > self.max_size as u64 {
+        self.spill_if_marked()?;
+        if !self.reserve(size) {
             self.roll()?; // does nothing if already rolled over
         }
         match &mut self.inner {
             SpooledData::InMemory(cursor) => {
                 cursor.get_mut().resize(size as usize, 0);
+                if let Some(pool) = &mut self.pool {
+                    pool.shrink_to(size as usize);
+                }
                 Ok(())
             }
             SpooledData::OnDisk(file) => file.set_len(size),
         }
     }
 
+    /// Closes the spooled temporary file, releasing the in-memory buffer or closing the temporary
+    /// file on disk.
+    ///
+    /// Although dropping a `SpooledTempFile` releases the same resources, any error encountered
+    /// while closing the on-disk file is silently ignored. To detect such errors, call `close`
+    /// instead.
+    ///
+    /// # Errors
+    ///
+    /// If the file has been rolled over to disk and closing it fails, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::SpooledTempFile;
+    /// use std::io::Write;
+    ///
+    /// let mut file = SpooledTempFile::new(15);
+    /// writeln!(file, "marvin gardens, boardwalk")?;
+    /// assert!(file.is_rolled());
+    ///
+    /// file.close()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn close(mut self) -> io::Result<()> {
+        let wiped = if self.wipe { self.wipe() } else { Ok(()) };
+        let closed = match self.take_inner() {
+            SpooledData::InMemory(_) => Ok(()),
+            SpooledData::OnDisk(file) => crate::file::close(file),
+        };
+        wiped.and(closed)
+    }
+
+    /// Rolls over to a file on disk (if not already rolled over) and returns the underlying file,
+    /// consuming the `SpooledTempFile`.
+    ///
+    /// The file's cursor is left at the current position.
+    ///
+    /// # Errors
+    ///
+    /// If the file needs to be rolled over and rolling over fails, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::SpooledTempFile;
+    /// use std::io::{Read, Seek, SeekFrom, Write};
+    ///
+    /// let mut spooled = SpooledTempFile::new(1024);
+    /// writeln!(spooled, "short line")?;
+    ///
+    /// let mut file = spooled.into_file()?;
+    /// file.seek(SeekFrom::Start(0))?;
+    /// let mut contents = String::new();
+    /// file.read_to_string(&mut contents)?;
+    /// assert_eq!(contents, "short line\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn into_file(mut self) -> io::Result<File> {
+        self.roll()?;
+        match self.take_inner() {
+            SpooledData::OnDisk(file) => Ok(file),
+            SpooledData::InMemory(_) => unreachable!("rolled over above"),
+        }
+    }
+
     /// Consumes and returns the inner `SpooledData` type.
+    ///
+    /// Use this to take ownership of the buffered contents without copying them:
+    ///
+    /// ```
+    /// use tempfile::{SpooledData, SpooledTempFile};
+    /// use std::io::Write;
+    ///
+    /// let mut file = SpooledTempFile::new(1024);
+    /// write!(file, "short line")?;
+    /// if let SpooledData::InMemory(cursor) = file.into_inner() {
+    ///     assert_eq!(cursor.into_inner(), b"short line");
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
     #[must_use]
-    pub fn into_inner(self) -> SpooledData {
-        self.inner
+    pub fn into_inner(mut self) -> SpooledData {
+        self.take_inner()
     }
 }
 
+impl Drop for SpooledTempFile {
+    fn drop(&mut self) {
+        if self.wipe {
+            // Best-effort: there's no way to report errors from a destructor.
+            let _ = self.wipe();
+        }
+    }
+}
+
+/// Overwrite the entire allocation of `buf` with zeros in a way the compiler can't optimize out.
+fn zeroize(buf: &mut Vec<u8>) {
+    // Also cover bytes past the end, left over from truncation. This doesn't reallocate.
+    buf.resize(buf.capacity(), 0);
+    for byte in buf.iter_mut() {
+        // SAFETY: `byte` is a valid, exclusive reference.
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    std::sync::atomic::compiler_fence(Ordering::SeqCst);
+    buf.clear();
+}
+
 impl Read for SpooledTempFile {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.spill_if_marked()?;
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.read(buf),
             SpooledData::OnDisk(file) => file.read(buf),
@@ -116,6 +742,7 @@ impl Read for SpooledTempFile {
     }
 
     fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        self.spill_if_marked()?;
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.read_vectored(bufs),
             SpooledData::OnDisk(file) => file.read_vectored(bufs),
@@ -123,6 +750,7 @@ impl Read for SpooledTempFile {
     }
 
     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.spill_if_marked()?;
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.read_to_end(buf),
             SpooledData::OnDisk(file) => file.read_to_end(buf),
@@ -130,6 +758,7 @@ impl Read for SpooledTempFile {
     }
 
     fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.spill_if_marked()?;
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.read_to_string(buf),
             SpooledData::OnDisk(file) => file.read_to_string(buf),
@@ -137,6 +766,7 @@ impl Read for SpooledTempFile {
     }
 
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.spill_if_marked()?;
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.read_exact(buf),
             SpooledData::OnDisk(file) => file.read_exact(buf),
@@ -146,12 +776,12 @@ impl Read for SpooledTempFile {
 
 impl Write for SpooledTempFile {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.spill_if_marked()?;
         // roll over to file if necessary
-        if matches! {
-            &self.inner, SpooledData::InMemory(cursor)
-            if cursor.position().saturating_add(buf.len() as u64) > self.max_size as u64
-        } {
-            self.roll()?;
+        if let SpooledData::InMemory(cursor) = &self.inner {
+            if !self.reserve(cursor.position().saturating_add(buf.len() as u64)) {
+                self.roll()?;
+            }
         }
 
         // write the bytes
@@ -162,15 +792,15 @@ impl Write for SpooledTempFile {
     }
 
     fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
-        if matches! {
-            &self.inner, SpooledData::InMemory(cursor)
+        self.spill_if_marked()?;
+        if let SpooledData::InMemory(cursor) = &self.inner {
             // Borrowed from the rust standard library.
-            if bufs
+            let end = bufs
                 .iter()
-                .fold(cursor.position(), |a, b| a.saturating_add(b.len() as u64))
-                > self.max_size as u64
-        } {
-            self.roll()?;
+                .fold(cursor.position(), |a, b| a.saturating_add(b.len() as u64));
+            if !self.reserve(end) {
+                self.roll()?;
+            }
         }
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.write_vectored(bufs),
@@ -180,6 +810,7 @@ impl Write for SpooledTempFile {
 
     #[inline]
     fn flush(&mut self) -> io::Result<()> {
+        self.spill_if_marked()?;
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.flush(),
             SpooledData::OnDisk(file) => file.flush(),
@@ -189,9 +820,256 @@ impl Write for SpooledTempFile {
 
 impl Seek for SpooledTempFile {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.spill_if_marked()?;
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.seek(pos),
             SpooledData::OnDisk(file) => file.seek(pos),
         }
     }
 }
+
+/// The two states of a [`SpooledNamedTempFile`].
+#[derive(Debug)]
+enum SpooledNamedData {
+    InMemory(Cursor<Vec<u8>>),
+    OnDisk(NamedTempFile),
+}
+
+/// Like [`SpooledTempFile`], but rolls over to a [`NamedTempFile`] that can be persisted.
+///
+/// Small payloads stay in memory and are only written to disk when persisted, while large payloads
+/// are spooled to a named temporary file that's atomically renamed into place. This is useful for,
+/// e.g., caches that atomically replace entries of widely varying sizes.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::SpooledNamedTempFile;
+/// use std::io::Write;
+///
+/// let dir = tempfile::tempdir()?;
+/// let mut file = SpooledNamedTempFile::new_in(1024, dir.path());
+/// writeln!(file, "small payload")?;
+/// assert!(file.path().is_none());
+///
+/// file.persist(dir.path().join("entry"))?;
+/// assert_eq!(std::fs::read_to_string(dir.path().join("entry"))?, "small payload\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SpooledNamedTempFile {
+    max_size: usize,
+    dir: Option<PathBuf>,
+    inner: SpooledNamedData,
+}
+
+impl SpooledNamedTempFile {
+    /// Create a new spooled named temporary file, rolling over into [`env::temp_dir()`].
+    #[must_use]
+    pub fn new(max_size: usize) -> SpooledNamedTempFile {
+        SpooledNamedTempFile {
+            max_size,
+            dir: None,
+            inner: SpooledNamedData::InMemory(Cursor::new(Vec::new())),
+        }
+    }
+
+    /// Create a new spooled named temporary file, rolling over into the specified directory.
+    ///
+    /// The directory should be on the same filesystem as the eventual [`persist`] target.
+    ///
+    /// [`persist`]: SpooledNamedTempFile::persist
+    #[must_use]
+    pub fn new_in<P: AsRef<Path>>(max_size: usize, dir: P) -> SpooledNamedTempFile {
+        SpooledNamedTempFile {
+            dir: Some(dir.as_ref().to_owned()),
+            ..SpooledNamedTempFile::new(max_size)
+        }
+    }
+
+    /// Returns true if the file has been rolled over to disk.
+    #[must_use]
+    pub fn is_rolled(&self) -> bool {
+        matches!(self.inner, SpooledNamedData::OnDisk(_))
+    }
+
+    /// Returns the path of the temporary file on disk, or `None` if the data is still in memory.
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        match &self.inner {
+            SpooledNamedData::InMemory(_) => None,
+            SpooledNamedData::OnDisk(file) => Some(file.path()),
+        }
+    }
+
+    /// Returns the buffered contents, or `None` if the file has been rolled over to disk.
+    #[must_use]
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        match &self.inner {
+            SpooledNamedData::InMemory(cursor) => Some(cursor.get_ref()),
+            SpooledNamedData::OnDisk(_) => None,
+        }
+    }
+
+    /// Rolls over to a named temporary file on disk, regardless of current size. Does nothing if
+    /// already rolled over.
+    pub fn roll(&mut self) -> io::Result<()> {
+        if let SpooledNamedData::InMemory(cursor) = &self.inner {
+            let dir = self.dir.clone().unwrap_or_else(env::temp_dir);
+            let file = spill(cursor, &dir)?;
+            self.inner = SpooledNamedData::OnDisk(file);
+        }
+        Ok(())
+    }
+
+    /// Rolls over to disk (if not already rolled over) and returns the underlying named temporary
+    /// file, consuming the `SpooledNamedTempFile`.
+    ///
+    /// The file's cursor is left at the current position.
+    pub fn into_named_tempfile(mut self) -> io::Result<NamedTempFile> {
+        self.roll()?;
+        match self.inner {
+            SpooledNamedData::OnDisk(file) => Ok(file),
+            SpooledNamedData::InMemory(_) => unreachable!("rolled over above"),
+        }
+    }
+
+    /// Persist the data at the target path, atomically replacing any existing file, and return
+    /// the underlying file.
+    ///
+    /// If the data is still in memory, it's first written to a named temporary file next to
+    /// `path` (so it can be atomically renamed into place) instead of the directory passed to
+    /// [`SpooledNamedTempFile::new_in`].
+    ///
+    /// # Errors
+    ///
+    /// If the data can't be written out or the file can't be persisted, `Err` is returned. The
+    /// `SpooledNamedTempFile` is returned in the [`SpooledPersistError`] so that the data isn't
+    /// lost; if the data was written out, it's now on disk next to `path`.
+    pub fn persist<P: AsRef<Path>>(mut self, path: P) -> Result<File, SpooledPersistError> {
+        let path = path.as_ref();
+        if let SpooledNamedData::InMemory(cursor) = &self.inner {
+            let dir = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            match spill(cursor, dir) {
+                Ok(file) => self.inner = SpooledNamedData::OnDisk(file),
+                Err(error) => return Err(SpooledPersistError { error, file: self }),
+            }
+        }
+        let file = match self.inner {
+            SpooledNamedData::OnDisk(file) => file,
+            SpooledNamedData::InMemory(_) => unreachable!("written out above"),
+        };
+        file.persist(path).map_err(|e| SpooledPersistError {
+            error: e.error,
+            file: SpooledNamedTempFile {
+                inner: SpooledNamedData::OnDisk(e.file),
+                ..self
+            },
+        })
+    }
+}
+
+/// Error returned when persisting a [`SpooledNamedTempFile`] fails.
+pub struct SpooledPersistError {
+    /// The underlying IO error.
+    pub error: io::Error,
+    /// The spooled temporary file that couldn't be persisted.
+    pub file: SpooledNamedTempFile,
+}
+
+impl fmt::Debug for SpooledPersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SpooledPersistError({:?})", self.error)
+    }
+}
+
+impl From<SpooledPersistError> for io::Error {
+    #[inline]
+    fn from(error: SpooledPersistError) -> io::Error {
+        error.error
+    }
+}
+
+impl From<SpooledPersistError> for SpooledNamedTempFile {
+    #[inline]
+    fn from(error: SpooledPersistError) -> SpooledNamedTempFile {
+        error.file
+    }
+}
+
+impl fmt::Display for SpooledPersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to persist spooled temporary file: {}",
+            self.error
+        )
+    }
+}
+
+impl error::Error for SpooledPersistError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Write out the contents of `cursor` to a new named temporary file in `dir`.
+fn spill(cursor: &Cursor<Vec<u8>>, dir: &Path) -> io::Result<NamedTempFile> {
+    let mut file = Builder::new().tempfile_in(dir)?;
+    file.write_all(cursor.get_ref())?;
+    file.seek(SeekFrom::Start(cursor.position()))?;
+    Ok(file)
+}
+
+impl Read for SpooledNamedTempFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            SpooledNamedData::InMemory(cursor) => cursor.read(buf),
+            SpooledNamedData::OnDisk(file) => file.read(buf),
+        }
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        match &mut self.inner {
+            SpooledNamedData::InMemory(cursor) => cursor.read_vectored(bufs),
+            SpooledNamedData::OnDisk(file) => file.read_vectored(bufs),
+        }
+    }
+}
+
+impl Write for SpooledNamedTempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // roll over to file if necessary
+        if matches! {
+            &self.inner, SpooledNamedData::InMemory(cursor)
+            if cursor.position().saturating_add(buf.len() as u64) > self.max_size as u64
+        } {
+            self.roll()?;
+        }
+
+        match &mut self.inner {
+            SpooledNamedData::InMemory(cursor) => cursor.write(buf),
+            SpooledNamedData::OnDisk(file) => file.write(buf),
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            SpooledNamedData::InMemory(cursor) => cursor.flush(),
+            SpooledNamedData::OnDisk(file) => file.flush(),
+        }
+    }
+}
+
+impl Seek for SpooledNamedTempFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match &mut self.inner {
+            SpooledNamedData::InMemory(cursor) => cursor.seek(pos),
+            SpooledNamedData::OnDisk(file) => file.seek(pos),
+        }
+    }
+}
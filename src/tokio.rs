@@ -0,0 +1,755 @@
+//! Async temporary files for use with [tokio](https://tokio.rs).
+//!
+//! This module is only available when the `tokio` feature is enabled. Blocking filesystem
+//! operations (creation, persisting, reopening, and deletion) are run on tokio's blocking thread
+//! pool via [`spawn_blocking`] so they don't stall the async executor.
+//!
+//! All async functions in this module must be called from within a tokio runtime.
+//!
+//! [`spawn_blocking`]: ::tokio::task::spawn_blocking
+
+// `PersistError<tokio::fs::File>` is large, but it mirrors the blocking API.
+#![allow(clippy::result_large_err)]
+
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Cursor, Seek, Write};
+use std::mem;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use ::tokio::fs::File;
+use ::tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, AsyncWriteExt, ReadBuf};
+use ::tokio::runtime::Handle;
+use ::tokio::task::{self, JoinError, JoinHandle};
+
+use crate::env;
+use crate::error::IoResultExt;
+use crate::{PersistError, TempPath};
+
+/// Run a blocking operation on tokio's blocking thread pool.
+async fn asyncify<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    match task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(e) => panic::resume_unwind(e.into_panic()),
+    }
+}
+
+/// An async named temporary file.
+///
+/// This is the async counterpart of [`crate::NamedTempFile`], wrapping a [`tokio::fs::File`]. The
+/// file is deleted when the `NamedTempFile` is dropped. When dropped from within a tokio runtime,
+/// the deletion is performed on the blocking thread pool; otherwise, it happens immediately.
+///
+/// If you need to know when the file has been deleted, or want to handle errors, use
+/// [`NamedTempFile::close`] instead of dropping the file.
+///
+/// # Examples
+///
+/// ```
+/// use tokio::io::AsyncWriteExt;
+///
+/// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+/// let mut file = tempfile::tokio::NamedTempFile::new().await?;
+/// file.write_all(b"Hello World!").await?;
+/// file.close().await?;
+/// # Ok::<(), std::io::Error>(())
+/// # })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// [`tokio::fs::File`]: ::tokio::fs::File
+pub struct NamedTempFile {
+    // Only `None` after the file has been consumed (or while dropping).
+    inner: Option<crate::NamedTempFile<File>>,
+}
+
+impl fmt::Debug for NamedTempFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NamedTempFile({:?})", self.path())
+    }
+}
+
+impl AsRef<Path> for NamedTempFile {
+    #[inline]
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl NamedTempFile {
+    /// Create a new named temporary file in [`env::temp_dir()`].
+    ///
+    /// See [`crate::NamedTempFile::new`] for details.
+    pub async fn new() -> io::Result<NamedTempFile> {
+        NamedTempFile::new_in(env::temp_dir()).await
+    }
+
+    /// Create a new named temporary file in the specified directory.
+    ///
+    /// See [`crate::NamedTempFile::new_in`] for details.
+    pub async fn new_in<P: AsRef<Path>>(dir: P) -> io::Result<NamedTempFile> {
+        let dir = dir.as_ref().to_owned();
+        let file = asyncify(move || crate::NamedTempFile::new_in(dir)).await?;
+        Ok(file.into())
+    }
+
+    fn inner(&self) -> &crate::NamedTempFile<File> {
+        self.inner
+            .as_ref()
+            .expect("temporary file already consumed")
+    }
+
+    fn inner_mut(&mut self) -> &mut crate::NamedTempFile<File> {
+        self.inner
+            .as_mut()
+            .expect("temporary file already consumed")
+    }
+
+    fn into_inner(mut self) -> crate::NamedTempFile<File> {
+        self.inner.take().expect("temporary file already consumed")
+    }
+
+    /// Get the temporary file's path.
+    ///
+    /// See [`crate::NamedTempFile::path`] for details.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.inner().path()
+    }
+
+    /// Close and remove the temporary file.
+    ///
+    /// Use this if you want to detect errors in deleting the file.
+    pub async fn close(self) -> io::Result<()> {
+        let (file, path) = self.into_inner().into_parts();
+        drop(file);
+        asyncify(move || path.close()).await
+    }
+
+    /// Persist the temporary file at the target path, replacing any existing file.
+    ///
+    /// Pending writes are flushed before the file is persisted. See
+    /// [`crate::NamedTempFile::persist`] for details.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be flushed or moved to the new location, `Err` is returned. The
+    /// temporary file is returned inside the error as a blocking [`crate::NamedTempFile`].
+    pub async fn persist<P: AsRef<Path>>(self, new_path: P) -> Result<File, PersistError<File>> {
+        let new_path = new_path.as_ref().to_owned();
+        let inner = self.flushed(Some(&new_path)).await?;
+        asyncify(move || inner.persist(new_path)).await
+    }
+
+    /// Persist the temporary file at the target path if and only if no file exists there.
+    ///
+    /// Pending writes are flushed before the file is persisted. See
+    /// [`crate::NamedTempFile::persist_noclobber`] for details.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be flushed or moved to the new location, `Err` is returned. The
+    /// temporary file is returned inside the error as a blocking [`crate::NamedTempFile`].
+    pub async fn persist_noclobber<P: AsRef<Path>>(
+        self,
+        new_path: P,
+    ) -> Result<File, PersistError<File>> {
+        let new_path = new_path.as_ref().to_owned();
+        let inner = self.flushed(Some(&new_path)).await?;
+        asyncify(move || inner.persist_noclobber(new_path)).await
+    }
+
+    /// Keep the temporary file from being deleted.
+    ///
+    /// Pending writes are flushed before the file is kept. See [`crate::NamedTempFile::keep`]
+    /// for details.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be flushed or kept, `Err` is returned. The temporary file is returned
+    /// inside the error as a blocking [`crate::NamedTempFile`].
+    pub async fn keep(self) -> Result<(File, PathBuf), PersistError<File>> {
+        let inner = self.flushed(None).await?;
+        asyncify(move || inner.keep()).await
+    }
+
+    async fn flushed(
+        mut self,
+        target: Option<&Path>,
+    ) -> Result<crate::NamedTempFile<File>, PersistError<File>> {
+        match self.as_file_mut().flush().await {
+            Ok(()) => Ok(self.into_inner()),
+            Err(error) => Err(PersistError::new(error, self.into_inner(), target)),
+        }
+    }
+
+    /// Securely reopen the temporary file.
+    ///
+    /// See [`crate::NamedTempFile::reopen`] for details.
+    pub async fn reopen(&self) -> io::Result<File> {
+        let file = self.as_file().try_clone().await?.into_std().await;
+        let path = self.path().to_owned();
+        let backend = self.inner().backend();
+        asyncify(move || backend.reopen(&file, &path).with_err_path(|| path))
+            .await
+            .map(File::from_std)
+    }
+
+    /// Get a reference to the underlying file.
+    pub fn as_file(&self) -> &File {
+        self.inner().as_file()
+    }
+
+    /// Get a mutable reference to the underlying file.
+    pub fn as_file_mut(&mut self) -> &mut File {
+        self.inner_mut().as_file_mut()
+    }
+
+    /// Turn this async named temporary file into an async file and its associated [`TempPath`].
+    pub fn into_parts(self) -> (File, TempPath) {
+        self.into_inner().into_parts()
+    }
+
+    /// Close the file, leaving only the temporary file path.
+    ///
+    /// This is useful when another program needs to open the file by path.
+    pub fn into_temp_path(self) -> TempPath {
+        self.into_inner().into_temp_path()
+    }
+
+    /// Convert this async named temporary file into a blocking [`crate::NamedTempFile`],
+    /// keeping the cleanup guard.
+    ///
+    /// Any in-flight operations on the file are completed first. See
+    /// [`crate::NamedTempFile::from_async`].
+    pub async fn into_std(self) -> crate::NamedTempFile {
+        crate::NamedTempFile::from_async(self.into_inner()).await
+    }
+}
+
+impl From<crate::NamedTempFile<File>> for NamedTempFile {
+    fn from(file: crate::NamedTempFile<File>) -> Self {
+        NamedTempFile { inner: Some(file) }
+    }
+}
+
+impl From<crate::NamedTempFile> for NamedTempFile {
+    fn from(file: crate::NamedTempFile) -> Self {
+        file.into_async().into()
+    }
+}
+
+impl Drop for NamedTempFile {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            let (file, path) = inner.into_parts();
+            drop(file);
+            // If the blocking task never runs (e.g., because the runtime is shutting down), the
+            // path is dropped along with the task and is deleted then.
+            if let Ok(handle) = Handle::try_current() {
+                handle.spawn_blocking(move || drop(path));
+            }
+        }
+    }
+}
+
+impl AsyncRead for NamedTempFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().as_file_mut()).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for NamedTempFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(self.get_mut().as_file_mut()).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().as_file_mut()).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().as_file_mut()).poll_shutdown(cx)
+    }
+}
+
+impl AsyncSeek for NamedTempFile {
+    fn start_seek(self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(self.get_mut().as_file_mut()).start_seek(position)
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(self.get_mut().as_file_mut()).poll_complete(cx)
+    }
+}
+
+impl crate::NamedTempFile<std::fs::File> {
+    /// Convert this named temporary file into one wrapping a [`tokio::fs::File`], keeping the
+    /// cleanup guard.
+    ///
+    /// This function is only available when the `tokio` feature is enabled. The returned file
+    /// still deletes itself synchronously on drop; use [`NamedTempFile`] (e.g., via `into()`) to
+    /// delete it on tokio's blocking thread pool instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+    /// let mut file = tempfile::NamedTempFile::new()?.into_async();
+    /// file.write_all(b"Hello World!").await?;
+    /// let file = tempfile::NamedTempFile::from_async(file).await;
+    /// # Ok::<(), std::io::Error>(())
+    /// # })?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [`tokio::fs::File`]: ::tokio::fs::File
+    pub fn into_async(self) -> crate::NamedTempFile<File> {
+        let (file, path) = self.into_parts();
+        crate::NamedTempFile::from_parts(File::from_std(file), path)
+    }
+
+    /// Convert a named temporary file wrapping a [`tokio::fs::File`] back into a blocking
+    /// named temporary file, keeping the cleanup guard.
+    ///
+    /// This function is only available when the `tokio` feature is enabled. Any in-flight
+    /// operations on the file are completed first.
+    ///
+    /// [`tokio::fs::File`]: ::tokio::fs::File
+    pub async fn from_async(file: crate::NamedTempFile<File>) -> crate::NamedTempFile {
+        let (file, path) = file.into_parts();
+        crate::NamedTempFile::from_parts(file.into_std().await, path)
+    }
+}
+
+impl<F: AsyncRead + Unpin> AsyncRead for crate::NamedTempFile<F> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().as_file_mut()).poll_read(cx, buf)
+    }
+}
+
+impl<F: AsyncWrite + Unpin> AsyncWrite for crate::NamedTempFile<F> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(self.get_mut().as_file_mut()).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().as_file_mut()).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().as_file_mut()).poll_shutdown(cx)
+    }
+}
+
+impl<F: AsyncSeek + Unpin> AsyncSeek for crate::NamedTempFile<F> {
+    fn start_seek(self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(self.get_mut().as_file_mut()).start_seek(position)
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(self.get_mut().as_file_mut()).poll_complete(cx)
+    }
+}
+
+/// An async temporary directory.
+///
+/// This is the async counterpart of [`crate::TempDir`]. The directory and its contents are
+/// recursively deleted when the `TempDir` is dropped. When dropped from within a tokio runtime,
+/// the deletion is performed on the blocking thread pool so that removing a large directory tree
+/// doesn't block the async executor; otherwise, it happens immediately.
+///
+/// If you need to know when the directory has been deleted, or want to handle errors, use
+/// [`TempDir::close`] instead of dropping the directory.
+///
+/// # Examples
+///
+/// ```
+/// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+/// let dir = tempfile::tokio::TempDir::new().await?;
+/// tokio::fs::write(dir.path().join("my-temporary-note.txt"), b"Hello World!").await?;
+/// dir.close().await?;
+/// # Ok::<(), std::io::Error>(())
+/// # })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct TempDir {
+    // Only `None` after the directory has been consumed (or while dropping).
+    inner: Option<crate::TempDir>,
+}
+
+impl fmt::Debug for TempDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TempDir")
+            .field("path", &self.path())
+            .finish()
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl TempDir {
+    /// Attempts to make a temporary directory inside of [`env::temp_dir()`].
+    ///
+    /// See [`crate::TempDir::new`] for details.
+    pub async fn new() -> io::Result<TempDir> {
+        TempDir::new_in(env::temp_dir()).await
+    }
+
+    /// Attempts to make a temporary directory inside of `dir`.
+    ///
+    /// See [`crate::TempDir::new_in`] for details.
+    pub async fn new_in<P: AsRef<Path>>(dir: P) -> io::Result<TempDir> {
+        let dir = dir.as_ref().to_owned();
+        let inner = asyncify(move || crate::TempDir::new_in(dir)).await?;
+        Ok(TempDir { inner: Some(inner) })
+    }
+
+    fn inner(&self) -> &crate::TempDir {
+        self.inner
+            .as_ref()
+            .expect("temporary directory already consumed")
+    }
+
+    fn into_inner(mut self) -> crate::TempDir {
+        self.inner
+            .take()
+            .expect("temporary directory already consumed")
+    }
+
+    /// Accesses the [`Path`] to the temporary directory.
+    pub fn path(&self) -> &Path {
+        self.inner().path()
+    }
+
+    /// Persist the temporary directory to disk, returning the [`PathBuf`] where it is located.
+    ///
+    /// See [`crate::TempDir::into_path`] for details.
+    pub fn into_path(self) -> PathBuf {
+        self.into_inner().into_path()
+    }
+
+    /// Closes and removes the temporary directory, returning a `Result`.
+    ///
+    /// The directory is removed on tokio's blocking thread pool. See [`crate::TempDir::close`]
+    /// for details.
+    pub async fn close(self) -> io::Result<()> {
+        let inner = self.into_inner();
+        asyncify(move || inner.close()).await
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            // If the blocking task never runs, the directory is deleted when the task is dropped.
+            if let Ok(handle) = Handle::try_current() {
+                handle.spawn_blocking(move || drop(inner));
+            }
+        }
+    }
+}
+
+/// The result of rolling a spooled temporary file over to disk. On failure, the buffer is returned
+/// along with the error.
+type RollResult = Result<std::fs::File, (Cursor<Vec<u8>>, io::Error)>;
+
+#[derive(Debug)]
+enum SpooledState {
+    InMemory(Cursor<Vec<u8>>),
+    Rolling {
+        task: JoinHandle<RollResult>,
+        seek: Option<io::SeekFrom>,
+    },
+    OnDisk(File),
+    /// The rollover task was cancelled (e.g., because the runtime shut down), taking the data
+    /// with it.
+    Failed,
+}
+
+/// An async spooled temporary file.
+///
+/// This is the async counterpart of [`crate::SpooledTempFile`]: data is kept in memory until it
+/// exceeds `max_size`, at which point it's written to an anonymous temporary file on disk. Unlike
+/// the blocking version, rolling over to disk (creating the file and writing out the buffer) is
+/// performed on tokio's blocking thread pool, so a large spill doesn't stall the async task.
+///
+/// # Examples
+///
+/// ```
+/// use tokio::io::AsyncWriteExt;
+///
+/// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+/// let mut file = tempfile::tokio::SpooledTempFile::new(15);
+/// file.write_all(b"short line\n").await?;
+/// assert!(!file.is_rolled());
+///
+/// file.write_all(b"marvin gardens\n").await?;
+/// assert!(file.is_rolled());
+/// # Ok::<(), std::io::Error>(())
+/// # })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SpooledTempFile {
+    max_size: usize,
+    state: SpooledState,
+}
+
+impl SpooledTempFile {
+    /// Create a new async spooled temporary file.
+    ///
+    /// See [`crate::SpooledTempFile::new`] for details.
+    #[must_use]
+    pub fn new(max_size: usize) -> SpooledTempFile {
+        SpooledTempFile {
+            max_size,
+            state: SpooledState::InMemory(Cursor::new(Vec::new())),
+        }
+    }
+
+    /// Returns true if the file has been rolled over to disk.
+    ///
+    /// A file that is in the process of rolling over, or whose rollover was cancelled, counts as
+    /// rolled over.
+    #[must_use]
+    pub fn is_rolled(&self) -> bool {
+        !matches!(self.state, SpooledState::InMemory(_))
+    }
+
+    /// Returns the buffered contents, or `None` if the file has been rolled over to disk.
+    #[must_use]
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        match &self.state {
+            SpooledState::InMemory(cursor) => Some(cursor.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Rolls over to a file on disk, regardless of current size. Does nothing if already rolled
+    /// over.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be created or written, `Err` is returned and the data remains in memory.
+    /// If the rollover is cancelled (e.g., because the runtime shuts down), the data is lost and
+    /// this and all further operations on the file fail.
+    pub async fn roll(&mut self) -> io::Result<()> {
+        loop {
+            match &mut self.state {
+                SpooledState::InMemory(_) => self.start_roll(),
+                SpooledState::Rolling { task, .. } => {
+                    let result = task.await;
+                    self.finish_roll(result)?;
+                }
+                SpooledState::OnDisk(_) => return Ok(()),
+                SpooledState::Failed => return Err(rollover_cancelled()),
+            }
+        }
+    }
+
+    /// Start rolling over to disk on the blocking thread pool if still in memory.
+    fn start_roll(&mut self) {
+        let mut cursor = match &mut self.state {
+            SpooledState::InMemory(cursor) => mem::take(cursor),
+            _ => return,
+        };
+        let task = task::spawn_blocking(move || {
+            let spill = || {
+                let mut file = crate::tempfile()?;
+                file.write_all(cursor.get_ref())?;
+                file.seek(io::SeekFrom::Start(cursor.position()))?;
+                Ok(file)
+            };
+            spill().map_err(|e| (mem::take(&mut cursor), e))
+        });
+        self.state = SpooledState::Rolling { task, seek: None };
+    }
+
+    fn finish_roll(&mut self, result: Result<RollResult, JoinError>) -> io::Result<()> {
+        let seek = match &mut self.state {
+            SpooledState::Rolling { seek, .. } => seek.take(),
+            _ => None,
+        };
+        match result {
+            Ok(Ok(file)) => {
+                let mut file = File::from_std(file);
+                // Replay a seek started while rolling over. It's completed by `poll_complete`.
+                let started = match seek {
+                    Some(pos) => Pin::new(&mut file).start_seek(pos),
+                    None => Ok(()),
+                };
+                self.state = SpooledState::OnDisk(file);
+                started
+            }
+            Ok(Err((mut cursor, e))) => {
+                // The seek was accepted by `start_seek`, so apply it to the buffer instead.
+                if let Some(pos) = seek {
+                    let _ = cursor.seek(pos);
+                }
+                self.state = SpooledState::InMemory(cursor);
+                Err(e)
+            }
+            Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+            Err(e) => {
+                self.state = SpooledState::Failed;
+                Err(io::Error::new(io::ErrorKind::Other, e))
+            }
+        }
+    }
+
+    /// Drive an in-progress rollover to completion.
+    fn poll_roll(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let SpooledState::Rolling { task, .. } = &mut self.state {
+            let result = match Pin::new(task).poll(cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => return Poll::Pending,
+            };
+            self.finish_roll(result)?;
+        }
+        if let SpooledState::Failed = self.state {
+            return Poll::Ready(Err(rollover_cancelled()));
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn rollover_cancelled() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        "spooled temporary file lost its contents: rolling over to disk was cancelled",
+    )
+}
+
+/// Return early from a poll function unless `$e` is `Poll::Ready(Ok(_))`.
+macro_rules! ready_ok {
+    ($e:expr) => {
+        match $e {
+            Poll::Ready(Ok(v)) => v,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        }
+    };
+}
+
+impl AsyncRead for SpooledTempFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready_ok!(this.poll_roll(cx));
+        match &mut this.state {
+            SpooledState::InMemory(cursor) => Pin::new(cursor).poll_read(cx, buf),
+            SpooledState::OnDisk(file) => Pin::new(file).poll_read(cx, buf),
+            SpooledState::Rolling { .. } | SpooledState::Failed => {
+                unreachable!("rollover completed above")
+            }
+        }
+    }
+}
+
+impl AsyncWrite for SpooledTempFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if matches! {
+            &this.state, SpooledState::InMemory(cursor)
+            if cursor.position().saturating_add(buf.len() as u64) > this.max_size as u64
+        } {
+            this.start_roll();
+        }
+        ready_ok!(this.poll_roll(cx));
+        match &mut this.state {
+            SpooledState::InMemory(cursor) => Poll::Ready(Write::write(cursor, buf)),
+            SpooledState::OnDisk(file) => Pin::new(file).poll_write(cx, buf),
+            SpooledState::Rolling { .. } | SpooledState::Failed => {
+                unreachable!("rollover completed above")
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready_ok!(this.poll_roll(cx));
+        match &mut this.state {
+            SpooledState::InMemory(_) => Poll::Ready(Ok(())),
+            SpooledState::OnDisk(file) => Pin::new(file).poll_flush(cx),
+            SpooledState::Rolling { .. } | SpooledState::Failed => {
+                unreachable!("rollover completed above")
+            }
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready_ok!(this.poll_roll(cx));
+        match &mut this.state {
+            SpooledState::InMemory(_) => Poll::Ready(Ok(())),
+            SpooledState::OnDisk(file) => Pin::new(file).poll_shutdown(cx),
+            SpooledState::Rolling { .. } | SpooledState::Failed => {
+                unreachable!("rollover completed above")
+            }
+        }
+    }
+}
+
+impl AsyncSeek for SpooledTempFile {
+    fn start_seek(self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        match &mut self.get_mut().state {
+            SpooledState::InMemory(cursor) => cursor.seek(position).map(|_| ()),
+            // Seek once the rollover completes.
+            SpooledState::Rolling { seek, .. } => {
+                *seek = Some(position);
+                Ok(())
+            }
+            SpooledState::OnDisk(file) => Pin::new(file).start_seek(position),
+            SpooledState::Failed => Err(rollover_cancelled()),
+        }
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        ready_ok!(this.poll_roll(cx));
+        match &mut this.state {
+            SpooledState::InMemory(cursor) => Poll::Ready(Ok(cursor.position())),
+            SpooledState::OnDisk(file) => Pin::new(file).poll_complete(cx),
+            SpooledState::Rolling { .. } | SpooledState::Failed => {
+                unreachable!("rollover completed above")
+            }
+        }
+    }
+}
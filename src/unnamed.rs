@@ -0,0 +1,233 @@
+//! Anonymous temporary files that can later be given a name (Linux only).
+
+use std::error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::path::Path;
+
+use rustix::fs::{linkat, AtFlags, OFlags, CWD};
+use rustix::io::Errno;
+
+use crate::error::IoResultExt;
+use crate::{env, util};
+
+/// An anonymous temporary file that can be atomically given a name once it's complete.
+///
+/// Like [`tempfile()`](crate::tempfile()), the file is created with `O_TMPFILE` and has no name:
+/// if the process exits before the file is published, there's nothing to clean up. Unlike
+/// `tempfile()`, `UnnamedTempFile` never falls back on creating and immediately deleting a named
+/// file, as such a file can't be linked back into the filesystem. Creation will therefore fail on
+/// filesystems that don't support `O_TMPFILE`.
+///
+/// This enables the "write anonymously, publish atomically on success" pattern without ever
+/// having a named temporary file that must be deleted.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::Write;
+/// use tempfile::UnnamedTempFile;
+///
+/// let mut file = UnnamedTempFile::new_in(".")?;
+/// writeln!(file, "Brian was here. Briefly.")?;
+/// file.persist("./saved_file.txt")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct UnnamedTempFile {
+    file: File,
+}
+
+impl UnnamedTempFile {
+    /// Create a new anonymous temporary file in [`env::temp_dir()`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created (including because the filesystem doesn't support
+    /// `O_TMPFILE`), `Err` is returned.
+    pub fn new() -> io::Result<UnnamedTempFile> {
+        UnnamedTempFile::new_in(env::temp_dir())
+    }
+
+    /// Create a new anonymous temporary file in the specified directory.
+    ///
+    /// The file can only be given a name on the same filesystem as `dir`.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created (including because the filesystem doesn't support
+    /// `O_TMPFILE`), `Err` is returned.
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> io::Result<UnnamedTempFile> {
+        let dir = dir.as_ref();
+        // Don't pass `O_EXCL`, that would prevent us from linking the file later.
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .mode(0o600)
+            .custom_flags(OFlags::TMPFILE.bits() as i32)
+            .open(dir)
+            .with_err_path(|| dir)
+            .map(|file| UnnamedTempFile { file })
+    }
+
+    /// Give the file a name, failing if `path` already exists.
+    ///
+    /// The file remains open and can be linked at multiple paths.
+    ///
+    /// # Errors
+    ///
+    /// If `path` already exists or is on a different filesystem, `Err` is returned.
+    pub fn link_at<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        // `AT_EMPTY_PATH` requires `CAP_DAC_READ_SEARCH` on older kernels; going through `/proc`
+        // doesn't.
+        match linkat(&self.file, "", CWD, path, AtFlags::EMPTY_PATH) {
+            Err(Errno::NOENT) | Err(Errno::PERM) => {
+                let proc_path = format!("/proc/self/fd/{}", self.file.as_raw_fd());
+                linkat(CWD, proc_path, CWD, path, AtFlags::SYMLINK_FOLLOW)
+            }
+            result => result,
+        }
+        .map_err(io::Error::from)
+        .with_err_path(|| path)
+    }
+
+    /// Give the file a name, atomically replacing `path` if it already exists, and return the
+    /// underlying file.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be linked into `path`'s directory or renamed over `path`, `Err` is
+    /// returned. The `UnnamedTempFile` is returned in the [`UnnamedPersistError`] so that it isn't
+    /// lost.
+    pub fn persist<P: AsRef<Path>>(self, path: P) -> Result<File, UnnamedPersistError> {
+        match self.persist_inner(path.as_ref()) {
+            Ok(()) => Ok(self.file),
+            Err(error) => Err(UnnamedPersistError { error, file: self }),
+        }
+    }
+
+    fn persist_inner(&self, path: &Path) -> io::Result<()> {
+        let dir = match path.parent() {
+            Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+            Some(parent) => parent,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "target path has no parent directory",
+                ))
+                .with_err_path(|| path)
+            }
+        };
+        // Links can't replace existing files so link the file at a temporary name first.
+        let tmp_path = util::create_helper(
+            dir,
+            OsStr::new(".tmp"),
+            OsStr::new(""),
+            crate::NUM_RAND_CHARS,
+            |tmp_path| self.link_at(&tmp_path).map(|_| tmp_path),
+        )?;
+        if let Err(e) = fs::rename(&tmp_path, path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e).with_err_path(|| path);
+        }
+        Ok(())
+    }
+
+    /// Get a reference to the underlying file.
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Get a mutable reference to the underlying file.
+    pub fn as_file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Convert the `UnnamedTempFile` into a `File`, giving up the ability to name it.
+    pub fn into_file(self) -> File {
+        self.file
+    }
+}
+
+impl Read for UnnamedTempFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for UnnamedTempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for UnnamedTempFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl AsFd for UnnamedTempFile {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl AsRawFd for UnnamedTempFile {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+/// Error returned when persisting an [`UnnamedTempFile`] fails.
+pub struct UnnamedPersistError {
+    /// The underlying IO error.
+    pub error: io::Error,
+    /// The temporary file that couldn't be persisted.
+    pub file: UnnamedTempFile,
+}
+
+impl fmt::Debug for UnnamedPersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UnnamedPersistError({:?})", self.error)
+    }
+}
+
+impl From<UnnamedPersistError> for io::Error {
+    #[inline]
+    fn from(error: UnnamedPersistError) -> io::Error {
+        error.error
+    }
+}
+
+impl From<UnnamedPersistError> for UnnamedTempFile {
+    #[inline]
+    fn from(error: UnnamedPersistError) -> UnnamedTempFile {
+        error.file
+    }
+}
+
+impl fmt::Display for UnnamedPersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to persist unnamed temporary file: {}",
+            self.error
+        )
+    }
+}
+
+impl error::Error for UnnamedPersistError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
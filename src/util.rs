@@ -1,22 +1,56 @@
+use std::borrow::Cow;
 use std::ffi::{OsStr, OsString};
-use std::path::{Path, PathBuf};
-use std::{io, iter::repeat_with};
+use std::future::Future;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 
-use crate::error::IoResultExt;
+use crate::error::{is_name_too_long, Error};
+use crate::name::{Alphanumeric, NameGenerator};
 
-fn tmpname(prefix: &OsStr, suffix: &OsStr, rand_len: usize) -> OsString {
+fn tmpname(
+    prefix: &OsStr,
+    suffix: &OsStr,
+    rand_len: usize,
+    generator: &dyn NameGenerator,
+) -> io::Result<OsString> {
+    let unique = generator.generate(rand_len);
     let capacity = prefix
         .len()
         .saturating_add(suffix.len())
-        .saturating_add(rand_len);
+        .saturating_add(unique.len());
     let mut buf = OsString::with_capacity(capacity);
     buf.push(prefix);
-    let mut char_buf = [0u8; 4];
-    for c in repeat_with(fastrand::alphanumeric).take(rand_len) {
-        buf.push(c.encode_utf8(&mut char_buf));
-    }
+    buf.push(&unique);
     buf.push(suffix);
-    buf
+    if !is_name_part(&unique) || buf == "." || buf == ".." {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("name generator returned an invalid name: {:?}", unique),
+        ));
+    }
+    Ok(buf)
+}
+
+/// Returns `true` if `unique` can be part of a file name, i.e., it's empty or a single normal path
+/// component without any separators.
+fn is_name_part(unique: &OsStr) -> bool {
+    let mut components = Path::new(unique).components();
+    match (components.next(), components.next()) {
+        (None, _) => true,
+        (Some(Component::Normal(name)), None) => name == unique,
+        _ => false,
+    }
+}
+
+/// Returns `path` relative to the current directory, if it isn't already absolute.
+pub fn absolute(path: &Path) -> io::Result<Cow<'_, Path>> {
+    if path.is_absolute() {
+        Ok(Cow::Borrowed(path))
+    } else {
+        Ok(Cow::Owned(std::env::current_dir()?.join(path)))
+    }
 }
 
 pub fn create_helper<R>(
@@ -24,46 +58,233 @@ pub fn create_helper<R>(
     prefix: &OsStr,
     suffix: &OsStr,
     random_len: usize,
+    f: impl FnMut(PathBuf) -> io::Result<R>,
+) -> io::Result<R> {
+    create_helper_with(
+        base,
+        prefix,
+        suffix,
+        random_len,
+        &Alphanumeric,
+        RetryPolicy::default(),
+        f,
+    )
+}
+
+/// How often (and how quickly) to retry when a temporary name is already taken.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct RetryPolicy {
+    /// The maximum number of retries after the first attempt, or `None` for the default.
+    pub(crate) max_retries: Option<u32>,
+    /// How long to wait before each retry.
+    pub(crate) backoff: Duration,
+    /// Decides whether errors besides the default ones should be retried with a new name.
+    pub(crate) retry_on: Option<fn(&io::Error) -> bool>,
+}
+
+impl PartialEq for RetryPolicy {
+    fn eq(&self, other: &Self) -> bool {
+        // Like the builder's other hooks, `retry_on` is compared by address.
+        self.max_retries == other.max_retries
+            && self.backoff == other.backoff
+            && self.retry_on.map(|f| f as usize) == other.retry_on.map(|f| f as usize)
+    }
+}
+
+impl Eq for RetryPolicy {}
+
+/// The number of attempts to make by default when retrying errors accepted by
+/// [`crate::Builder::retry_on`].
+const RETRY_ON_ATTEMPTS: u32 = 8;
+
+impl RetryPolicy {
+    /// The total number of attempts to make, given the default.
+    pub(crate) fn attempts(&self, default: u32) -> u32 {
+        self.max_retries
+            .map_or(default, |retries| retries.saturating_add(1))
+    }
+
+    /// Returns `true` if the given (zero-based) attempt out of `num_attempts` to create a
+    /// temporary file failed with `e` because the name is taken, and should be retried.
+    pub(crate) fn should_retry(&self, e: &io::Error, attempt: u32, num_attempts: u32) -> bool {
+        match e.kind() {
+            io::ErrorKind::AlreadyExists => num_attempts > 1,
+            // AddrInUse can happen if we're creating a UNIX domain socket and
+            // the path already exists.
+            io::ErrorKind::AddrInUse => num_attempts > 1,
+            _ => {
+                // These errors may not go away, so don't keep retrying unless asked to.
+                let num_attempts = match self.max_retries {
+                    Some(_) => num_attempts,
+                    None => num_attempts.min(RETRY_ON_ATTEMPTS),
+                };
+                attempt + 1 < num_attempts && self.retry_on.map_or(false, |retry_on| retry_on(e))
+            }
+        }
+    }
+
+    /// Wait before making the given (zero-based) attempt.
+    pub(crate) fn wait(&self, attempt: u32) {
+        if attempt > 0 && !self.backoff.is_zero() {
+            thread::sleep(self.backoff);
+        }
+    }
+}
+
+/// Like [`create_helper`], but generating names with the given [`NameGenerator`] and retrying
+/// according to the given [`RetryPolicy`].
+pub fn create_helper_with<R>(
+    base: &Path,
+    prefix: &OsStr,
+    suffix: &OsStr,
+    random_len: usize,
+    generator: &dyn NameGenerator,
+    retry: RetryPolicy,
     mut f: impl FnMut(PathBuf) -> io::Result<R>,
 ) -> io::Result<R> {
-    let num_retries = if random_len != 0 {
+    let random_len = fit_name(base, prefix, suffix, random_len)?;
+    let num_retries = retry.attempts(num_retries(random_len));
+    let mut last_attempt = PathBuf::new();
+
+    for i in 0..num_retries {
+        retry.wait(i);
+        maybe_reseed(i);
+        let path = base.join(tmpname(prefix, suffix, random_len, generator)?);
+        last_attempt.clone_from(&path);
+        return match f(path) {
+            Err(ref e) if retry.should_retry(e, i, num_retries) => continue,
+            res => res.map_err(|e| creation_error(e, base, &last_attempt)),
+        };
+    }
+
+    Err(too_many_retries(base, num_retries, last_attempt))
+}
+
+/// The async equivalent of [`create_helper_with`]. The retry backoff is ignored as we can't sleep
+/// without blocking the executor.
+pub async fn create_helper_async<R, Fut>(
+    base: &Path,
+    prefix: &OsStr,
+    suffix: &OsStr,
+    random_len: usize,
+    generator: &dyn NameGenerator,
+    retry: RetryPolicy,
+    mut f: impl FnMut(PathBuf) -> Fut,
+) -> io::Result<R>
+where
+    Fut: Future<Output = io::Result<R>>,
+{
+    let random_len = fit_name(base, prefix, suffix, random_len)?;
+    let num_retries = retry.attempts(num_retries(random_len));
+    let mut last_attempt = PathBuf::new();
+
+    for i in 0..num_retries {
+        maybe_reseed(i);
+        let path = base.join(tmpname(prefix, suffix, random_len, generator)?);
+        last_attempt.clone_from(&path);
+        return match f(path).await {
+            Err(ref e) if retry.should_retry(e, i, num_retries) => continue,
+            res => res.map_err(|e| creation_error(e, base, &last_attempt)),
+        };
+    }
+
+    Err(too_many_retries(base, num_retries, last_attempt))
+}
+
+fn num_retries(random_len: usize) -> u32 {
+    if random_len != 0 {
         crate::NUM_RETRIES
     } else {
         1
-    };
+    }
+}
 
-    for i in 0..num_retries {
-        // If we fail to create the file the first three times, re-seed from system randomness in
-        // case an attacker is predicting our randomness (fastrand is predictable). If re-seeding
-        // doesn't help, either:
-        //
-        // 1. We have lots of temporary files, possibly created by an attacker but not necessarily.
-        //    Re-seeding the randomness won't help here.
-        // 2. We're failing to create random files for some other reason. This shouldn't be the case
-        //    given that we're checking error kinds, but it could happen.
-        #[cfg(all(
-            feature = "getrandom",
-            any(windows, unix, target_os = "redox", target_os = "wasi")
-        ))]
-        if i == 3 {
-            let mut seed = [0u8; 8];
-            if getrandom::fill(&mut seed).is_ok() {
-                fastrand::seed(u64::from_ne_bytes(seed));
-            }
+#[allow(unused_variables)]
+fn maybe_reseed(i: u32) {
+    // If we fail to create the file the first three times, re-seed from system randomness in
+    // case an attacker is predicting our randomness (fastrand is predictable). If re-seeding
+    // doesn't help, either:
+    //
+    // 1. We have lots of temporary files, possibly created by an attacker but not necessarily.
+    //    Re-seeding the randomness won't help here.
+    // 2. We're failing to create random files for some other reason. This shouldn't be the case
+    //    given that we're checking error kinds, but it could happen.
+    #[cfg(all(
+        feature = "fastrand",
+        feature = "getrandom",
+        any(windows, unix, target_os = "redox", target_os = "wasi")
+    ))]
+    if i == 3 {
+        let mut seed = [0u8; 8];
+        if getrandom::fill(&mut seed).is_ok() {
+            fastrand::seed(u64::from_ne_bytes(seed));
         }
-        let path = base.join(tmpname(prefix, suffix, random_len));
-        return match f(path) {
-            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && num_retries > 1 => continue,
-            // AddrInUse can happen if we're creating a UNIX domain socket and
-            // the path already exists.
-            Err(ref e) if e.kind() == io::ErrorKind::AddrInUse && num_retries > 1 => continue,
-            res => res,
-        };
     }
+}
+
+pub(crate) fn too_many_retries(base: &Path, attempts: u32, last_attempt: PathBuf) -> io::Error {
+    Error::TooManyCollisions {
+        dir: base.to_owned(),
+        attempts,
+        last_attempt,
+    }
+    .into()
+}
+
+/// Report the error from creating `path` in `base` as an [`Error::TempDirNotFound`] if `base`
+/// doesn't exist, or as an [`Error::NameTooLong`] if the name is too long.
+pub(crate) fn creation_error(e: io::Error, base: &Path, path: &Path) -> io::Error {
+    if e.kind() == io::ErrorKind::NotFound && !base.exists() {
+        Error::TempDirNotFound {
+            dir: base.to_owned(),
+        }
+        .into()
+    } else if is_name_too_long(&e) {
+        Error::NameTooLong {
+            dir: base.to_owned(),
+            name: path.file_name().unwrap_or_default().to_owned(),
+        }
+        .into()
+    } else {
+        e
+    }
+}
+
+/// The maximum length of a file name on most filesystems, in bytes (UTF-16 code units on
+/// Windows).
+const NAME_MAX: usize = 255;
 
-    Err(io::Error::new(
-        io::ErrorKind::AlreadyExists,
-        "too many temporary files exist",
-    ))
-    .with_err_path(|| base)
+fn name_len(name: &OsStr) -> usize {
+    #[cfg(windows)]
+    return std::os::windows::ffi::OsStrExt::encode_wide(name).count();
+    #[cfg(not(windows))]
+    return name.len();
+}
+
+/// The random part of names isn't shortened below this many characters to fit in [`NAME_MAX`], as
+/// that would make collisions (and guessing) too likely.
+const MIN_RANDOM_LEN: usize = 6;
+
+/// Shrink the random part of names to fit in [`NAME_MAX`], failing with [`Error::NameTooLong`] if
+/// the prefix and suffix alone don't leave room for at least [`MIN_RANDOM_LEN`] characters.
+pub(crate) fn fit_name(
+    base: &Path,
+    prefix: &OsStr,
+    suffix: &OsStr,
+    random_len: usize,
+) -> io::Result<usize> {
+    let fixed_len = name_len(prefix) + name_len(suffix);
+    if fixed_len.saturating_add(random_len) <= NAME_MAX {
+        Ok(random_len)
+    } else if fixed_len + MIN_RANDOM_LEN <= NAME_MAX {
+        Ok(NAME_MAX - fixed_len)
+    } else {
+        let mut name = prefix.to_owned();
+        name.push(suffix);
+        Err(Error::NameTooLong {
+            dir: base.to_owned(),
+            name,
+        }
+        .into())
+    }
 }
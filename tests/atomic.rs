@@ -0,0 +1,165 @@
+#![deny(rust_2018_idioms)]
+
+use std::fs;
+use std::io::{Read, Write};
+
+use tempfile::{atomic_copy, persist_all, tempdir, write_atomic, AtomicWriteFile, NamedTempFile};
+
+#[test]
+fn test_atomic_copy() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("src");
+    let dst = dir.path().join("dst");
+    fs::write(&src, b"abcde").unwrap();
+
+    assert_eq!(atomic_copy(&src, &dst).unwrap(), 5);
+    assert_eq!(fs::read(&dst).unwrap(), b"abcde");
+
+    // Replace an existing file.
+    fs::write(&src, b"fghij").unwrap();
+    assert_eq!(atomic_copy(&src, &dst).unwrap(), 5);
+    assert_eq!(fs::read(&dst).unwrap(), b"fghij");
+
+    // No temporary files should be left behind.
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_atomic_copy_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("src");
+    let dst = dir.path().join("dst");
+    fs::write(&src, b"abcde").unwrap();
+    fs::set_permissions(&src, fs::Permissions::from_mode(0o640)).unwrap();
+
+    atomic_copy(&src, &dst).unwrap();
+    let mode = fs::metadata(&dst).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+}
+
+#[test]
+fn test_atomic_copy_missing_source() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("src");
+    let dst = dir.path().join("dst");
+
+    atomic_copy(&src, &dst).unwrap_err();
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_write_atomic() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    write_atomic(&path, b"abcde").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"abcde");
+
+    // Replace an existing file.
+    write_atomic(&path, "fghij").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"fghij");
+
+    // No temporary files should be left behind.
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_atomic_preserves_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("file");
+    fs::write(&path, b"abcde").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+    write_atomic(&path, b"fghij").unwrap();
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+}
+
+#[test]
+fn test_atomic_write_file() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("file");
+    fs::write(&path, b"old").unwrap();
+
+    let mut file = AtomicWriteFile::open(&path).unwrap();
+    assert_eq!(file.target(), path);
+    file.write_all(b"new").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"old");
+    file.commit().unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"new");
+
+    // Dropping or discarding leaves the target untouched.
+    let mut file = AtomicWriteFile::open(&path).unwrap();
+    file.write_all(b"dropped").unwrap();
+    drop(file);
+    let mut file = AtomicWriteFile::open(&path).unwrap();
+    file.sync_on_commit(false);
+    file.write_all(b"discarded").unwrap();
+    file.discard().unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"new");
+
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_persist_all() {
+    let dir = tempdir().unwrap();
+    let mut a = NamedTempFile::new_in(&dir).unwrap();
+    let mut b = NamedTempFile::new_in(&dir).unwrap();
+    write!(a, "a").unwrap();
+    write!(b, "b").unwrap();
+    let files = persist_all([(a, dir.path().join("a")), (b, dir.path().join("b"))]).unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(fs::read_to_string(dir.path().join("a")).unwrap(), "a");
+    assert_eq!(fs::read_to_string(dir.path().join("b")).unwrap(), "b");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+}
+
+#[test]
+fn test_persist_all_rollback() {
+    let dir = tempdir().unwrap();
+    let existing = dir.path().join("existing");
+    let new = dir.path().join("new");
+    let blocked = dir.path().join("blocked");
+    fs::write(&existing, "old").unwrap();
+    // A non-empty directory can't be replaced by a file.
+    fs::create_dir(&blocked).unwrap();
+    fs::write(blocked.join("file"), "").unwrap();
+
+    let unprocessed = dir.path().join("unprocessed");
+
+    let mut files = Vec::new();
+    for target in [&existing, &new, &blocked, &unprocessed] {
+        let mut file = NamedTempFile::new_in(&dir).unwrap();
+        write!(file, "new").unwrap();
+        files.push((file, target.clone()));
+    }
+    let err = persist_all(files).unwrap_err();
+    assert_eq!(err.target(), blocked);
+    assert_eq!(err.persisted(), [existing.clone(), new.clone()]);
+    assert!(err.rollback_failures().is_empty());
+
+    // The files that weren't persisted are returned.
+    let files = err.into_files();
+    let targets: Vec<_> = files.iter().map(|(_, target)| target.clone()).collect();
+    assert_eq!(targets, [blocked, unprocessed]);
+    for (file, _) in files {
+        let mut contents = String::new();
+        file.reopen()
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "new");
+    }
+
+    // Everything is back to how it was.
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "old");
+    assert!(!new.exists());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+}
@@ -0,0 +1,162 @@
+#![deny(rust_2018_idioms)]
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tempfile::{Builder, TempBackend};
+
+static EVENTS: Mutex<Vec<(&'static str, PathBuf)>> = Mutex::new(Vec::new());
+
+#[derive(Debug)]
+struct Recording;
+
+impl TempBackend for Recording {
+    fn persist(&self, old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<()> {
+        EVENTS
+            .lock()
+            .unwrap()
+            .push(("persist", new_path.to_owned()));
+        tempfile::OsBackend.persist(old_path, new_path, overwrite)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        EVENTS
+            .lock()
+            .unwrap()
+            .push(("remove_file", path.to_owned()));
+        std::fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        EVENTS
+            .lock()
+            .unwrap()
+            .push(("remove_dir_all", path.to_owned()));
+        std::fs::remove_dir_all(path)
+    }
+}
+
+static RECORDING: Recording = Recording;
+
+#[derive(Debug)]
+struct ReadOnly;
+
+impl TempBackend for ReadOnly {
+    fn create_file(
+        &self,
+        _: &Path,
+        _: &mut std::fs::OpenOptions,
+        _: Option<&std::fs::Permissions>,
+    ) -> io::Result<std::fs::File> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "read-only"))
+    }
+}
+
+static READ_ONLY: ReadOnly = ReadOnly;
+
+static REMOVED_CONTENTS: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+#[derive(Debug)]
+struct Snapshot;
+
+impl TempBackend for Snapshot {
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        REMOVED_CONTENTS.lock().unwrap().push(std::fs::read(path)?);
+        std::fs::remove_file(path)
+    }
+}
+
+static SNAPSHOT: Snapshot = Snapshot;
+
+#[test]
+fn test_backend() {
+    let tmpdir = tempfile::tempdir().unwrap();
+
+    let mut file = Builder::new()
+        .backend(&RECORDING)
+        .tempfile_in(tmpdir.path())
+        .unwrap();
+    write!(file, "abcde").unwrap();
+    let removed = file.path().to_owned();
+    drop(file);
+    assert!(!removed.exists());
+
+    let file = Builder::new()
+        .backend(&RECORDING)
+        .tempfile_in(tmpdir.path())
+        .unwrap();
+    let persisted = tmpdir.path().join("persisted");
+    file.persist(&persisted).unwrap();
+    assert!(persisted.exists());
+
+    let dir = Builder::new()
+        .backend(&RECORDING)
+        .tempdir_in(tmpdir.path())
+        .unwrap();
+    let dir_path = dir.path().to_owned();
+    dir.close().unwrap();
+    assert!(!dir_path.exists());
+
+    assert_eq!(
+        *EVENTS.lock().unwrap(),
+        [
+            ("remove_file", removed),
+            ("persist", persisted),
+            ("remove_dir_all", dir_path),
+        ]
+    );
+
+    let err = Builder::new()
+        .backend(&READ_ONLY)
+        .tempfile_in(tmpdir.path())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn test_unwind_safe() {
+    fn assert_unwind_safe<T: std::panic::UnwindSafe + std::panic::RefUnwindSafe>() {}
+    assert_unwind_safe::<tempfile::TempDir>();
+    assert_unwind_safe::<tempfile::TempPath>();
+    assert_unwind_safe::<tempfile::NamedTempFile>();
+}
+
+#[test]
+fn test_builder_eq() {
+    let mut a = Builder::new();
+    let mut b = Builder::new();
+    a.backend(&RECORDING);
+    b.backend(&RECORDING);
+    assert_eq!(a, b);
+    b.backend(&READ_ONLY);
+    assert_ne!(a, b);
+}
+
+#[test]
+fn test_wipe_on_drop_append() {
+    let tmpdir = tempfile::tempdir().unwrap();
+
+    let mut file = Builder::new()
+        .append(true)
+        .wipe_on_drop(true)
+        .backend(&SNAPSHOT)
+        .tempfile_in(tmpdir.path())
+        .unwrap();
+    file.write_all(b"secret").unwrap();
+    drop(file);
+
+    let mut file = Builder::new()
+        .open_options(|options| {
+            options.append(true);
+        })
+        .wipe_on_drop(true)
+        .backend(&SNAPSHOT)
+        .tempfile_in(tmpdir.path())
+        .unwrap();
+    file.write_all(b"secret").unwrap();
+    file.close().unwrap();
+
+    // The contents were overwritten in place before the files were deleted.
+    assert_eq!(*REMOVED_CONTENTS.lock().unwrap(), [[0; 6], [0; 6]]);
+}
@@ -0,0 +1,35 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "debug-registry")]
+
+use std::path::Path;
+
+use tempfile::debug::live_resources;
+use tempfile::{tempdir, NamedTempFile};
+
+fn is_live(path: &Path) -> bool {
+    live_resources().iter().any(|r| r.path() == path)
+}
+
+#[test]
+fn test_live_resources() {
+    let dir = tempdir().unwrap();
+    let file = NamedTempFile::new_in(&dir).unwrap();
+    let resources = live_resources();
+    assert!(resources
+        .iter()
+        .any(|r| r.path() == dir.path() && r.is_dir()));
+    assert!(resources
+        .iter()
+        .any(|r| r.path() == file.path() && !r.is_dir()));
+
+    // Persisted and deleted resources are no longer listed.
+    let file_path = file.path().to_owned();
+    let persisted = dir.path().join("persisted");
+    file.persist(&persisted).unwrap();
+    assert!(!is_live(&file_path));
+    assert!(!is_live(&persisted));
+
+    let dir_path = dir.path().to_owned();
+    dir.close().unwrap();
+    assert!(!is_live(&dir_path));
+}
@@ -0,0 +1,94 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "encryption")]
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use tempfile::{tempdir, EncryptedTempFile};
+
+#[test]
+fn test_roundtrip() {
+    let dir = tempdir().unwrap();
+    let mut file = EncryptedTempFile::new_in(dir.path()).unwrap();
+    let data: Vec<u8> = (0..20000u32).map(|i| i as u8).collect();
+    file.write_all(&data).unwrap();
+    assert_eq!(file.as_inner().metadata().unwrap().len(), 20000);
+
+    // Overwrite in the middle.
+    file.seek(SeekFrom::Start(10000)).unwrap();
+    file.write_all(b"hello").unwrap();
+
+    let mut buf = Vec::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_end(&mut buf).unwrap();
+    assert_eq!(&buf[..10000], &data[..10000]);
+    assert_eq!(&buf[10000..10005], b"hello");
+    assert_eq!(&buf[10005..], &data[10005..]);
+
+    let mut buf = [0u8; 5];
+    file.seek(SeekFrom::End(-5)).unwrap();
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, data[19995..]);
+
+    // Gaps left by seeking past the end read back as zeros.
+    file.seek(SeekFrom::Start(30000)).unwrap();
+    file.write_all(b"end").unwrap();
+    assert_eq!(file.as_inner().metadata().unwrap().len(), 30003);
+    let mut buf = Vec::new();
+    file.seek(SeekFrom::Start(20000)).unwrap();
+    file.read_to_end(&mut buf).unwrap();
+    assert!(buf[..10000].iter().all(|&b| b == 0));
+    assert_eq!(&buf[10000..], b"end");
+}
+
+#[test]
+fn test_spooled() {
+    let mut file = EncryptedTempFile::spooled(10).unwrap();
+    file.write_all(b"secret").unwrap();
+    assert!(!file.as_inner().is_rolled());
+    let ciphertext = file.as_inner().as_slice().unwrap().to_vec();
+    assert_eq!(ciphertext.len(), 6);
+    assert_ne!(ciphertext, b"secret");
+
+    // Keys are unique per file.
+    let mut other = EncryptedTempFile::spooled(10).unwrap();
+    other.write_all(b"secret").unwrap();
+    assert_ne!(other.as_inner().as_slice().unwrap(), ciphertext);
+
+    // Rolling over keeps the ciphertext.
+    file.write_all(b" stuff").unwrap();
+    assert!(file.as_inner().is_rolled());
+    let mut buf = String::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "secret stuff");
+}
+
+#[test]
+fn test_overwrite_fresh_keystream() {
+    let mut file = EncryptedTempFile::spooled(1 << 16).unwrap();
+    file.write_all(&[0u8; 10000]).unwrap();
+    let before = file.as_inner().as_slice().unwrap().to_vec();
+
+    // Rewriting the same data must not produce the same ciphertext in the overwritten chunk.
+    file.seek(SeekFrom::Start(100)).unwrap();
+    file.write_all(&[0u8; 10]).unwrap();
+    let after = file.as_inner().as_slice().unwrap().to_vec();
+    assert_ne!(after[..8192], before[..8192]);
+    assert_eq!(after[8192..], before[8192..]);
+
+    // Overwrites spanning chunks and extending the file.
+    file.seek(SeekFrom::Start(8190)).unwrap();
+    file.write_all(b"abcd").unwrap();
+    file.seek(SeekFrom::Start(9998)).unwrap();
+    file.write_all(b"wxyz").unwrap();
+
+    let mut buf = Vec::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf.len(), 10002);
+    assert_eq!(&buf[8190..8194], b"abcd");
+    assert_eq!(&buf[9998..], b"wxyz");
+    buf[8190..8194].fill(0);
+    buf[9998..].fill(0);
+    assert!(buf.iter().all(|&b| b == 0));
+}
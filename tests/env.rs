@@ -13,3 +13,57 @@ fn test_override_temp_dir() {
     let new_tmp2 = Path::new("/tmp/override2");
     tempfile::env::override_temp_dir(new_tmp2).expect_err("override should only be possible once");
 }
+
+#[test]
+fn test_with_temp_dir() {
+    // Don't depend on the global override set by `test_override_temp_dir`.
+    let outer = tempfile::tempdir_in(std::env::temp_dir()).unwrap();
+    let inner = tempfile::tempdir_in(std::env::temp_dir()).unwrap();
+
+    tempfile::env::with_temp_dir(outer.path(), || {
+        assert_eq!(tempfile::env::temp_dir(), outer.path());
+        let file = tempfile::NamedTempFile::new().unwrap();
+        assert!(file.path().starts_with(outer.path()));
+
+        tempfile::env::with_temp_dir(inner.path(), || {
+            assert_eq!(tempfile::env::temp_dir(), inner.path());
+        });
+        assert_eq!(tempfile::env::temp_dir(), outer.path());
+
+        // Other threads are unaffected.
+        let other = std::thread::spawn(tempfile::env::temp_dir).join().unwrap();
+        assert_ne!(other, outer.path());
+    });
+
+    assert_ne!(tempfile::env::temp_dir(), outer.path());
+}
+
+#[test]
+fn test_app_temp_dir() {
+    let tmp = tempfile::tempdir_in(std::env::temp_dir()).unwrap();
+    tempfile::env::with_temp_dir(tmp.path(), || {
+        let dir = tempfile::app_temp_dir("myapp").unwrap();
+        assert!(dir.starts_with(tmp.path()));
+        assert!(dir.is_dir());
+        // Calling it again returns the same directory.
+        assert_eq!(tempfile::app_temp_dir("myapp").unwrap(), dir);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let name = dir.file_name().unwrap().to_str().unwrap();
+            assert!(name.starts_with("myapp-"), "{}", name);
+            let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+
+            // Directories other users can access are rejected.
+            std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o777)).unwrap();
+            let err = tempfile::app_temp_dir("myapp").unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        }
+
+        let err = tempfile::app_temp_dir("../myapp").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    });
+}
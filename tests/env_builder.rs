@@ -0,0 +1,39 @@
+#![deny(rust_2018_idioms)]
+
+use tempfile::{env, tempdir, Builder, NamedTempFile};
+
+#[test]
+fn test_override_default_builder() {
+    let dir = tempdir().unwrap();
+
+    let mut defaults = Builder::new();
+    defaults.prefix("app-").suffix(".tmp").rand_bytes(4);
+    #[cfg(unix)]
+    defaults.mode(0o600);
+    env::override_default_builder(defaults).unwrap();
+
+    let tmpfile = NamedTempFile::new_in(dir.path()).unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("app-"), "{}", name);
+    assert!(name.ends_with(".tmp"), "{}", name);
+    assert_eq!(name.len(), "app-.tmp".len() + 4);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = tmpfile.as_file().metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // Explicit settings take precedence.
+    let tmpdir = Builder::new()
+        .prefix("mine-")
+        .tempdir_in(dir.path())
+        .unwrap();
+    let name = tmpdir.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("mine-"), "{}", name);
+    assert!(name.ends_with(".tmp"), "{}", name);
+
+    let err = env::override_default_builder(Builder::new())
+        .expect_err("override should only be possible once");
+    assert_eq!(*err, Builder::new());
+}
@@ -0,0 +1,27 @@
+#![deny(rust_2018_idioms)]
+
+use tempfile::{env, NamedTempFile};
+
+#[test]
+fn test_set_temp_dir_candidates() {
+    let root = tempfile::tempdir().unwrap();
+    let missing = root.path().join("missing");
+    let file = root.path().join("file");
+    std::fs::write(&file, b"").unwrap();
+    let usable = root.path().join("usable");
+    let later = root.path().join("later");
+    std::fs::create_dir(&usable).unwrap();
+    std::fs::create_dir(&later).unwrap();
+
+    env::set_temp_dir_candidates([&missing, &file, &usable, &later]).unwrap();
+    assert_eq!(env::temp_dir(), usable);
+    let tmpfile = NamedTempFile::new().unwrap();
+    assert!(tmpfile.path().starts_with(&usable));
+
+    // The choice is cached.
+    std::fs::create_dir(&missing).unwrap();
+    assert_eq!(env::temp_dir(), usable);
+
+    let err = env::set_temp_dir_candidates([&later]).unwrap_err();
+    assert_eq!(err, [missing, file, usable, later]);
+}
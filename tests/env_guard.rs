@@ -0,0 +1,30 @@
+#![deny(rust_2018_idioms)]
+
+use tempfile::{env, NamedTempFile};
+
+#[test]
+fn test_override_temp_dir_guarded() {
+    let original = env::temp_dir();
+    let outer = tempfile::tempdir().unwrap();
+    let inner = tempfile::tempdir().unwrap();
+
+    {
+        let _outer = env::override_temp_dir_guarded(outer.path());
+        assert_eq!(env::temp_dir(), outer.path());
+        {
+            let _inner = env::override_temp_dir_guarded(inner.path());
+            assert_eq!(env::temp_dir(), inner.path());
+            let file = NamedTempFile::new().unwrap();
+            assert!(file.path().starts_with(inner.path()));
+        }
+        assert_eq!(env::temp_dir(), outer.path());
+
+        // The override is global.
+        let other = std::thread::spawn(env::temp_dir).join().unwrap();
+        assert_eq!(other, outer.path());
+
+        // Thread-scoped overrides take precedence.
+        env::with_temp_dir(inner.path(), || assert_eq!(env::temp_dir(), inner.path()));
+    }
+    assert_eq!(env::temp_dir(), original);
+}
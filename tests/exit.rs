@@ -0,0 +1,66 @@
+#![deny(rust_2018_idioms)]
+#![cfg(any(unix, windows))]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+const CHILD_ENV: &str = "TEMPFILE_EXIT_TEST_DIR";
+
+// Re-executes the test binary; the child registers temporary files for cleanup and exits without
+// running destructors.
+#[test]
+fn test_cleanup_on_exit() {
+    if let Some(dir) = std::env::var_os(CHILD_ENV) {
+        child(PathBuf::from(dir));
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "test_cleanup_on_exit",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env(CHILD_ENV, dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+
+    // Only the persisted file (and the file only registered for cleanup on signal) remains.
+    let mut remaining: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    remaining.sort();
+    #[cfg(all(feature = "signal-cleanup", unix))]
+    {
+        assert!(remaining.pop().unwrap().starts_with("signal-only"));
+    }
+    assert_eq!(remaining, ["kept"]);
+}
+
+fn child(dir: PathBuf) -> ! {
+    let file = tempfile::NamedTempFile::new_in(&dir).unwrap();
+    file.cleanup_on_exit().unwrap();
+
+    let tmpdir = tempfile::TempDir::new_in(&dir).unwrap();
+    std::fs::write(tmpdir.path().join("file"), b"abcde").unwrap();
+    tmpdir.cleanup_on_exit().unwrap();
+
+    let kept = tempfile::NamedTempFile::new_in(&dir).unwrap();
+    kept.cleanup_on_exit().unwrap();
+    kept.persist(dir.join("kept")).unwrap();
+
+    #[cfg(all(feature = "signal-cleanup", unix))]
+    {
+        let signal_only = tempfile::Builder::new()
+            .prefix("signal-only")
+            .tempfile_in(&dir)
+            .unwrap();
+        signal_only.cleanup_on_signal().unwrap();
+        std::mem::forget(signal_only);
+    }
+
+    std::process::exit(0)
+}
@@ -0,0 +1,34 @@
+#![deny(rust_2018_idioms)]
+#![cfg(unix)]
+
+use tempfile::{tempdir, Builder};
+
+/// A forked child inherits its parent's name generator state, but mustn't generate the same names.
+#[test]
+fn test_fork_names() {
+    let dir = tempdir().unwrap();
+    let create = || {
+        Builder::new()
+            .max_retries(0)
+            .tempfile_in(&dir)
+            .and_then(|f| f.keep().map_err(|e| e.error))
+    };
+    // Make sure the generator state is initialized before forking.
+    create().unwrap();
+
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0, "fork failed");
+    if pid == 0 {
+        let ok = (0..100).all(|_| create().is_ok());
+        unsafe { libc::_exit(if ok { 0 } else { 1 }) };
+    }
+    let result: Vec<_> = (0..100).map(|_| create()).collect();
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+
+    for r in result {
+        r.unwrap();
+    }
+    assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 201);
+}
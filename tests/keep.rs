@@ -0,0 +1,43 @@
+#![deny(rust_2018_idioms)]
+
+use std::fs;
+
+use tempfile::{env, Builder, NamedTempFile, TempDir};
+
+// Lives in its own test binary because the debug mode is global.
+#[test]
+fn test_keep_tempfiles() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(!env::keep_tempfiles());
+
+    env::set_keep_tempfiles(true);
+    assert!(env::keep_tempfiles());
+
+    let file = NamedTempFile::new_in(dir.path()).unwrap();
+    let file_path = file.path().to_owned();
+    drop(file);
+    assert!(file_path.exists());
+
+    // Printing the retained paths is opt-in.
+    env::set_keep_tempfiles_verbose(true);
+    let tmpdir = TempDir::new_in(dir.path()).unwrap();
+    let dir_path = tmpdir.path().to_owned();
+    drop(tmpdir);
+    assert!(dir_path.exists());
+    env::set_keep_tempfiles_verbose(false);
+
+    // Explicitly closing still deletes.
+    let tmpdir = Builder::new().tempdir_in(dir.path()).unwrap();
+    let closed = tmpdir.path().to_owned();
+    tmpdir.close().unwrap();
+    assert!(!closed.exists());
+
+    env::set_keep_tempfiles(false);
+    let file = NamedTempFile::new_in(dir.path()).unwrap();
+    let removed = file.path().to_owned();
+    drop(file);
+    assert!(!removed.exists());
+
+    fs::remove_file(file_path).unwrap();
+    fs::remove_dir(dir_path).unwrap();
+}
@@ -0,0 +1,39 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "memmap")]
+
+use std::fs;
+
+use tempfile::{tempdir, MappedTempFile};
+
+#[test]
+fn test_anonymous() {
+    let dir = tempdir().unwrap();
+    let mut buf = MappedTempFile::new_in(dir.path(), 8192).unwrap();
+    assert_eq!(buf.len(), 8192);
+    assert!(buf.path().is_none());
+    assert!(buf.iter().all(|&b| b == 0));
+    buf[..5].copy_from_slice(b"abcde");
+    buf.flush().unwrap();
+    assert_eq!(&buf[..5], b"abcde");
+
+    buf.persist(dir.path().join("out")).unwrap_err();
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_named() {
+    let dir = tempdir().unwrap();
+    let mut buf = MappedTempFile::named_in(dir.path(), 5).unwrap();
+    let path = buf.path().unwrap().to_owned();
+    assert!(path.is_file());
+    buf.copy_from_slice(b"abcde");
+    drop(buf);
+    assert!(!path.exists());
+
+    let mut buf = MappedTempFile::named_in(dir.path(), 5).unwrap();
+    buf.copy_from_slice(b"abcde");
+    let target = dir.path().join("out");
+    buf.persist(&target).unwrap();
+    assert_eq!(fs::read(&target).unwrap(), b"abcde");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
@@ -123,6 +123,315 @@ fn test_append() {
     assert_eq!(buf, b"a");
 }
 
+#[test]
+fn test_open_options() {
+    let mut tmpfile = Builder::new()
+        .open_options(|options| {
+            options.append(true);
+        })
+        .tempfile()
+        .unwrap();
+    tmpfile.write_all(b"a").unwrap();
+    tmpfile.seek(SeekFrom::Start(0)).unwrap();
+    tmpfile.write_all(b"b").unwrap();
+
+    tmpfile.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "ab");
+}
+
+#[cfg(windows)]
+#[test]
+fn test_attributes() {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_TEMPORARY: u32 = 0x100;
+    const FILE_ATTRIBUTE_NOT_CONTENT_INDEXED: u32 = 0x2000;
+
+    let tmpfile = Builder::new().tempfile().unwrap();
+    let attributes = tmpfile.as_file().metadata().unwrap().file_attributes();
+    assert_eq!(
+        attributes & FILE_ATTRIBUTE_TEMPORARY,
+        FILE_ATTRIBUTE_TEMPORARY
+    );
+
+    let tmpfile = Builder::new()
+        .attributes(FILE_ATTRIBUTE_NOT_CONTENT_INDEXED)
+        .tempfile()
+        .unwrap();
+    let attributes = tmpfile.as_file().metadata().unwrap().file_attributes();
+    assert_eq!(attributes & FILE_ATTRIBUTE_TEMPORARY, 0);
+    assert_eq!(
+        attributes & FILE_ATTRIBUTE_NOT_CONTENT_INDEXED,
+        FILE_ATTRIBUTE_NOT_CONTENT_INDEXED
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn test_security() {
+    use tempfile::WindowsSecurity;
+
+    let mut builder = Builder::new();
+    builder.security(WindowsSecurity::CurrentUserOnly);
+    let dir = builder.tempdir().unwrap();
+    let mut tmpfile = builder.tempfile_in(dir.path()).unwrap();
+    tmpfile.write_all(b"abcde").unwrap();
+    assert_eq!(std::fs::read(tmpfile.path()).unwrap(), b"abcde");
+    tmpfile.close().unwrap();
+    dir.close().unwrap();
+
+    builder.security(WindowsSecurity::Sddl("not a descriptor".into()));
+    builder.tempfile().unwrap_err();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmpfile = Builder::new().mode(0o640).tempfile().unwrap();
+    let mode = tmpfile.as_file().metadata().unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+
+    let tmpdir = Builder::new().mode(0o750).tempdir().unwrap();
+    let mode = tmpdir.path().metadata().unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o750);
+
+    // The last call wins.
+    let tmpfile = Builder::new()
+        .permissions(std::fs::Permissions::from_mode(0o600))
+        .mode(0o644)
+        .tempfile()
+        .unwrap();
+    let mode = tmpfile.as_file().metadata().unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o644);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_permissions_exact() {
+    use std::fs::Permissions;
+    use std::os::unix::fs::PermissionsExt;
+
+    // Group and world write bits are usually masked out by the umask.
+    let tmpfile = Builder::new()
+        .permissions_exact(Permissions::from_mode(0o666))
+        .tempfile()
+        .unwrap();
+    let mode = tmpfile.as_file().metadata().unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o666);
+
+    let tmpdir = Builder::new()
+        .permissions_exact(Permissions::from_mode(0o777))
+        .subdirs(["sub"])
+        .tempdir()
+        .unwrap();
+    let mode = tmpdir.path().metadata().unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o777);
+    let mode = tmpdir.child("sub").metadata().unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o777);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_owner() {
+    use std::os::unix::fs::MetadataExt;
+
+    let meta = tempfile::tempfile().unwrap().metadata().unwrap();
+    let (uid, gid) = (meta.uid(), meta.gid());
+
+    let tmpfile = Builder::new()
+        .owner(Some(uid), Some(gid))
+        .tempfile()
+        .unwrap();
+    let meta = tmpfile.as_file().metadata().unwrap();
+    assert_eq!((meta.uid(), meta.gid()), (uid, gid));
+
+    let tmpdir = Builder::new()
+        .owner(None, Some(gid))
+        .subdirs(["sub"])
+        .tempdir()
+        .unwrap();
+    assert_eq!(tmpdir.path().metadata().unwrap().gid(), gid);
+    assert_eq!(tmpdir.child("sub").metadata().unwrap().gid(), gid);
+
+    // Only the superuser can give files away.
+    if uid != 0 {
+        let dir = tempdir().unwrap();
+        Builder::new()
+            .owner(Some(0), None)
+            .tempfile_in(&dir)
+            .unwrap_err();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    }
+}
+
+#[test]
+fn test_name_generator() {
+    use std::ffi::OsString;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::NameGenerator;
+
+    #[derive(Debug)]
+    struct Counter(AtomicUsize);
+
+    impl NameGenerator for Counter {
+        fn generate(&self, len: usize) -> OsString {
+            let n = self.0.fetch_add(1, Ordering::Relaxed);
+            format!("{:0width$}", n, width = len).into()
+        }
+    }
+
+    static COUNTER: Counter = Counter(AtomicUsize::new(0));
+
+    let dir = tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.prefix("p-").suffix(".s").rand_bytes(3);
+    builder.name_generator(&COUNTER);
+
+    let first = builder.tempfile_in(dir.path()).unwrap();
+    assert_eq!(first.path().file_name().unwrap(), "p-000.s");
+    let second = builder.tempdir_in(dir.path()).unwrap();
+    assert_eq!(second.path().file_name().unwrap(), "p-001.s");
+
+    // Existing names are skipped.
+    std::fs::write(dir.path().join("p-002.s"), b"").unwrap();
+    let third = builder.tempfile_in(dir.path()).unwrap();
+    assert_eq!(third.path().file_name().unwrap(), "p-003.s");
+    // Names that would escape the directory are rejected.
+    #[derive(Debug)]
+    struct Fixed(&'static str);
+
+    impl NameGenerator for Fixed {
+        fn generate(&self, _len: usize) -> OsString {
+            self.0.into()
+        }
+    }
+
+    static ESCAPE: Fixed = Fixed("../escaped");
+    static PARENT: Fixed = Fixed("..");
+    for generator in [&ESCAPE, &PARENT] {
+        let err = Builder::new()
+            .prefix("")
+            .name_generator(generator)
+            .tempfile_in(dir.path())
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    assert!(!dir.path().parent().unwrap().join("escaped").exists());
+}
+
+#[test]
+fn test_max_retries() {
+    use std::time::Duration;
+    use tempfile::NameGenerator;
+
+    #[derive(Debug)]
+    struct Fixed;
+
+    impl NameGenerator for Fixed {
+        fn generate(&self, _len: usize) -> OsString {
+            "taken".into()
+        }
+    }
+
+    let dir = tempdir().unwrap();
+    std::fs::write(dir.path().join("taken"), b"").unwrap();
+    let mut builder = Builder::new();
+    builder.prefix("").name_generator(&Fixed);
+
+    // No retries: the original error is returned.
+    let err = builder.max_retries(0).tempfile_in(dir.path()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert!(!err.to_string().contains("attempts"), "{}", err);
+
+    let err = builder
+        .max_retries(2)
+        .retry_backoff(Duration::from_millis(1))
+        .tempdir_in(dir.path())
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert!(
+        err.to_string().contains("gave up after 3 attempts"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_validate_dir() {
+    let dir = tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.validate_dir(true);
+
+    let missing = dir.path().join("missing");
+    let err = builder.tempfile_in(&missing).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert!(err.to_string().contains("inaccessible"), "{}", err);
+
+    let file = dir.path().join("file");
+    std::fs::write(&file, b"").unwrap();
+    let err = builder.tempdir_in(&file).unwrap_err();
+    assert!(err.to_string().contains("not a directory"), "{}", err);
+
+    // The probe file is cleaned up.
+    let _tmpfile = builder.tempfile_in(dir.path()).unwrap();
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn test_rand_charset() {
+    use tempfile::Charset;
+
+    let dir = tempdir().unwrap();
+    for (charset, valid) in [
+        (Charset::Hex, b"0123456789abcdef" as &[u8]),
+        (
+            Charset::LowercaseAlphanumeric,
+            b"0123456789abcdefghijklmnopqrstuvwxyz",
+        ),
+    ] {
+        let tmpfile = Builder::new()
+            .prefix("")
+            .rand_bytes(32)
+            .rand_charset(charset)
+            .tempfile_in(dir.path())
+            .unwrap();
+        let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+        assert_eq!(name.len(), 32);
+        assert!(name.bytes().all(|b| valid.contains(&b)), "{}", name);
+    }
+}
+
+#[test]
+fn test_seed_rng() {
+    let dir = tempdir().unwrap();
+    let names = || {
+        tempfile::seed_rng(1234);
+        let a = Builder::new().tempfile_in(dir.path()).unwrap();
+        let b = Builder::new().tempdir_in(dir.path()).unwrap();
+        (a.path().to_owned(), b.path().to_owned())
+    };
+    let (a, b) = names();
+    assert_ne!(a, b);
+    assert_eq!(names(), (a.clone(), b));
+
+    // Other threads are unaffected.
+    let dir_path = dir.path().to_owned();
+    let other = std::thread::spawn(move || {
+        Builder::new()
+            .tempfile_in(dir_path)
+            .unwrap()
+            .path()
+            .to_owned()
+    })
+    .join()
+    .unwrap();
+    assert_ne!(other, a);
+}
+
 #[test]
 fn test_reopen() {
     let source = NamedTempFile::new().unwrap();
@@ -136,6 +445,47 @@ fn test_reopen() {
     assert_eq!("abcde", buf);
 }
 
+#[test]
+fn test_reopen_with() {
+    let mut source = NamedTempFile::new().unwrap();
+    write!(source, "abc").unwrap();
+    let mut appender = source
+        .reopen_with(std::fs::OpenOptions::new().append(true))
+        .unwrap();
+    write!(appender, "de").unwrap();
+
+    let mut reader = source
+        .reopen_with(std::fs::OpenOptions::new().read(true))
+        .unwrap();
+    assert!(reader.write_all(b"x").is_err());
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+
+    // Replaced files are detected. Windows may not let us reuse the name right away.
+    #[cfg(unix)]
+    {
+        let path = source.path().to_owned();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&path, b"replaced").unwrap();
+        let err = source
+            .reopen_with(std::fs::OpenOptions::new().read(true))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+}
+
+#[test]
+fn test_reopen_readonly() {
+    let mut source = NamedTempFile::new().unwrap();
+    write!(source, "abcde").unwrap();
+    let mut reader = source.reopen_readonly().unwrap();
+    assert!(reader.write_all(b"x").is_err());
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+}
+
 #[test]
 fn test_into_file() {
     let mut file = NamedTempFile::new().unwrap();
@@ -311,6 +661,53 @@ fn test_into_parts() {
     assert_eq!("abcdefgh", buf);
 }
 
+#[test]
+fn test_map() {
+    use std::io::BufWriter;
+
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_owned();
+
+    let mut file = file.map(BufWriter::new);
+    write!(file, "abcd").unwrap();
+    assert_eq!(file.path(), path);
+    let mut file = file.try_map(BufWriter::into_inner).unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcd");
+
+    // The file is deleted if the transformation fails.
+    let err = file
+        .try_map(|_| Err::<File, _>(std::io::Error::from(std::io::ErrorKind::Other)))
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_buffered() {
+    let dir = tempdir().unwrap();
+    let mut file = NamedTempFile::new_buffered_in(dir.path()).unwrap();
+    write!(file, "abcd").unwrap();
+    let target = dir.path().join("target");
+    let mut file = file.flush_and_persist(&target).unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"abcd");
+    write!(file, "efgh").unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"abcdefgh");
+
+    let mut file = Builder::new()
+        .suffix(".txt")
+        .tempfile_buffered_in(dir.path())
+        .unwrap();
+    write!(file, "abcd").unwrap();
+    assert!(file.path().to_str().unwrap().ends_with(".txt"));
+    let err = file
+        .flush_and_persist(dir.path().join("missing").join("target"))
+        .unwrap_err();
+    assert!(err.file.path().exists());
+}
+
 #[test]
 fn test_from_parts() {
     let mut file = NamedTempFile::new().unwrap();
@@ -352,6 +749,25 @@ fn test_keep() {
     std::fs::remove_file(&path).unwrap();
 }
 
+#[test]
+fn test_keep_in_place() {
+    let dir = tempdir().unwrap();
+    let mut tmpfile = Builder::new()
+        .prefix("final")
+        .tempfile_in(dir.path())
+        .unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    let old_path = tmpfile.path().to_owned();
+    let (mut f, path) = tmpfile.keep().unwrap();
+    assert_eq!(old_path, path);
+    assert!(exists(&path));
+
+    f.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    f.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+}
+
 #[test]
 fn test_builder_keep() {
     let mut tmpfile = Builder::new().keep(true).tempfile().unwrap();
@@ -369,6 +785,80 @@ fn test_builder_keep() {
     std::fs::remove_file(&path).unwrap();
 }
 
+#[test]
+fn test_builder_disable_cleanup() {
+    let dir = tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.disable_cleanup(true);
+
+    let tmpfile = builder.tempfile_in(dir.path()).unwrap();
+    let kept = tmpfile.path().to_owned();
+    drop(tmpfile);
+    assert!(exists(&kept));
+
+    let tmpdir = builder.tempdir_in(dir.path()).unwrap();
+    let kept_dir = tmpdir.path().to_owned();
+    drop(tmpdir);
+    assert!(exists(&kept_dir));
+
+    // Cleanup can be re-enabled per file.
+    let mut tmpfile = builder.tempfile_in(dir.path()).unwrap();
+    tmpfile.disable_cleanup(false);
+    let removed = tmpfile.path().to_owned();
+    drop(tmpfile);
+    assert!(!exists(&removed));
+
+    let mut temp_path = builder.tempfile_in(dir.path()).unwrap().into_temp_path();
+    temp_path.disable_cleanup(false);
+    let removed = temp_path.to_path_buf();
+    drop(temp_path);
+    assert!(!exists(removed));
+}
+
+#[test]
+fn test_lifecycle_hooks() {
+    use std::io;
+    use std::sync::Mutex;
+
+    static CREATED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+    static CLEANED: Mutex<Vec<(PathBuf, bool)>> = Mutex::new(Vec::new());
+
+    fn created(path: &Path) {
+        CREATED.lock().unwrap().push(path.to_owned());
+    }
+
+    fn cleaned(path: &Path, result: io::Result<()>) {
+        CLEANED
+            .lock()
+            .unwrap()
+            .push((path.to_owned(), result.is_ok()));
+    }
+
+    let tmpfile = Builder::new()
+        .on_create(created)
+        .on_cleanup(cleaned)
+        .tempfile()
+        .unwrap();
+    let path = tmpfile.path().to_owned();
+    assert_eq!(CREATED.lock().unwrap()[..], [path.as_path()]);
+    assert!(CLEANED.lock().unwrap().is_empty());
+
+    // Deleting the file ourselves should be reported as a failure.
+    std::fs::remove_file(&path).unwrap();
+    drop(tmpfile);
+    assert_eq!(*CLEANED.lock().unwrap(), [(path, false)]);
+
+    // Persisting isn't cleanup.
+    let tmpfile = Builder::new()
+        .on_cleanup(cleaned)
+        .make(|path| File::create(path))
+        .unwrap();
+    let path = tmpfile.path().to_owned();
+    tmpfile.persist(&path).unwrap();
+    assert_eq!(CLEANED.lock().unwrap().len(), 1);
+    std::fs::remove_file(&path).unwrap();
+}
+
 #[test]
 fn test_make() {
     let tmpfile = Builder::new().make(|path| File::create(path)).unwrap();
@@ -388,6 +878,32 @@ fn test_make_in() {
     assert_eq!(tmpfile.path().parent(), Some(tmp_dir.path()));
 }
 
+#[cfg(unix)]
+#[test]
+fn test_make_with_permissions() {
+    use std::fs::Permissions;
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_dir = tempdir().unwrap();
+
+    let tmpfile = Builder::new()
+        .make_with_permissions_in(tmp_dir.path(), |path, permissions| {
+            assert!(permissions.is_none());
+            File::create(path)
+        })
+        .unwrap();
+    assert_eq!(tmpfile.path().parent(), Some(tmp_dir.path()));
+
+    let tmpfile = Builder::new()
+        .permissions(Permissions::from_mode(0o640))
+        .make_with_permissions_in(tmp_dir.path(), |path, permissions| {
+            assert_eq!(permissions.unwrap().mode(), 0o640);
+            File::create(path)
+        })
+        .unwrap();
+    assert!(tmpfile.path().is_file());
+}
+
 #[test]
 fn test_make_fnmut() {
     let mut count = 0;
@@ -453,8 +969,59 @@ fn test_make_uds_conflict() {
     }
 }
 
+#[test]
+fn test_retry_on() {
+    use std::io::ErrorKind;
+
+    let mut attempts = 0;
+    let tmpfile = Builder::new()
+        .retry_on(|e| e.kind() == ErrorKind::PermissionDenied)
+        .make(|path| {
+            attempts += 1;
+            if attempts < 3 {
+                return Err(ErrorKind::PermissionDenied.into());
+            }
+            File::create(path)
+        })
+        .unwrap();
+    assert_eq!(attempts, 3);
+    assert!(tmpfile.path().is_file());
+
+    // Persistent errors are only retried a few times, and the last one is returned.
+    let mut attempts = 0;
+    let err = Builder::new()
+        .retry_on(|e| e.kind() == ErrorKind::PermissionDenied)
+        .make(|_| {
+            attempts += 1;
+            Err::<File, _>(ErrorKind::PermissionDenied.into())
+        })
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert!(attempts > 1 && attempts < 100, "{}", attempts);
+
+    // Unless more retries are requested explicitly.
+    let mut attempts = 0;
+    let err = Builder::new()
+        .retry_on(|e| e.kind() == ErrorKind::PermissionDenied)
+        .max_retries(100)
+        .make(|_| {
+            attempts += 1;
+            Err::<File, _>(ErrorKind::PermissionDenied.into())
+        })
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(attempts, 101);
+
+    // Without it, the first error is returned.
+    let err = Builder::new()
+        .make(|_| Err::<File, _>(ErrorKind::PermissionDenied.into()))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+}
+
 /// Make sure we re-seed with system randomness if we run into a conflict.
 #[test]
+#[cfg(feature = "fastrand")]
 fn test_reseed() {
     // Deterministic seed.
     fastrand::seed(42);
@@ -512,3 +1079,491 @@ fn test_overly_generic_bounds() {
         };
     }
 }
+
+#[test]
+fn test_reap_orphans() {
+    let dir = tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.prefix("svc-").suffix(".dat").tag_pid(true);
+
+    let live = builder.tempfile_in(dir.path()).unwrap();
+    let name = live.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with(&format!("svc-{}-", std::process::id())));
+    assert!(name.ends_with(".dat"));
+
+    // Find the PID of a process that has exited.
+    let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+        .arg("--list")
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let dead = child.id();
+    child.wait().unwrap();
+
+    let orphan_file = dir.path().join(format!("svc-{}-abc123.dat", dead));
+    let orphan_dir = dir.path().join(format!("svc-{}-def456.dat", dead));
+    let unrelated = dir.path().join(format!("other-{}-abc123.dat", dead));
+    File::create(&orphan_file).unwrap();
+    std::fs::create_dir(&orphan_dir).unwrap();
+    File::create(orphan_dir.join("inner")).unwrap();
+    File::create(&unrelated).unwrap();
+
+    assert_eq!(builder.reap_orphans_in(dir.path()).unwrap(), 2);
+    assert!(!exists(&orphan_file));
+    assert!(!exists(&orphan_dir));
+    assert!(exists(&unrelated));
+    assert!(exists(live.path()));
+}
+
+#[test]
+fn test_tempfiles() {
+    let dir = tempdir().unwrap();
+    let files = Builder::new()
+        .prefix("shard-")
+        .tempfiles_in(&dir, 5)
+        .unwrap();
+    assert_eq!(files.len(), 5);
+    let mut paths: Vec<_> = files.iter().map(|f| f.path().to_owned()).collect();
+    paths.sort();
+    paths.dedup();
+    assert_eq!(paths.len(), 5);
+    assert!(paths.iter().all(|p| p.is_file()
+        && p.file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("shard-")));
+    drop(files);
+    assert!(paths.iter().all(|p| !p.exists()));
+
+    let dirs = Builder::new().tempdirs_in(&dir, 3).unwrap();
+    assert_eq!(dirs.len(), 3);
+    assert!(dirs.iter().all(|d| d.path().is_dir()));
+    assert!(Builder::new().tempfiles_in(&dir, 0).unwrap().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_keep_alive() {
+    let file = NamedTempFile::new().unwrap();
+    let before = file.as_file().metadata().unwrap().modified().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    file.keep_alive().unwrap();
+    let after = file.as_file().metadata().unwrap().modified().unwrap();
+    assert!(after > before);
+
+    let dir = tempdir().unwrap();
+    let inner = dir.path().join("inner");
+    std::fs::write(&inner, b"").unwrap();
+    let before = std::fs::metadata(&inner).unwrap().modified().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    dir.keep_alive().unwrap();
+    assert!(std::fs::metadata(&inner).unwrap().modified().unwrap() > before);
+    let path = dir.path().to_owned();
+    dir.close().unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_structured_errors() {
+    use tempfile::Error;
+
+    let dir = tempdir().unwrap();
+    let _taken = Builder::new()
+        .prefix("taken")
+        .rand_bytes(0)
+        .tempfile_in(&dir)
+        .unwrap();
+    let err = Builder::new()
+        .prefix("taken")
+        .rand_bytes(0)
+        .max_retries(2)
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    let message = err.to_string();
+    assert!(message.contains("gave up after 3 attempts"), "{}", message);
+    assert!(message.contains("\"taken\""), "{}", message);
+    match Error::from_io(err) {
+        Ok(Error::TooManyCollisions {
+            dir: d,
+            attempts,
+            last_attempt,
+        }) => {
+            assert_eq!(d, dir.path());
+            assert_eq!(attempts, 3);
+            assert_eq!(last_attempt, dir.path().join("taken"));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    let missing = dir.path().join("missing");
+    let err = NamedTempFile::new_in(&missing).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    match Error::from_io(err) {
+        Ok(Error::TempDirNotFound { dir: d }) => assert_eq!(d, missing),
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    // Errors that don't wrap a structured error are returned as-is.
+    let err = std::io::Error::new(std::io::ErrorKind::Other, "oops");
+    let err = Error::from_io(err).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    assert_eq!(err.to_string(), "oops");
+
+    // Including plain OS errors, which keep their error code.
+    let err = std::io::Error::from_raw_os_error(2);
+    let message = err.to_string();
+    let err = Error::from_io(err).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(2));
+    assert_eq!(err.to_string(), message);
+
+    // Persisting across filesystems is recognized from the error code, which is kept.
+    #[cfg(target_os = "linux")]
+    match Error::from_io(std::io::Error::from_raw_os_error(18)) {
+        Ok(Error::CrossDevice { source }) => assert_eq!(source.raw_os_error(), Some(18)),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_persist_error_target() {
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("missing").join("file");
+
+    let file = NamedTempFile::new_in(&dir).unwrap();
+    let path = file.path().to_owned();
+    let err = file.persist(&target).unwrap_err();
+    assert_eq!(err.target(), Some(&*target));
+    let message = err.to_string();
+    assert!(
+        message.starts_with(&format!(
+            "failed to persist {} to {}: ",
+            path.display(),
+            target.display()
+        )),
+        "{}",
+        message
+    );
+
+    let err = err.file.into_temp_path().persist(&target).unwrap_err();
+    assert_eq!(err.target(), Some(&*target));
+    assert!(err.to_string().contains(&*target.to_string_lossy()));
+}
+
+#[test]
+#[cfg(not(feature = "fastrand"))]
+fn test_sequential_names() {
+    use tempfile::NameGenerator;
+
+    // Consecutive names are derived from a counter, so they never collide.
+    let mut names: Vec<_> = (0..1000)
+        .map(|_| tempfile::Alphanumeric.generate(6))
+        .collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), 1000);
+
+    let _files: Vec<_> = (0..10)
+        .map(|_| {
+            Builder::new()
+                .make(|path| File::options().write(true).create_new(true).open(path))
+                .unwrap()
+        })
+        .collect();
+}
+
+#[test]
+#[cfg(feature = "getrandom")]
+fn test_secure_names() {
+    let dir = tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder
+        .prefix("")
+        .rand_bytes(32)
+        .rand_charset(tempfile::Charset::Hex)
+        .secure_names(true);
+
+    // Seeding has no effect on secure names.
+    tempfile::seed_rng(42);
+    let first = builder.tempfile_in(&dir).unwrap();
+    tempfile::seed_rng(42);
+    let second = builder.tempfile_in(&dir).unwrap();
+    assert_ne!(first.path(), second.path());
+
+    let name = first.path().file_name().unwrap().to_str().unwrap();
+    assert_eq!(name.len(), 32);
+    assert!(name
+        .bytes()
+        .all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase()));
+}
+
+#[test]
+fn test_name_too_long() {
+    use tempfile::Error;
+
+    let dir = tempdir().unwrap();
+
+    // The random part is shortened to fit, but not below 6 characters.
+    let prefix = "p".repeat(249);
+    let file = Builder::new().prefix(&prefix).tempfile_in(&dir).unwrap();
+    assert_eq!(file.path().file_name().unwrap().len(), 255);
+    let err = Builder::new()
+        .prefix(&"p".repeat(250))
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    // The suffixes of file sets are taken into account.
+    let set = Builder::new()
+        .prefix(&"p".repeat(240))
+        .rand_bytes(20)
+        .tempfile_set_in(&dir, [".a", ".longer"])
+        .unwrap();
+    let longest = set.get(".longer").unwrap().path().file_name().unwrap();
+    assert_eq!(longest.len(), 255);
+    let err = Builder::new()
+        .prefix(&"p".repeat(240))
+        .tempfile_set_in(&dir, [".a", &"l".repeat(10)])
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    // The prefix alone is too long.
+    let prefix = "p".repeat(300);
+    let err = Builder::new()
+        .prefix(&prefix)
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    match Error::from_io(err) {
+        Ok(Error::NameTooLong { dir: d, name }) => {
+            assert_eq!(d, dir.path());
+            assert_eq!(name, OsStr::new(&prefix));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    // Custom generators can still produce names that are too long.
+    #[derive(Debug)]
+    struct Long;
+    impl tempfile::NameGenerator for Long {
+        fn generate(&self, _len: usize) -> OsString {
+            "l".repeat(300).into()
+        }
+    }
+    let err = Builder::new()
+        .name_generator(&Long)
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert!(
+        matches!(Error::from_io(err), Ok(Error::NameTooLong { .. })),
+        "expected NameTooLong"
+    );
+}
+
+#[test]
+#[cfg(windows)]
+fn test_long_paths() {
+    let dir = tempdir().unwrap();
+    let mut long = dir.path().to_owned();
+    while long.as_os_str().len() < 300 {
+        long.push("a".repeat(50));
+    }
+    std::fs::create_dir_all(&long).unwrap();
+
+    let mut builder = Builder::new();
+    builder.security(tempfile::WindowsSecurity::CurrentUserOnly);
+    let file = builder.tempfile_in(&long).unwrap();
+    let target = long.join("persisted");
+    file.into_temp_path().persist(&target).unwrap();
+    assert!(target.exists());
+
+    let subdir = builder.tempdir_in(&long).unwrap();
+    let kept = NamedTempFile::new_in(&subdir)
+        .unwrap()
+        .into_temp_path()
+        .keep()
+        .unwrap();
+    assert!(kept.exists());
+}
+
+#[test]
+fn test_from_parts_checked() {
+    let dir = tempdir().unwrap();
+    let (file, path) = NamedTempFile::new_in(&dir).unwrap().into_parts();
+    let mut tmpfile = NamedTempFile::from_parts_checked(file, path).unwrap();
+    write!(tmpfile, "abcde").unwrap();
+
+    let (file, _path) = tmpfile.into_parts();
+    let (_, other_path) = NamedTempFile::new_in(&dir).unwrap().into_parts();
+    let other = other_path.to_path_buf();
+    let err = NamedTempFile::from_parts_checked(file, other_path).unwrap_err();
+    assert_eq!(err.error.kind(), std::io::ErrorKind::InvalidInput);
+
+    // Neither part is destroyed by a failed check.
+    assert!(other.exists());
+    let mut file = err.file;
+    write!(file, "fgh").unwrap();
+    drop(err.path);
+    assert!(!other.exists());
+}
+
+#[test]
+fn test_leak() {
+    let dir = tempdir().unwrap();
+    let (file, path) = NamedTempFile::new_in(&dir).unwrap().leak();
+    write!(file, "abcde").unwrap();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "abcde");
+
+    let other = NamedTempFile::new_in(&dir).unwrap().into_temp_path().leak();
+    assert!(other.exists());
+}
+
+#[test]
+fn test_persist_in() {
+    let src = tempdir().unwrap();
+    let outbox = tempdir().unwrap();
+
+    let mut tmpfile = Builder::new()
+        .suffix(".csv")
+        .tempfile_in(src.path())
+        .unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_owned();
+    let (_, path) = tmpfile.persist_in(outbox.path()).unwrap();
+    assert_eq!(path, outbox.path().join(&name));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "abcde");
+
+    // Taken names get a unique suffix, keeping the extension.
+    let tmpfile = Builder::new()
+        .prefix("report")
+        .rand_bytes(0)
+        .suffix(".csv")
+        .tempfile_in(src.path())
+        .unwrap();
+    std::fs::write(outbox.path().join("report.csv"), "taken").unwrap();
+    let (_, path) = tmpfile.persist_in(outbox.path()).unwrap();
+    let new_name = path.file_name().unwrap().to_str().unwrap();
+    assert!(new_name.starts_with("report-"), "{}", new_name);
+    assert!(new_name.ends_with(".csv"), "{}", new_name);
+    assert_eq!(
+        std::fs::read_to_string(outbox.path().join("report.csv")).unwrap(),
+        "taken"
+    );
+    assert_eq!(std::fs::read_dir(src.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_temppath_persist_in() {
+    let src = tempdir().unwrap();
+    let outbox = tempdir().unwrap();
+
+    let path = NamedTempFile::new_in(src.path()).unwrap().into_temp_path();
+    let name = path.file_name().unwrap().to_owned();
+    std::fs::write(outbox.path().join(&name), "taken").unwrap();
+
+    let new_path = path.persist_in(outbox.path()).unwrap();
+    assert_eq!(new_path.parent(), Some(outbox.path()));
+    assert_ne!(new_path.file_name(), Some(&*name));
+    assert!(new_path.is_file());
+    assert_eq!(std::fs::read_dir(src.path()).unwrap().count(), 0);
+    assert_eq!(std::fs::read_dir(outbox.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn test_preallocate() {
+    let tmpfile = Builder::new().preallocate(8192).tempfile().unwrap();
+    assert_eq!(tmpfile.as_file().metadata().unwrap().len(), 8192);
+
+    let tmpfile = Builder::new().tempfile().unwrap();
+    assert_eq!(tmpfile.as_file().metadata().unwrap().len(), 0);
+}
+
+#[test]
+fn test_wipe_on_drop() {
+    let tmpdir = tempdir().unwrap();
+
+    // Observe the contents through a second link, which survives deleting the tempfile.
+    let mut tmpfile = Builder::new()
+        .wipe_on_drop(true)
+        .tempfile_in(&tmpdir)
+        .unwrap();
+    tmpfile.write_all(b"secret").unwrap();
+    let link = tmpdir.path().join("dropped");
+    std::fs::hard_link(tmpfile.path(), &link).unwrap();
+    drop(tmpfile);
+    assert_eq!(std::fs::read(&link).unwrap(), b"");
+
+    let mut tmpfile = Builder::new()
+        .wipe_on_drop(true)
+        .tempfile_in(&tmpdir)
+        .unwrap();
+    tmpfile.write_all(b"secret").unwrap();
+    let link = tmpdir.path().join("closed");
+    std::fs::hard_link(tmpfile.path(), &link).unwrap();
+    tmpfile.close().unwrap();
+    assert_eq!(std::fs::read(&link).unwrap(), b"");
+
+    // Bare paths reopen the file to wipe it.
+    let mut tmpfile = Builder::new()
+        .wipe_on_drop(true)
+        .tempfile_in(&tmpdir)
+        .unwrap();
+    tmpfile.write_all(b"secret").unwrap();
+    let link = tmpdir.path().join("path");
+    std::fs::hard_link(tmpfile.path(), &link).unwrap();
+    drop(tmpfile.into_temp_path());
+    assert_eq!(std::fs::read(&link).unwrap(), b"");
+
+    // Persisted files are left alone.
+    let mut tmpfile = Builder::new()
+        .wipe_on_drop(true)
+        .tempfile_in(&tmpdir)
+        .unwrap();
+    tmpfile.write_all(b"secret").unwrap();
+    let path = tmpdir.path().join("persisted");
+    tmpfile.persist(&path).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"secret");
+
+    // Without the option, the contents are untouched.
+    let mut tmpfile = Builder::new().tempfile_in(&tmpdir).unwrap();
+    tmpfile.write_all(b"secret").unwrap();
+    let link = tmpdir.path().join("unwiped");
+    std::fs::hard_link(tmpfile.path(), &link).unwrap();
+    drop(tmpfile);
+    assert_eq!(std::fs::read(&link).unwrap(), b"secret");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_wipe_on_drop_symlink() {
+    let tmpdir = tempdir().unwrap();
+    let target = tmpdir.path().join("target");
+    std::fs::write(&target, b"precious").unwrap();
+
+    // The file created by the builder is wiped through its handle, and the symlink swapped in
+    // for it is left alone.
+    let mut tmpfile = Builder::new()
+        .wipe_on_drop(true)
+        .tempfile_in(&tmpdir)
+        .unwrap();
+    tmpfile.write_all(b"secret").unwrap();
+    let orig = tmpdir.path().join("orig");
+    std::fs::rename(tmpfile.path(), &orig).unwrap();
+    std::os::unix::fs::symlink(&target, tmpfile.path()).unwrap();
+    drop(tmpfile);
+    assert_eq!(std::fs::read(&orig).unwrap(), b"");
+    assert_eq!(std::fs::read(&target).unwrap(), b"precious");
+
+    // Without the handle, the symlink isn't followed.
+    let path = Builder::new()
+        .wipe_on_drop(true)
+        .tempfile_in(&tmpdir)
+        .unwrap()
+        .into_temp_path();
+    std::fs::remove_file(&path).unwrap();
+    std::os::unix::fs::symlink(&target, &path).unwrap();
+    path.close().unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"precious");
+    assert_eq!(std::fs::read_dir(&tmpdir).unwrap().count(), 2);
+}
@@ -0,0 +1,36 @@
+#![deny(rust_2018_idioms)]
+#![cfg(windows)]
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+
+use tempfile::{Builder, TempNamedPipe};
+
+#[test]
+fn test_named_pipe() {
+    let pipe = Builder::new()
+        .prefix("tempfile-test-")
+        .named_pipe()
+        .unwrap();
+    let name = pipe.path().to_str().unwrap();
+    assert!(name.starts_with(r"\\.\pipe\tempfile-test-"));
+
+    let mut client = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(pipe.path())
+        .unwrap();
+    client.write_all(b"ping").unwrap();
+
+    let mut server = File::from(pipe.into_handle());
+    let mut buf = [0; 4];
+    server.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ping");
+}
+
+#[test]
+fn test_unique_names() {
+    let a = TempNamedPipe::new().unwrap();
+    let b = TempNamedPipe::new().unwrap();
+    assert_ne!(a.path(), b.path());
+}
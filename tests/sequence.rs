@@ -0,0 +1,56 @@
+#![deny(rust_2018_idioms)]
+
+use std::fs::File;
+
+use tempfile::{tempdir, Builder, TempSequence};
+
+#[test]
+fn test_sequence_names() {
+    let dir = tempdir().unwrap();
+    let seq = Builder::new()
+        .prefix("out-")
+        .suffix(".txt")
+        .rand_bytes(4)
+        .sequence_in(dir.path());
+    assert_eq!(seq.session().len(), 4);
+
+    let session = seq.session().to_str().unwrap();
+    let first = seq.tempfile().unwrap();
+    let second = seq.tempdir().unwrap();
+    assert_eq!(
+        first.path().file_name().unwrap().to_str().unwrap(),
+        format!("out-{}-00000000.txt", session)
+    );
+    assert_eq!(
+        second.path().file_name().unwrap().to_str().unwrap(),
+        format!("out-{}-00000001.txt", session)
+    );
+    assert!(second.path().is_dir());
+}
+
+#[test]
+fn test_sequence_collision() {
+    let dir = tempdir().unwrap();
+    let seq = TempSequence::new_in(dir.path());
+    let session = seq.session().to_str().unwrap().to_owned();
+
+    // Take the first two names.
+    File::create(dir.path().join(format!(".tmp{}-00000000", session))).unwrap();
+    File::create(dir.path().join(format!(".tmp{}-00000001", session))).unwrap();
+
+    let file = seq.tempfile().unwrap();
+    assert_eq!(
+        file.path().file_name().unwrap().to_str().unwrap(),
+        format!(".tmp{}-00000002", session)
+    );
+}
+
+#[test]
+fn test_sequence_cleanup() {
+    let dir = tempdir().unwrap();
+    let seq = TempSequence::new_in(dir.path());
+    let files: Vec<_> = (0..3).map(|_| seq.tempfile().unwrap()).collect();
+    assert!(files.windows(2).all(|w| w[0].path() < w[1].path()));
+    drop(files);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
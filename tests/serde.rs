@@ -0,0 +1,35 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "serde")]
+
+use serde::de::value::{Error, StrDeserializer};
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+use serde_test::{assert_ser_tokens, Token};
+use tempfile::{NamedTempFile, TempPath};
+
+#[test]
+fn test_serialize() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap().to_owned();
+    assert_ser_tokens(&file, &[Token::Str(Box::leak(path.clone().into()))]);
+
+    let temp_path = file.into_temp_path();
+    assert_ser_tokens(&temp_path, &[Token::Str(Box::leak(path.into()))]);
+}
+
+#[test]
+fn test_deserialize() {
+    let path = NamedTempFile::new()
+        .unwrap()
+        .into_temp_path()
+        .keep()
+        .unwrap();
+    let deserializer: StrDeserializer<'_, Error> = path.to_str().unwrap().into_deserializer();
+    let temp_path = TempPath::deserialize(deserializer).unwrap();
+    assert_eq!(*temp_path, *path);
+    assert!(path.exists());
+
+    drop(temp_path);
+    // Deserialized paths are deleted on drop.
+    assert!(!path.exists());
+}
@@ -0,0 +1,91 @@
+#![deny(rust_2018_idioms)]
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Write;
+
+use tempfile::{tempdir, Builder, NameGenerator, TempFileSet};
+
+#[test]
+fn test_set_names() {
+    let dir = tempdir().unwrap();
+    let mut set = Builder::new()
+        .prefix("data.")
+        .suffix(".db")
+        .tempfile_set_in(dir.path(), ["", "-wal", "-shm"])
+        .unwrap();
+    assert_eq!(set.len(), 3);
+
+    let base = set.base_path().to_str().unwrap().to_owned();
+    assert!(base.ends_with(".db"));
+    for (suffix, file) in set.iter() {
+        assert!(file.path().is_file());
+        assert_eq!(
+            file.path().to_str().unwrap(),
+            format!("{}{}", base, suffix.to_str().unwrap())
+        );
+    }
+    write!(set.get_mut("-wal").unwrap(), "log").unwrap();
+    assert!(set.get("-journal").is_none());
+
+    let paths: Vec<_> = set.iter().map(|(_, f)| f.path().to_owned()).collect();
+    drop(set);
+    assert!(paths.iter().all(|p| !p.exists()));
+}
+
+#[test]
+fn test_set_duplicate_suffixes() {
+    let dir = tempdir().unwrap();
+    let err = TempFileSet::new_in(dir.path(), [".a", ".a"]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[derive(Debug)]
+struct Fixed;
+
+impl NameGenerator for Fixed {
+    fn generate(&self, _len: usize) -> OsString {
+        "x".into()
+    }
+}
+
+#[test]
+fn test_set_collision() {
+    let dir = tempdir().unwrap();
+    // Only the name for the second suffix is taken.
+    File::create(dir.path().join("x.wal")).unwrap();
+    Builder::new()
+        .prefix("")
+        .name_generator(&Fixed)
+        .max_retries(3)
+        .tempfile_set_in(dir.path(), [".db", ".wal"])
+        .unwrap_err();
+    // The file created for the first suffix was cleaned up.
+    assert!(!dir.path().join("x.db").exists());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_set_persist() {
+    let dir = tempdir().unwrap();
+    let mut set = TempFileSet::new_in(dir.path(), [".db", ".db-wal"]).unwrap();
+    write!(set.get_mut(".db").unwrap(), "data").unwrap();
+    let files = set.persist(dir.path().join("out")).unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("out.db")).unwrap(),
+        "data"
+    );
+    assert!(dir.path().join("out.db-wal").is_file());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn test_set_close() {
+    let dir = tempdir().unwrap();
+    TempFileSet::new_in(dir.path(), [".a", ".b"])
+        .unwrap()
+        .close()
+        .unwrap();
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
@@ -0,0 +1,62 @@
+#![deny(rust_2018_idioms)]
+#![cfg(all(feature = "signal-cleanup", unix))]
+
+use std::os::unix::process::ExitStatusExt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use signal_hook::consts::SIGTERM;
+
+const CHILD_ENV: &str = "TEMPFILE_SIGNAL_TEST_DIR";
+
+// Re-executes the test binary; the child registers temporary files for cleanup and kills itself.
+#[test]
+fn test_cleanup_on_signal() {
+    if let Some(dir) = std::env::var_os(CHILD_ENV) {
+        child(PathBuf::from(dir));
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "test_cleanup_on_signal",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env(CHILD_ENV, dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.signal(), Some(SIGTERM), "{:?}", output);
+
+    // Only the kept file remains.
+    let remaining: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(remaining, ["kept"]);
+}
+
+fn child(dir: PathBuf) -> ! {
+    let file = tempfile::NamedTempFile::new_in(&dir).unwrap();
+    file.cleanup_on_signal().unwrap();
+
+    let tmpdir = tempfile::TempDir::new_in(&dir).unwrap();
+    std::fs::write(tmpdir.path().join("file"), b"abcde").unwrap();
+    tmpdir.cleanup_on_signal().unwrap();
+
+    let kept = tempfile::NamedTempFile::new_in(&dir).unwrap();
+    kept.cleanup_on_signal().unwrap();
+    kept.persist(dir.join("kept")).unwrap();
+
+    let dropped = tempfile::NamedTempFile::new_in(&dir).unwrap();
+    dropped.cleanup_on_signal().unwrap();
+    drop(dropped);
+
+    signal_hook::low_level::raise(SIGTERM).unwrap();
+    loop {
+        thread::sleep(Duration::from_secs(1));
+    }
+}
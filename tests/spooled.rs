@@ -2,7 +2,9 @@
 
 use std::io::{Read, Seek, SeekFrom, Write};
 
-use tempfile::{spooled_tempfile, SpooledTempFile};
+use tempfile::{
+    spooled_tempfile, spooled_tempfile_auto, SpooledNamedTempFile, SpooledPool, SpooledTempFile,
+};
 
 #[test]
 fn test_automatic_rollover() {
@@ -320,3 +322,233 @@ fn test_set_len_truncation() {
     assert!(t.set_len(usize::MAX as u64 + 5).is_ok());
     assert!(t.is_rolled());
 }
+
+#[test]
+fn test_auto_max_size() {
+    let mut t = spooled_tempfile_auto();
+    // The derived limit is always at least 64KiB.
+    assert_eq!(t.write(&[0u8; 64 * 1024]).unwrap(), 64 * 1024);
+    assert!(!t.is_rolled());
+    // And at most 64MiB.
+    t.set_len(64 * 1024 * 1024 + 1).unwrap();
+    assert!(t.is_rolled());
+}
+
+#[test]
+fn test_close() {
+    let mut t = spooled_tempfile(10);
+    assert_eq!(t.write(b"abcde").unwrap(), 5);
+    assert!(!t.is_rolled());
+    t.close().unwrap();
+
+    let mut t = spooled_tempfile(10);
+    assert_eq!(t.write(b"abcdefghijklmno").unwrap(), 15);
+    assert!(t.is_rolled());
+    t.close().unwrap();
+}
+
+#[test]
+fn test_into_file() {
+    let mut t = spooled_tempfile(10);
+    t.write_all(b"abcdef").unwrap();
+    t.seek(SeekFrom::Start(2)).unwrap();
+    assert!(!t.is_rolled());
+
+    let mut file = t.into_file().unwrap();
+    assert_eq!(file.stream_position().unwrap(), 2);
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "cdef");
+
+    let mut t = spooled_tempfile(10);
+    t.write_all(b"abcdefghijklmno").unwrap();
+    assert!(t.is_rolled());
+    let mut file = t.into_file().unwrap();
+    assert_eq!(file.stream_position().unwrap(), 15);
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcdefghijklmno");
+}
+
+#[test]
+fn test_spooled_named() {
+    let dir = tempfile::tempdir().unwrap();
+    let spool = dir.path().join("spool");
+    std::fs::create_dir(&spool).unwrap();
+
+    // Small payloads are written out on persist.
+    let mut t = SpooledNamedTempFile::new_in(10, &spool);
+    t.write_all(b"abcde").unwrap();
+    assert!(!t.is_rolled());
+    assert!(t.path().is_none());
+    let target = dir.path().join("small");
+    let mut file = t.persist(&target).unwrap();
+    assert_eq!(file.stream_position().unwrap(), 5);
+    assert_eq!(std::fs::read(&target).unwrap(), b"abcde");
+    assert_eq!(std::fs::read_dir(&spool).unwrap().count(), 0);
+
+    // Large payloads are spooled to a named file.
+    let mut t = SpooledNamedTempFile::new_in(10, &spool);
+    t.write_all(b"abcdefghijklmno").unwrap();
+    assert!(t.is_rolled());
+    let path = t.path().unwrap().to_owned();
+    assert!(path.starts_with(&spool));
+    t.seek(SeekFrom::Start(3)).unwrap();
+    let mut buf = [0; 3];
+    t.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"def");
+    let target = dir.path().join("large");
+    t.persist(&target).unwrap();
+    assert!(!path.exists());
+    assert_eq!(std::fs::read(&target).unwrap(), b"abcdefghijklmno");
+    // The data is returned if it can't be persisted.
+    let missing = dir.path().join("missing").join("file");
+    let mut t = SpooledNamedTempFile::new_in(10, &spool);
+    t.write_all(b"abcde").unwrap();
+    let err = t.persist(&missing).unwrap_err();
+    assert_eq!(err.error.kind(), std::io::ErrorKind::NotFound);
+    let mut t = err.file;
+    assert_eq!(t.as_slice(), Some(&b"abcde"[..]));
+    t.write_all(b"fghijklmno").unwrap();
+    assert!(t.is_rolled());
+    let err = t.persist(&missing).unwrap_err();
+    let t = err.file;
+    assert!(t.path().unwrap().exists());
+    let target = dir.path().join("retried");
+    t.persist(&target).unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"abcdefghijklmno");
+}
+
+#[test]
+fn test_as_slice() {
+    let mut t = spooled_tempfile(10);
+    assert_eq!(t.as_slice(), Some(&b""[..]));
+    t.write_all(b"abcde").unwrap();
+    t.seek(SeekFrom::Start(1)).unwrap();
+    assert_eq!(t.as_slice(), Some(&b"abcde"[..]));
+    t.write_all(b"abcdefghij").unwrap();
+    assert!(t.is_rolled());
+    assert_eq!(t.as_slice(), None);
+
+    let mut t = SpooledNamedTempFile::new(10);
+    t.write_all(b"abcde").unwrap();
+    assert_eq!(t.as_slice(), Some(&b"abcde"[..]));
+    t.roll().unwrap();
+    assert_eq!(t.as_slice(), None);
+}
+
+#[test]
+fn test_pool() {
+    let pool = SpooledPool::new(20);
+    assert_eq!(pool.capacity(), 20);
+    let mut a = pool.spooled_tempfile(15);
+    let mut b = pool.spooled_tempfile(15);
+
+    a.write_all(b"0123456789").unwrap();
+    // Overwriting doesn't use more memory.
+    a.seek(SeekFrom::Start(0)).unwrap();
+    a.write_all(b"abcde").unwrap();
+    b.set_len(10).unwrap();
+    assert_eq!(pool.used(), 20);
+    assert!(!a.is_rolled() && !b.is_rolled());
+
+    // Shrinking releases memory.
+    b.set_len(5).unwrap();
+    assert_eq!(pool.used(), 15);
+
+    // Exhausting the pool rolls over the growing file.
+    b.seek(SeekFrom::End(0)).unwrap();
+    b.write_all(b"0123456789").unwrap();
+    assert!(b.is_rolled());
+    assert!(!a.is_rolled());
+    assert_eq!(pool.used(), 10);
+
+    drop(a);
+    assert_eq!(pool.used(), 0);
+    let mut buf = Vec::new();
+    b.seek(SeekFrom::Start(0)).unwrap();
+    b.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, [&[0u8; 5][..], b"0123456789"].concat());
+}
+
+#[test]
+fn test_pool_spills_largest() {
+    let pool = SpooledPool::new(20);
+    let mut a = pool.spooled_tempfile(20);
+    let mut b = pool.spooled_tempfile(20);
+    let mut c = pool.spooled_tempfile(20);
+    a.write_all(b"0123456789").unwrap();
+    b.write_all(b"01234").unwrap();
+    c.write_all(b"012").unwrap();
+
+    // `a` is marked to make room for `c`, and only spills when it's next used.
+    c.write_all(b"3456").unwrap();
+    assert!(!a.is_rolled() && !b.is_rolled() && !c.is_rolled());
+    assert_eq!(pool.used(), 22);
+
+    // Files already marked count as freed.
+    b.write_all(b"5").unwrap();
+    assert!(!b.is_rolled());
+    assert_eq!(pool.used(), 23);
+
+    let mut buf = Vec::new();
+    a.seek(SeekFrom::Start(0)).unwrap();
+    assert!(a.is_rolled());
+    a.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"0123456789");
+    assert_eq!(pool.used(), 13);
+
+    // Growing the largest file rolls it over.
+    b.write_all(b"6789").unwrap();
+    c.write_all(b"789").unwrap();
+    assert_eq!(pool.used(), 20);
+    c.write_all(b"a").unwrap();
+    assert!(c.is_rolled() && !b.is_rolled());
+    assert_eq!(pool.used(), 10);
+}
+
+#[test]
+fn test_as_file() {
+    let mut t = spooled_tempfile(10);
+    t.write_all(b"abcde").unwrap();
+    assert!(t.as_file().is_none());
+    assert!(t.as_file_mut().is_none());
+    t.roll().unwrap();
+    assert_eq!(t.as_file().unwrap().metadata().unwrap().len(), 5);
+    t.as_file_mut().unwrap().write_all(b"fgh").unwrap();
+    assert_eq!(t.as_file().unwrap().metadata().unwrap().len(), 8);
+}
+
+#[test]
+fn test_wipe_on_drop() {
+    let mut t = spooled_tempfile(10);
+    t.wipe_on_drop(true);
+    t.write_all(b"secret").unwrap();
+    t.set_len(3).unwrap();
+    t.roll().unwrap();
+
+    // Rolling over keeps the contents.
+    let mut buf = Vec::new();
+    t.seek(SeekFrom::Start(0)).unwrap();
+    t.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"sec");
+
+    // The file on disk is wiped when dropped.
+    let mut file = t.as_file().unwrap().try_clone().unwrap();
+    drop(t);
+    assert_eq!(file.metadata().unwrap().len(), 0);
+    buf.clear();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_end(&mut buf).unwrap();
+    assert!(buf.is_empty());
+
+    // Contents handed off are left alone.
+    let mut t = spooled_tempfile(10);
+    t.wipe_on_drop(true);
+    t.write_all(b"secret").unwrap();
+    match t.into_inner() {
+        tempfile::SpooledData::InMemory(cursor) => assert_eq!(cursor.into_inner(), b"secret"),
+        tempfile::SpooledData::OnDisk(_) => panic!("should still be in memory"),
+    }
+}
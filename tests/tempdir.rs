@@ -14,8 +14,9 @@ use std::fs;
 use std::path::Path;
 use std::sync::mpsc::channel;
 use std::thread;
+use std::time::{Duration, Instant};
 
-use tempfile::{Builder, TempDir};
+use tempfile::{Builder, SharedTempDir, TempDir, TempDirPath};
 
 fn test_tempdir() {
     let path = {
@@ -175,6 +176,694 @@ fn test_keep() {
     drop(tmpdir);
     assert!(path.exists());
     fs::remove_dir(path).unwrap();
+
+    let path = TempDir::new().unwrap().keep();
+    assert!(path.exists());
+    fs::remove_dir(path).unwrap();
+
+    let mut tmpdir = TempDir::new().unwrap();
+    let path = tmpdir.path().to_owned();
+    tmpdir.disable_cleanup(true);
+    tmpdir.disable_cleanup(false);
+    drop(tmpdir);
+    assert!(!path.exists());
+
+    let mut tmpdir = TempDir::new().unwrap();
+    let path = tmpdir.path().to_owned();
+    tmpdir.disable_cleanup(true);
+    drop(tmpdir);
+    assert!(path.exists());
+    fs::remove_dir(path).unwrap();
+}
+
+fn test_child() {
+    use std::io::{ErrorKind, Write};
+
+    let tmpdir = TempDir::new().unwrap();
+    assert_eq!(tmpdir.child("a/b"), tmpdir.path().join("a/b"));
+
+    let mut file = tmpdir.create_child_file("a/b/file").unwrap();
+    file.write_all(b"abcde").unwrap();
+    assert_eq!(fs::read(tmpdir.child("a/b/file")).unwrap(), b"abcde");
+
+    let dir = tmpdir.create_child_dir("c/d").unwrap();
+    assert!(dir.is_dir());
+    assert_eq!(tmpdir.create_child_dir("c/d").unwrap(), dir);
+
+    let err = tmpdir.create_child_file("../escape").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = tmpdir
+        .create_child_dir(std::env::temp_dir().join("escape"))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(std::panic::catch_unwind(|| tmpdir.child("a/../../escape")).is_err());
+}
+
+fn test_open_in() {
+    use std::io::{ErrorKind, Write};
+    use tempfile::OpenInOptions;
+
+    let tmpdir = TempDir::new().unwrap();
+    let mut options = OpenInOptions::new();
+    options.write(true).create_new(true);
+
+    let mut file = tmpdir.open_in("a", &options).unwrap();
+    file.write_all(b"abcde").unwrap();
+    assert_eq!(fs::read(tmpdir.child("a")).unwrap(), b"abcde");
+
+    let err = tmpdir.open_in("a", &options).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    let err = tmpdir.open_in("../a", &options).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = tmpdir
+        .open_in("c", OpenInOptions::new().read(true).create(true))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let mut file = tmpdir
+        .open_in("a", OpenInOptions::new().append(true))
+        .unwrap();
+    file.write_all(b"fgh").unwrap();
+    assert_eq!(fs::read(tmpdir.child("a")).unwrap(), b"abcdefgh");
+
+    // Files are created in the original directory, even if it has been moved.
+    #[cfg(unix)]
+    {
+        let moved = tmpdir.path().with_extension("moved");
+        fs::rename(tmpdir.path(), &moved).unwrap();
+        tmpdir.open_in("b", &options).unwrap();
+        assert!(moved.join("b").exists());
+        fs::rename(&moved, tmpdir.path()).unwrap();
+    }
+
+    // Symbolic links out of the directory aren't followed.
+    #[cfg(unix)]
+    {
+        let outside = TempDir::new().unwrap();
+        fs::write(outside.child("f"), b"outside").unwrap();
+        std::os::unix::fs::symlink(outside.path(), tmpdir.child("dir")).unwrap();
+        std::os::unix::fs::symlink(outside.child("f"), tmpdir.child("link")).unwrap();
+        let read = OpenInOptions::new().read(true).clone();
+        tmpdir.open_in("dir/f", &read).unwrap_err();
+        tmpdir.open_in("link", &read).unwrap_err();
+        tmpdir.open_in("dir/g", &options).unwrap_err();
+        assert!(!outside.child("g").exists());
+
+        fs::create_dir(tmpdir.child("sub")).unwrap();
+        tmpdir.open_in("./sub/c", &options).unwrap();
+        assert!(tmpdir.child("sub/c").exists());
+    }
+}
+
+fn test_persist() {
+    let parent = TempDir::new().unwrap();
+    let final_path = parent.path().join("published");
+
+    let tmpdir = TempDir::new_in(parent.path()).unwrap();
+    let old_path = tmpdir.path().to_owned();
+    fs::write(tmpdir.path().join("file"), b"abcde").unwrap();
+    tmpdir.persist(&final_path).unwrap();
+    assert!(!old_path.exists());
+    assert_eq!(fs::read(final_path.join("file")).unwrap(), b"abcde");
+
+    // Refuse to replace a non-empty directory, returning the temporary directory.
+    let tmpdir = TempDir::new_in(parent.path()).unwrap();
+    let err = tmpdir.persist(&final_path).unwrap_err();
+    let tmpdir = err.dir;
+    assert!(tmpdir.path().exists());
+    let path = tmpdir.path().to_owned();
+    drop(tmpdir);
+    assert!(!path.exists());
+}
+
+fn test_copy_from() {
+    let template = TempDir::new().unwrap();
+    fs::write(template.path().join("a"), b"abcde").unwrap();
+    fs::create_dir_all(template.path().join("b/c")).unwrap();
+    fs::write(template.path().join("b/c/d"), b"fghij").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(template.path().join("a"), fs::Permissions::from_mode(0o751)).unwrap();
+        std::os::unix::fs::symlink("a", template.path().join("link")).unwrap();
+    }
+
+    let tmpdir = TempDir::new().unwrap();
+    tmpdir.copy_from(template.path()).unwrap();
+    assert_eq!(fs::read(tmpdir.child("a")).unwrap(), b"abcde");
+    assert_eq!(fs::read(tmpdir.child("b/c/d")).unwrap(), b"fghij");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(tmpdir.child("a"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o751);
+        assert_eq!(fs::read_link(tmpdir.child("link")).unwrap(), Path::new("a"));
+    }
+
+    // Copying again replaces the existing entries.
+    tmpdir.copy_from(template.path()).unwrap();
+    assert_eq!(fs::read(tmpdir.child("b/c/d")).unwrap(), b"fghij");
+
+    // Symbolic links at the destination are replaced, not written through.
+    #[cfg(unix)]
+    {
+        let outside = TempDir::new().unwrap();
+        fs::write(outside.child("a"), b"outside").unwrap();
+        let tmpdir = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.child("a"), tmpdir.child("a")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), tmpdir.child("b")).unwrap();
+        tmpdir.copy_from(template.path()).unwrap();
+        assert_eq!(fs::read(outside.child("a")).unwrap(), b"outside");
+        assert!(!outside.child("c").exists());
+        assert_eq!(fs::read(tmpdir.child("a")).unwrap(), b"abcde");
+        assert!(fs::symlink_metadata(tmpdir.child("b")).unwrap().is_dir());
+        assert_eq!(fs::read(tmpdir.child("b/c/d")).unwrap(), b"fghij");
+    }
+}
+
+fn test_lifecycle_hooks() {
+    use std::io;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    static CREATED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+    static CLEANED: Mutex<Vec<(PathBuf, bool)>> = Mutex::new(Vec::new());
+
+    fn created(path: &Path) {
+        CREATED.lock().unwrap().push(path.to_owned());
+    }
+
+    fn cleaned(path: &Path, result: io::Result<()>) {
+        CLEANED
+            .lock()
+            .unwrap()
+            .push((path.to_owned(), result.is_ok()));
+    }
+
+    let mut builder = Builder::new();
+    builder.on_create(created).on_cleanup(cleaned);
+
+    let tmpdir = builder.tempdir().unwrap();
+    let path = tmpdir.path().to_owned();
+    assert_eq!(CREATED.lock().unwrap()[..], [path.as_path()]);
+    drop(tmpdir);
+    assert!(!path.exists());
+    assert_eq!(*CLEANED.lock().unwrap(), [(path.clone(), true)]);
+
+    let tmpdir = builder.tempdir().unwrap();
+    let path2 = tmpdir.path().to_owned();
+    tmpdir.close().unwrap();
+    assert_eq!(CREATED.lock().unwrap().len(), 2);
+    assert_eq!(*CLEANED.lock().unwrap(), [(path, true), (path2, true)]);
+}
+
+fn test_try_cleanup() {
+    let mut tmpdir = TempDir::new().unwrap();
+    let path = tmpdir.path().to_owned();
+    fs::write(tmpdir.path().join("file"), b"abcde").unwrap();
+    tmpdir.try_cleanup().unwrap();
+    assert!(!path.exists());
+    assert_eq!(tmpdir.path(), Path::new(""));
+    // Re-enabling cleanup doesn't resurrect the old path.
+    tmpdir.disable_cleanup(false);
+    fs::create_dir(&path).unwrap();
+    drop(tmpdir);
+    assert!(path.exists());
+    fs::remove_dir(&path).unwrap();
+
+    // Kept directories are left alone.
+    let mut tmpdir = TempDir::new().unwrap();
+    tmpdir.disable_cleanup(true);
+    tmpdir.try_cleanup().unwrap();
+    assert!(tmpdir.path().exists());
+    fs::remove_dir(tmpdir.path()).unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut tmpdir = TempDir::new().unwrap();
+        let locked = tmpdir.create_child_dir("locked").unwrap();
+        fs::write(locked.join("a"), b"").unwrap();
+        fs::write(locked.join("b"), b"").unwrap();
+        fs::write(tmpdir.path().join("unlocked"), b"").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+
+        // Privileged users can delete from read-only directories.
+        if fs::write(locked.join("probe"), b"").is_ok() {
+            return;
+        }
+
+        let err = tmpdir.try_cleanup().unwrap_err();
+        let mut failed: Vec<_> = err.failures().iter().map(|(p, _)| p.clone()).collect();
+        failed.sort();
+        assert_eq!(failed, [locked.join("a"), locked.join("b")]);
+        assert!(!tmpdir.path().join("unlocked").exists());
+
+        // Fix the problem and try again.
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        let path = tmpdir.path().to_owned();
+        tmpdir.try_cleanup().unwrap();
+        assert!(!path.exists());
+    }
+}
+
+fn test_close_with_retries() {
+    let tmpdir = TempDir::new().unwrap();
+    let path = tmpdir.path().to_owned();
+    fs::write(tmpdir.path().join("file"), b"abcde").unwrap();
+    tmpdir
+        .close_with_retries(3, Duration::from_millis(1))
+        .unwrap();
+    assert!(!path.exists());
+
+    // Errors that won't go away on their own are returned without retrying.
+    let tmpdir = TempDir::new().unwrap();
+    let path = tmpdir.path().to_owned();
+    fs::remove_dir(&path).unwrap();
+    let start = Instant::now();
+    let err = tmpdir
+        .close_with_retries(10, Duration::from_secs(1))
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+fn test_background_cleanup() {
+    let tmpdir = Builder::new().background_cleanup(true).tempdir().unwrap();
+    let path = tmpdir.path().to_owned();
+    for i in 0..100 {
+        fs::write(tmpdir.path().join(i.to_string()), b"abcde").unwrap();
+    }
+    drop(tmpdir);
+
+    let start = Instant::now();
+    while path.exists() {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "directory wasn't deleted in the background"
+        );
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    // Explicitly closing still deletes synchronously.
+    let mut tmpdir = TempDir::new().unwrap();
+    tmpdir.background_cleanup(true);
+    let path = tmpdir.path().to_owned();
+    tmpdir.close().unwrap();
+    assert!(!path.exists());
+}
+
+fn test_temp_dir_path() {
+    // Adopt an existing directory.
+    fs::create_dir_all("adopted/sub").unwrap();
+    fs::write("adopted/sub/file", b"").unwrap();
+    let adopted = TempDirPath::from_path("adopted");
+    assert_eq!(adopted.path(), Path::new("adopted"));
+    assert!(adopted.join("sub").exists());
+    adopted.close().unwrap();
+    assert!(!Path::new("adopted").exists());
+
+    // Split the guard from a `TempDir`.
+    let dir = TempDir::new().unwrap().into_temp_dir_path();
+    let path = dir.to_path_buf();
+    assert!(path.is_dir());
+    drop(dir);
+    assert!(!path.exists());
+
+    let dir = TempDirPath::from(TempDir::new().unwrap());
+    let path = dir.keep();
+    assert!(path.is_dir());
+    fs::remove_dir(path).unwrap();
+}
+
+fn test_from_existing() {
+    fs::create_dir_all("existing/sub").unwrap();
+    let dir = TempDir::from_existing("existing").unwrap();
+    assert!(dir.path().is_absolute());
+    assert_eq!(
+        dir.path(),
+        std::env::current_dir().unwrap().join("existing")
+    );
+    drop(dir);
+    assert!(!Path::new("existing").exists());
+
+    assert!(TempDir::from_existing("missing").is_err());
+    fs::write("not-a-dir", b"").unwrap();
+    assert!(TempDir::from_existing("not-a-dir").is_err());
+    fs::remove_file("not-a-dir").unwrap();
+
+    fs::create_dir_all("nonempty/sub").unwrap();
+    assert!(TempDir::from_existing_empty("nonempty").is_err());
+    assert!(Path::new("nonempty/sub").exists());
+    fs::remove_dir("nonempty/sub").unwrap();
+    TempDir::from_existing_empty("nonempty")
+        .unwrap()
+        .close()
+        .unwrap();
+    assert!(!Path::new("nonempty").exists());
+
+    #[cfg(unix)]
+    {
+        fs::create_dir("owned").unwrap();
+        TempDir::from_existing_owned("owned")
+            .unwrap()
+            .close()
+            .unwrap();
+        assert!(!Path::new("owned").exists());
+    }
+}
+
+fn test_leak() {
+    let path = TempDir::new_in(".").unwrap().leak();
+    assert!(path.is_dir());
+    fs::remove_dir(path).unwrap();
+}
+
+fn test_subdirs() {
+    let dir = Builder::new()
+        .subdirs(["in", "out", "cache/objects", "cache"])
+        .tempdir_in(".")
+        .unwrap();
+    for subdir in ["in", "out", "cache", "cache/objects"] {
+        assert!(dir.path().join(subdir).is_dir());
+    }
+
+    // Paths escaping the temporary directory are rejected before anything is created.
+    for subdir in ["../escape", "/absolute", ""] {
+        let err = Builder::new()
+            .prefix("rejected")
+            .subdirs([subdir])
+            .tempdir_in(".")
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    assert!(!fs::read_dir(".").unwrap().any(|entry| entry
+        .unwrap()
+        .file_name()
+        .to_string_lossy()
+        .starts_with("rejected")));
+}
+
+fn test_total_size() {
+    let dir = TempDir::new_in(".").unwrap();
+    assert_eq!(dir.total_size().unwrap(), Default::default());
+
+    fs::create_dir_all(dir.path().join("a/b")).unwrap();
+    fs::write(dir.path().join("one"), b"1").unwrap();
+    fs::write(dir.path().join("a/b/three"), b"333").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("/", dir.path().join("a/link")).unwrap();
+
+    let usage = dir.total_size().unwrap();
+    if cfg!(unix) {
+        assert_eq!(usage.files(), 3);
+        assert_eq!(usage.bytes(), 5);
+    } else {
+        assert_eq!(usage.files(), 2);
+        assert_eq!(usage.bytes(), 4);
+    }
+
+    // The original directory is measured, even if it has been replaced.
+    #[cfg(unix)]
+    {
+        let moved = dir.path().with_extension("moved");
+        fs::rename(dir.path(), &moved).unwrap();
+        fs::create_dir(dir.path()).unwrap();
+        fs::write(dir.path().join("decoy"), b"decoy").unwrap();
+        assert_eq!(dir.total_size().unwrap(), usage);
+        fs::remove_dir_all(dir.path()).unwrap();
+        fs::rename(&moved, dir.path()).unwrap();
+    }
+}
+
+fn test_close_empty() {
+    let dir = TempDir::new_in(".").unwrap();
+    let path = dir.path().to_owned();
+    fs::write(path.join("leftover"), b"").unwrap();
+    let err = dir.close_empty().unwrap_err();
+    let cleanup = err
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<tempfile::CleanupError>()
+        .unwrap();
+    assert_eq!(cleanup.failures().len(), 1);
+    assert_eq!(cleanup.failures()[0].0, path.join("leftover"));
+    assert!(path.join("leftover").exists());
+    fs::remove_dir_all(&path).unwrap();
+
+    let dir = TempDir::new_in(".").unwrap();
+    let path = dir.path().to_owned();
+    dir.close_empty().unwrap();
+    assert!(!path.exists());
+
+    // Non-recursive cleanup on drop.
+    let dir = Builder::new()
+        .recursive_cleanup(false)
+        .tempdir_in(".")
+        .unwrap();
+    let path = dir.path().to_owned();
+    fs::write(path.join("leftover"), b"").unwrap();
+    drop(dir);
+    assert!(path.join("leftover").exists());
+    fs::remove_file(path.join("leftover")).unwrap();
+
+    let dir = Builder::new()
+        .recursive_cleanup(false)
+        .tempdir_in(".")
+        .unwrap();
+    let path = dir.path().to_owned();
+    drop(dir);
+    assert!(!path.exists());
+}
+
+fn test_seal() {
+    let mut dir = TempDir::new_in(".").unwrap();
+    let path = dir.path().to_owned();
+    fs::create_dir(path.join("sub")).unwrap();
+    fs::write(path.join("sub/file"), b"fixture").unwrap();
+
+    dir.seal().unwrap();
+    assert!(fs::metadata(path.join("sub/file"))
+        .unwrap()
+        .permissions()
+        .readonly());
+    #[cfg(unix)]
+    assert!(fs::metadata(path.join("sub"))
+        .unwrap()
+        .permissions()
+        .readonly());
+
+    dir.unseal().unwrap();
+    fs::write(path.join("sub/file"), b"modified").unwrap();
+
+    dir.seal().unwrap();
+    dir.close().unwrap();
+    assert!(!path.exists());
+
+    // Sealed directories are also cleaned up on drop.
+    let mut dir = TempDir::new_in(".").unwrap();
+    let path = dir.path().to_owned();
+    fs::write(path.join("file"), b"fixture").unwrap();
+    dir.seal().unwrap();
+    drop(dir);
+    assert!(!path.exists());
+}
+
+fn test_make_dir() {
+    let mut attempts = 0;
+    let (dir, value) = Builder::new()
+        .prefix("made")
+        .make_dir_in(".", |path| {
+            attempts += 1;
+            if attempts == 1 {
+                return Err(std::io::ErrorKind::AlreadyExists.into());
+            }
+            fs::create_dir(path)?;
+            fs::write(path.join("file"), b"")?;
+            Ok(42)
+        })
+        .unwrap();
+    assert_eq!(attempts, 2);
+    assert_eq!(value, 42);
+    assert!(dir.path().is_absolute());
+    assert!(dir.path().join("file").exists());
+    let path = dir.path().to_owned();
+    dir.close().unwrap();
+    assert!(!path.exists());
+
+    let err = Builder::new()
+        .make_dir_in(".", |_| {
+            Err::<(), _>(std::io::ErrorKind::PermissionDenied.into())
+        })
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+    // A directory created by a failing closure is deleted.
+    let mut created = None;
+    let err = Builder::new()
+        .make_dir_in(".", |path| {
+            created = Some(path.to_owned());
+            fs::create_dir(path)?;
+            fs::write(path.join("file"), b"")?;
+            Err::<(), _>(std::io::ErrorKind::PermissionDenied.into())
+        })
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+    assert!(!created.unwrap().exists());
+}
+
+fn test_with_tempdir() {
+    let mut path = None;
+    let result = tempfile::with_tempdir(|dir| {
+        assert!(dir.is_dir());
+        path = Some(dir.to_owned());
+        42
+    })
+    .unwrap();
+    assert_eq!(result, 42);
+    assert!(!path.unwrap().exists());
+
+    // Cleaned up on panic.
+    let (tx, rx) = channel();
+    let result = std::panic::catch_unwind(move || {
+        tempfile::with_tempdir(|dir| {
+            tx.send(dir.to_owned()).unwrap();
+            panic!("oops");
+        })
+    });
+    assert!(result.is_err());
+    assert!(!rx.recv().unwrap().exists());
+
+    // Cleaned up on failure.
+    let mut path = None;
+    let err = tempfile::try_with_tempdir(|dir| {
+        path = Some(dir.to_owned());
+        fs::read(dir.join("missing"))
+    })
+    .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert!(!path.unwrap().exists());
+}
+
+fn test_tempdir_macro() {
+    let dir = tempfile::tempdir!().unwrap();
+    let name = dir.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("tempdir-test_tempdir_macro-"), "{}", name);
+    assert!(dir.path().starts_with(tempfile::env::temp_dir()));
+
+    // Closures are attributed to the enclosing function.
+    let create = || tempfile::tempdir!(".").unwrap();
+    let dir = create();
+    let name = dir.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("tempdir-test_tempdir_macro-"), "{}", name);
+    assert!(dir.path().starts_with(std::env::current_dir().unwrap()));
+}
+
+fn test_shared() {
+    let dir = TempDir::new().unwrap().shared();
+    let path = dir.to_path_buf();
+    let clone = dir.clone();
+    assert_eq!(dir.clone_count(), 2);
+    thread::spawn(move || fs::write(clone.join("file"), b"").unwrap())
+        .join()
+        .unwrap();
+    assert_eq!(dir.clone_count(), 1);
+    assert!(path.join("file").exists());
+
+    // Closing a clone leaves the directory alone.
+    let clone = dir.clone();
+    clone.close().unwrap();
+    assert!(path.is_dir());
+
+    // The last clone can be unwrapped.
+    let other = dir.clone();
+    let dir = dir.try_unwrap().unwrap_err();
+    drop(other);
+    let dir = SharedTempDir::from(dir.try_unwrap().unwrap());
+    dir.close().unwrap();
+    assert!(!path.exists());
+
+    // When clones are closed concurrently, exactly one of them removes the directory (and reports
+    // that it has already been deleted).
+    for _ in 0..16 {
+        let dir = TempDir::new().unwrap().shared();
+        fs::remove_dir(dir.path()).unwrap();
+        let clones: Vec<_> = (0..4).map(|_| dir.clone()).collect();
+        drop(dir);
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(clones.len()));
+        let closers: Vec<_> = clones
+            .into_iter()
+            .map(|dir| {
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    dir.close().is_err()
+                })
+            })
+            .collect();
+        let errors = closers
+            .into_iter()
+            .filter_map(|closer| closer.join().unwrap().then_some(()))
+            .count();
+        assert_eq!(errors, 1);
+    }
+}
+
+#[cfg(unix)]
+fn test_cleanup_after_replace() {
+    let tmpdir = TempDir::new_in(".").unwrap();
+    let path = tmpdir.path().to_owned();
+    fs::create_dir(path.join("sub")).unwrap();
+    fs::write(path.join("sub/file"), b"").unwrap();
+
+    // Move the directory and put a symlink to a victim in its place.
+    fs::rename(&path, "moved").unwrap();
+    fs::create_dir("victim").unwrap();
+    fs::write("victim/file", b"").unwrap();
+    std::os::unix::fs::symlink(fs::canonicalize("victim").unwrap(), &path).unwrap();
+
+    let result = tmpdir.close();
+    assert!(Path::new("victim/file").exists());
+    assert!(!Path::new("moved/sub").exists());
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        // The directory is found at its new location.
+        result.unwrap();
+        assert!(!Path::new("moved").exists());
+    } else {
+        result.unwrap_err();
+    }
+}
+
+#[cfg(unix)]
+fn test_as_dir_fd() {
+    let tmpdir = TempDir::new().unwrap();
+    let fd = tmpdir.as_dir_fd().expect("directory handle");
+    let dir = fs::File::from(fd.try_clone_to_owned().unwrap());
+    assert!(dir.metadata().unwrap().is_dir());
+}
+
+#[cfg(unix)]
+fn test_file_permissions_for_dir() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // File permissions are made usable for directories.
+    let mut builder = Builder::new();
+    builder.permissions(fs::Permissions::from_mode(0o600));
+    let tmpdir = builder.tempdir().unwrap();
+    let mode = tmpdir.path().metadata().unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o700);
+    builder.tempfile_in(&tmpdir).unwrap();
+
+    // Directory permissions are used as-is.
+    builder.dir_permissions(fs::Permissions::from_mode(0o600));
+    let tmpdir = builder.tempdir().unwrap();
+    let mode = tmpdir.path().metadata().unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    fs::set_permissions(tmpdir.path(), fs::Permissions::from_mode(0o700)).unwrap();
 }
 
 #[test]
@@ -188,4 +877,29 @@ fn main() {
     in_tmpdir(dont_double_panic);
     in_tmpdir(pass_as_asref_path);
     in_tmpdir(test_keep);
+    in_tmpdir(test_child);
+    in_tmpdir(test_open_in);
+    in_tmpdir(test_persist);
+    in_tmpdir(test_copy_from);
+    in_tmpdir(test_lifecycle_hooks);
+    in_tmpdir(test_try_cleanup);
+    in_tmpdir(test_close_with_retries);
+    in_tmpdir(test_background_cleanup);
+    in_tmpdir(test_temp_dir_path);
+    in_tmpdir(test_from_existing);
+    in_tmpdir(test_leak);
+    in_tmpdir(test_subdirs);
+    in_tmpdir(test_total_size);
+    in_tmpdir(test_close_empty);
+    in_tmpdir(test_seal);
+    in_tmpdir(test_make_dir);
+    in_tmpdir(test_with_tempdir);
+    in_tmpdir(test_tempdir_macro);
+    in_tmpdir(test_shared);
+    #[cfg(unix)]
+    in_tmpdir(test_cleanup_after_replace);
+    #[cfg(unix)]
+    in_tmpdir(test_as_dir_fd);
+    #[cfg(unix)]
+    in_tmpdir(test_file_permissions_for_dir);
 }
@@ -66,3 +66,92 @@ fn test_pathological_cleaner() {
     drop(tx);
     cleaner_thread.join().expect("The cleaner thread failed");
 }
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_memfile() {
+    let mut file = tempfile::memfile().unwrap();
+    write!(file, "abcde").unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+}
+
+#[test]
+fn test_prefer_memory() {
+    let mut file = tempfile::Builder::new()
+        .prefer_memory(true)
+        .anonymous_tempfile()
+        .unwrap();
+    write!(file, "abcde").unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+}
+
+#[test]
+fn test_shared() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let mut files = tempfile::tempfile_shared_in(&tmpdir, 3).unwrap();
+    assert_eq!(files.len(), 3);
+    assert_eq!(fs::read_dir(&tmpdir).unwrap().count(), 0);
+
+    files[0].write_all(b"abcde").unwrap();
+    files[1].seek(SeekFrom::Start(2)).unwrap();
+    let mut buf = String::new();
+    files[1].read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "cde");
+    buf.clear();
+    files[2].read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+
+    assert!(tempfile::tempfile_shared_in(&tmpdir, 0).unwrap().is_empty());
+}
+
+#[test]
+fn test_with_tempfile() {
+    let mut path = None;
+    let contents = tempfile::with_tempfile(|file, p| {
+        file.write_all(b"abcde").unwrap();
+        path = Some(p.to_owned());
+        fs::read(p).unwrap()
+    })
+    .unwrap();
+    assert_eq!(contents, b"abcde");
+    assert!(!path.unwrap().exists());
+
+    // Cleaned up on panic.
+    let (tx, rx) = std::sync::mpsc::channel();
+    let result = std::panic::catch_unwind(move || {
+        tempfile::with_tempfile(|_, p| {
+            tx.send(p.to_owned()).unwrap();
+            panic!("oops");
+        })
+    });
+    assert!(result.is_err());
+    assert!(!rx.recv().unwrap().exists());
+
+    // Cleaned up on failure.
+    let mut path = None;
+    let err = tempfile::try_with_tempfile(|_, p| {
+        path = Some(p.to_owned());
+        Err::<(), _>(std::io::Error::new(std::io::ErrorKind::Other, "oops"))
+    })
+    .unwrap_err();
+    assert_eq!(err.to_string(), "oops");
+    assert!(!path.unwrap().exists());
+}
+
+#[test]
+fn test_sized() {
+    let mut file = tempfile::tempfile_sized(8192).unwrap();
+    assert_eq!(file.metadata().unwrap().len(), 8192);
+    file.write_all(b"abcde").unwrap();
+    file.set_len(5).unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+}
@@ -0,0 +1,182 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "tokio")]
+
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use tempfile::tokio::NamedTempFile;
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn test_basic() {
+    block_on(async {
+        let mut file = NamedTempFile::new().await?;
+        file.write_all(b"abcde").await?;
+        file.seek(io::SeekFrom::Start(0)).await?;
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).await?;
+        assert_eq!("abcde", buf);
+
+        let mut other = file.reopen().await?;
+        buf.clear();
+        other.read_to_string(&mut buf).await?;
+        assert_eq!("abcde", buf);
+
+        let path = file.path().to_owned();
+        assert!(path.exists());
+        file.close().await?;
+        assert!(!path.exists());
+        Ok::<(), io::Error>(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_drop() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = block_on(async {
+        let file = NamedTempFile::new_in(tmpdir.path()).await.unwrap();
+        file.path().to_owned()
+    });
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_persist() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let persist_path = tmpdir.path().join("persisted");
+    block_on(async {
+        let mut file = NamedTempFile::new_in(tmpdir.path()).await?;
+        file.write_all(b"abcde").await?;
+        let old_path = file.path().to_owned();
+        file.persist(&persist_path).await?;
+        assert!(!old_path.exists());
+
+        let file = NamedTempFile::new_in(tmpdir.path()).await?;
+        let err = file.persist_noclobber(&persist_path).await.unwrap_err();
+        assert_eq!(err.error.kind(), io::ErrorKind::AlreadyExists);
+        Ok::<(), io::Error>(())
+    })
+    .unwrap();
+    assert_eq!(std::fs::read(&persist_path).unwrap(), b"abcde");
+}
+
+#[test]
+fn test_tempdir() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let (closed, dropped) = block_on(async {
+        let dir = tempfile::tokio::TempDir::new_in(tmpdir.path()).await?;
+        let closed = dir.path().to_owned();
+        tokio::fs::create_dir(closed.join("sub")).await?;
+        tokio::fs::write(closed.join("sub").join("file"), b"abcde").await?;
+        dir.close().await?;
+
+        let dir = tempfile::tokio::TempDir::new_in(tmpdir.path()).await?;
+        let dropped = dir.path().to_owned();
+        tokio::fs::write(dropped.join("file"), b"abcde").await?;
+        Ok::<_, io::Error>((closed, dropped))
+    })
+    .unwrap();
+    assert!(!closed.exists());
+    assert!(!dropped.exists());
+}
+
+async fn create_new(path: PathBuf) -> io::Result<tokio::fs::File> {
+    tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .await
+}
+
+#[test]
+fn test_make_async() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    block_on(async {
+        let file = tempfile::Builder::new()
+            .prefix("async")
+            .make_in_async(tmpdir.path(), |path| create_new(path.to_owned()))
+            .await?;
+        assert!(file.path().starts_with(tmpdir.path()));
+        assert!(file.path().exists());
+
+        let err = tempfile::Builder::new()
+            .rand_bytes(0)
+            .prefix(file.path().file_name().unwrap())
+            .make_in_async(tmpdir.path(), |path| create_new(path.to_owned()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        let path = file.path().to_owned();
+        drop(file);
+        assert!(!path.exists());
+        Ok::<(), io::Error>(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_conversions() {
+    block_on(async {
+        let mut file = tempfile::NamedTempFile::new()?.into_async();
+        file.write_all(b"abcde").await?;
+        let path = file.path().to_owned();
+
+        let mut file = tempfile::NamedTempFile::from_async(file).await;
+        let mut buf = String::new();
+        std::io::Seek::rewind(&mut file)?;
+        std::io::Read::read_to_string(&mut file, &mut buf)?;
+        assert_eq!("abcde", buf);
+
+        let file: NamedTempFile = file.into();
+        assert_eq!(file.path(), path);
+        let file = file.into_std().await;
+        assert_eq!(file.path(), path);
+        drop(file);
+        assert!(!path.exists());
+        Ok::<(), io::Error>(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_spooled() {
+    use tempfile::tokio::SpooledTempFile;
+
+    block_on(async {
+        let mut file = SpooledTempFile::new(10);
+        file.write_all(b"abcde").await.unwrap();
+        assert!(!file.is_rolled());
+        assert_eq!(file.as_slice(), Some(&b"abcde"[..]));
+
+        // Rolls over on the blocking thread pool.
+        file.write_all(b"fghijklmno").await.unwrap();
+        assert!(file.is_rolled());
+        assert_eq!(file.as_slice(), None);
+
+        file.seek(io::SeekFrom::Start(3)).await.unwrap();
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "defghijklmno");
+
+        // Explicit rollover keeps the position.
+        let mut file = SpooledTempFile::new(10);
+        file.write_all(b"abc").await.unwrap();
+        file.seek(io::SeekFrom::Start(1)).await.unwrap();
+        file.roll().await.unwrap();
+        assert!(file.is_rolled());
+        let mut buf = String::new();
+        file.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "bc");
+    });
+}
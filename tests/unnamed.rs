@@ -0,0 +1,65 @@
+#![deny(rust_2018_idioms)]
+#![cfg(target_os = "linux")]
+
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use tempfile::{tempdir, UnnamedTempFile};
+
+#[test]
+fn test_link_at() {
+    let dir = tempdir().unwrap();
+    let mut file = match UnnamedTempFile::new_in(dir.path()) {
+        Ok(file) => file,
+        // O_TMPFILE isn't supported everywhere.
+        Err(_) => return,
+    };
+    // Nothing is visible until the file is linked.
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    file.write_all(b"abcde").unwrap();
+
+    let path = dir.path().join("linked");
+    file.link_at(&path).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"abcde");
+    // Don't replace existing files.
+    assert_eq!(
+        file.link_at(&path).unwrap_err().kind(),
+        std::io::ErrorKind::AlreadyExists
+    );
+}
+
+#[test]
+fn test_persist() {
+    let dir = tempdir().unwrap();
+    let mut file = match UnnamedTempFile::new_in(dir.path()) {
+        Ok(file) => file,
+        Err(_) => return,
+    };
+    file.write_all(b"abcde").unwrap();
+
+    let path = dir.path().join("persisted");
+    fs::write(&path, b"old").unwrap();
+    let mut file = file.persist(&path).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"abcde");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    let mut buf = String::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+
+    // The file is returned if it can't be persisted.
+    let mut file = UnnamedTempFile::new_in(dir.path()).unwrap();
+    file.write_all(b"fghij").unwrap();
+    let err = file
+        .persist(dir.path().join("missing").join("file"))
+        .unwrap_err();
+    assert_eq!(err.error.kind(), std::io::ErrorKind::NotFound);
+    let mut file = err.file;
+    let mut buf = String::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "fghij");
+    file.persist(dir.path().join("retried")).unwrap();
+    assert_eq!(fs::read(dir.path().join("retried")).unwrap(), b"fghij");
+}